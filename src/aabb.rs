@@ -0,0 +1,71 @@
+use crate::ray::Ray;
+use crate::vec3;
+
+// 轴对齐包围盒
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: vec3::Point3,
+    pub max: vec3::Point3,
+}
+
+impl Aabb {
+    pub fn new(min: vec3::Point3, max: vec3::Point3) -> Self {
+        Aabb { min, max }
+    }
+
+    // 不包含任何点的包围盒
+    pub fn empty() -> Self {
+        Aabb {
+            min: vec3::Point3::fill(f64::INFINITY),
+            max: vec3::Point3::fill(f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn include(&self, p: vec3::Point3) -> Aabb {
+        Aabb::new(self.min.min(p), self.max.max(p))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn centroid(&self) -> vec3::Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x() > d.y() && d.x() > d.z() {
+            0
+        } else if d.y() > d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        if d.x() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    // slab 测试，inv_direction 为光线方向各分量的倒数
+    pub fn hit(&self, r: &Ray, inv_direction: vec3::Vec3, t_min: f64, t_max: f64) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - r.origin[axis]) * inv_direction[axis];
+            let t1 = (self.max[axis] - r.origin[axis]) * inv_direction[axis];
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -1,6 +1,111 @@
 use crate::ray::Ray;
+use crate::sampler::{self, Sampler};
+use crate::scene::CameraDescription;
 use crate::vec3;
 
+// 根据视口上的坐标 (s, t) ∈ [0, 1]² 生成光线，镜头采样等随机数取自 sampler
+pub trait RayGenerator: Send + Sync {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray;
+    // 导出场景时的描述，无法描述的相机返回 None
+    fn describe(&self) -> Option<CameraDescription> {
+        None
+    }
+    // 成像模型，用于从场景中的点连接到相机，不支持时返回 None
+    fn viewport(&self) -> Option<Viewport> {
+        None
+    }
+}
+
+// 薄透镜相机：光线从以 origin 为中心、半径为 lens_radius 的镜头上的点射向对焦平面上的
+// lower_left_corner + s * horizontal + t * vertical，镜头平行于对焦平面，
+// horizontal 与 vertical 互相垂直，lens_radius 为 0 时为针孔相机
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub origin: vec3::Point3,
+    pub lower_left_corner: vec3::Point3,
+    pub horizontal: vec3::Vec3,
+    pub vertical: vec3::Vec3,
+    pub lens_radius: f64,
+}
+
+impl Viewport {
+    // 指向相机后方的单位法向量，以及镜头到对焦平面的距离
+    fn axis(&self) -> (vec3::Vec3, f64) {
+        let mut w = self.horizontal.cross(self.vertical).unit_vector();
+        let mut distance = (self.origin - self.lower_left_corner).dot(w);
+        if distance < 0.0 {
+            w = -w;
+            distance = -distance;
+        }
+        (w, distance)
+    }
+
+    // 在镜头上均匀采样一点
+    pub fn sample_lens(&self, u: (f64, f64)) -> vec3::Point3 {
+        let rd = self.lens_radius * sampler::in_unit_disk(u);
+        self.origin + rd.x() * self.horizontal.unit_vector() + rd.y() * self.vertical.unit_vector()
+    }
+
+    // 从镜头上的点 lens 看向 p 的光线在视口上的坐标 (s, t)，p 在相机后方时返回 None
+    pub fn project(&self, lens: vec3::Point3, p: vec3::Point3) -> Option<(f64, f64)> {
+        let (w, distance) = self.axis();
+        let direction = p - lens;
+        let depth = -direction.dot(w);
+        if depth <= 0.0 {
+            return None;
+        }
+        let offset = lens + distance / depth * direction - self.lower_left_corner;
+        Some((
+            offset.dot(self.horizontal) / self.horizontal.length_squared(),
+            offset.dot(self.vertical) / self.vertical.length_squared(),
+        ))
+    }
+
+    // (s, t) 在 [0, 1]² 上均匀分布时，从镜头上的点发出的方向对立体角的概率密度，
+    // direction 与光轴夹角的余弦为 cos_theta
+    pub fn pdf_direction(&self, cos_theta: f64) -> f64 {
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        let (_, distance) = self.axis();
+        distance * distance
+            / (self.horizontal.length() * self.vertical.length() * cos_theta.powi(3))
+    }
+
+    // 光线与光轴夹角的余弦
+    pub fn cos_theta(&self, direction: vec3::Vec3) -> f64 {
+        let (w, _) = self.axis();
+        -direction.unit_vector().dot(w)
+    }
+}
+
+// 由视口反推相机参数，look_at 取视口中心，即对焦平面上的点
+// 描述中的相机总是右手系，镜像的视口无法描述
+fn describe_viewport(
+    origin: vec3::Point3,
+    lower_left_corner: vec3::Point3,
+    horizontal: vec3::Vec3,
+    vertical: vec3::Vec3,
+    lens_radius: f64,
+) -> Option<CameraDescription> {
+    let look_at = lower_left_corner + horizontal / 2.0 + vertical / 2.0;
+    let focus_distance = (look_at - origin).length();
+    let vfov = 2.0 * (vertical.length() / 2.0 / focus_distance).atan();
+    if horizontal.dot(vertical.cross(origin - look_at)) < 0.0 {
+        return None;
+    }
+
+    Some(CameraDescription {
+        look_from: origin.to_array(),
+        look_at: look_at.to_array(),
+        up: vertical.unit_vector().to_array(),
+        vfov: vfov.to_degrees(),
+        aperture: 2.0 * lens_radius,
+        focus_distance: None,
+        aspect_ratio: Some(horizontal.length() / vertical.length()),
+    })
+}
+
 pub struct Camera {
     pub origin: vec3::Point3,
     pub lower_left_corner: vec3::Point3,
@@ -32,13 +137,39 @@ impl Camera {
             vertical,
         }
     }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new()
+    }
+}
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+impl RayGenerator for Camera {
+    fn get_ray(&self, u: f64, v: f64, _sampler: &mut dyn Sampler) -> Ray {
         Ray::new(
             self.origin,
             self.lower_left_corner + u * self.horizontal + v * self.vertical,
         )
     }
+    fn describe(&self) -> Option<CameraDescription> {
+        describe_viewport(
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            0.0,
+        )
+    }
+    fn viewport(&self) -> Option<Viewport> {
+        Some(Viewport {
+            origin: self.origin,
+            lower_left_corner: self.lower_left_corner,
+            horizontal: self.horizontal,
+            vertical: self.vertical,
+            lens_radius: 0.0,
+        })
+    }
 }
 
 pub struct AdjustableFOVCamera {
@@ -74,13 +205,33 @@ impl AdjustableFOVCamera {
             vertical,
         }
     }
+}
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+impl RayGenerator for AdjustableFOVCamera {
+    fn get_ray(&self, u: f64, v: f64, _sampler: &mut dyn Sampler) -> Ray {
         Ray::new(
             self.origin,
             self.lower_left_corner + u * self.horizontal + v * self.vertical,
         )
     }
+    fn describe(&self) -> Option<CameraDescription> {
+        describe_viewport(
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            0.0,
+        )
+    }
+    fn viewport(&self) -> Option<Viewport> {
+        Some(Viewport {
+            origin: self.origin,
+            lower_left_corner: self.lower_left_corner,
+            horizontal: self.horizontal,
+            vertical: self.vertical,
+            lens_radius: 0.0,
+        })
+    }
 }
 
 pub struct PositionableCamera {
@@ -119,13 +270,33 @@ impl PositionableCamera {
             vertical,
         }
     }
+}
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
+impl RayGenerator for PositionableCamera {
+    fn get_ray(&self, s: f64, t: f64, _sampler: &mut dyn Sampler) -> Ray {
         Ray::new(
             self.origin,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
         )
     }
+    fn describe(&self) -> Option<CameraDescription> {
+        describe_viewport(
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            0.0,
+        )
+    }
+    fn viewport(&self) -> Option<Viewport> {
+        Some(Viewport {
+            origin: self.origin,
+            lower_left_corner: self.lower_left_corner,
+            horizontal: self.horizontal,
+            vertical: self.vertical,
+            lens_radius: 0.0,
+        })
+    }
 }
 
 pub struct LensCamera {
@@ -148,17 +319,31 @@ impl LensCamera {
         aspect_ratio: f64,
         aperture: f64,
         focus_dist: f64,
+    ) -> Self {
+        let w = (lookfrom - lookat).unit_vector();
+        let u = (vup.cross(w)).unit_vector();
+        let v = w.cross(u);
+
+        LensCamera::from_basis(lookfrom, u, v, w, vfov, aspect_ratio, aperture, focus_dist)
+    }
+
+    // 由相机坐标系直接构造，u 指向图像右方，v 指向上方，相机朝向 -w
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_basis(
+        origin: vec3::Point3,
+        u: vec3::Vec3,
+        v: vec3::Vec3,
+        w: vec3::Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
     ) -> Self {
         let theta = vfov.to_radians();
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
 
-        let w = (lookfrom - lookat).unit_vector();
-        let u = (vup.cross(w)).unit_vector();
-        let v = w.cross(u);
-
-        let origin = lookfrom;
         let horizontal = focus_dist * viewport_width * u;
         let vertical = focus_dist * viewport_height * v;
         let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
@@ -176,9 +361,11 @@ impl LensCamera {
             lens_radius,
         }
     }
+}
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * vec3::Vec3::random_in_unit_disk();
+impl RayGenerator for LensCamera {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * sampler::in_unit_disk(sampler.get_2d());
         let offset = self.u * rd.x() + self.v * rd.y();
 
         Ray::new(
@@ -186,4 +373,22 @@ impl LensCamera {
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
         )
     }
+    fn describe(&self) -> Option<CameraDescription> {
+        describe_viewport(
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            self.lens_radius,
+        )
+    }
+    fn viewport(&self) -> Option<Viewport> {
+        Some(Viewport {
+            origin: self.origin,
+            lower_left_corner: self.lower_left_corner,
+            horizontal: self.horizontal,
+            vertical: self.vertical,
+            lens_radius: self.lens_radius,
+        })
+    }
 }
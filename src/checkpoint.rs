@@ -0,0 +1,201 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::film::{Film, Filter};
+
+const MAGIC: &[u8; 4] = b"RTCP";
+pub const VERSION: u32 = 5;
+
+// 检查点文件格式(小端序)：
+//   magic "RTCP" | version: u32 | width: u64 | height: u64 | samples: u64
+//   version 3 起之后为 seed: u64，更早的版本视为种子 0
+//   之后为 width * height 个像素的数据，从最下方一行开始：
+//   version 1: (r, g, b, weight)，均为 f64
+//   version 2: (r, g, b, weight) 之后加上亮度统计量 (count: u64, mean: f64, m2: f64)
+//   version 4 起像素数据之后为是否有 splat 的标记 u8，为 1 时接着是
+//   width * height 个像素的 splat (r, g, b)，均为 f64
+//   version 5 起种子之后为采样器的名称 (长度: u32, UTF-8) 与创建它时的每像素样本数 u64，
+//   名称为空表示未记录
+pub struct Checkpoint {
+    pub samples: usize,
+    pub seed: u64,
+    pub film: Film,
+    // (采样器的名称, 创建时的每像素样本数)，分层采样的分层方式取决于后者
+    pub sampler: Option<(String, usize)>,
+}
+
+// 采样器名称的长度上限
+const MAX_NAME: u32 = 64;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32<T: Read>(input: &mut T) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<T: Read>(input: &mut T) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+impl Checkpoint {
+    pub fn new(samples: usize, seed: u64, film: Film) -> Self {
+        Checkpoint {
+            samples,
+            seed,
+            film,
+            sampler: None,
+        }
+    }
+
+    pub fn write<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.film.width as u64).to_le_bytes())?;
+        out.write_all(&(self.film.height as u64).to_le_bytes())?;
+        out.write_all(&(self.samples as u64).to_le_bytes())?;
+        out.write_all(&self.seed.to_le_bytes())?;
+        let (name, samples_per_pixel) = self
+            .sampler
+            .as_ref()
+            .map_or(("", 0), |(name, n)| (name.as_str(), *n));
+        out.write_all(&(name.len() as u32).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&(samples_per_pixel as u64).to_le_bytes())?;
+        self.film.write_accumulated(out)
+    }
+
+    // 累积值是按滤波器加权的，恢复时应使用与保存时相同的滤波器。
+    // 图像尺寸与 width、height 不同时返回错误，损坏的文件头不会导致分配过大的胶片
+    pub fn read<T: Read>(
+        input: &mut T,
+        filter: Arc<dyn Filter>,
+        width: usize,
+        height: usize,
+    ) -> io::Result<Checkpoint> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data(String::from("not a checkpoint file")));
+        }
+        let version = read_u32(input)?;
+        if version == 0 || version > VERSION {
+            return Err(invalid_data(format!(
+                "unsupported checkpoint version {}",
+                version
+            )));
+        }
+
+        let size = (read_u64(input)?, read_u64(input)?);
+        if size != (width as u64, height as u64) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "checkpoint is {}x{}, expected {}x{}",
+                    size.0, size.1, width, height
+                ),
+            ));
+        }
+        let samples = read_u64(input)? as usize;
+        let seed = if version >= 3 { read_u64(input)? } else { 0 };
+        let sampler = if version >= 5 {
+            let length = read_u32(input)?;
+            if length > MAX_NAME {
+                return Err(invalid_data(format!(
+                    "sampler name of {} bytes is too long",
+                    length
+                )));
+            }
+            let mut name = vec![0u8; length as usize];
+            input.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| invalid_data(String::from("sampler name is not UTF-8")))?;
+            let samples_per_pixel = read_u64(input)? as usize;
+            Some((name, samples_per_pixel)).filter(|(name, _)| !name.is_empty())
+        } else {
+            None
+        };
+        let mut film = Film::new(width, height, filter);
+        film.read_accumulated(input, version >= 2, version >= 4)?;
+        Ok(Checkpoint {
+            samples,
+            seed,
+            film,
+            sampler,
+        })
+    }
+
+    // 先写入临时文件再重命名，中途崩溃不会破坏已有的检查点
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        {
+            let mut f = BufWriter::new(File::create(&tmp)?);
+            self.write(&mut f)?;
+            f.flush()?;
+        }
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        filter: Arc<dyn Filter>,
+        width: usize,
+        height: usize,
+    ) -> io::Result<Checkpoint> {
+        let mut f = BufReader::new(File::open(path)?);
+        Checkpoint::read(&mut f, filter, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::film::BoxFilter;
+    use crate::vec3;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let filter: Arc<dyn Filter> = Arc::new(BoxFilter::new(0.5));
+        let mut film = Film::new(3, 2, filter.clone());
+        film.add_sample(0.5, 0.5, vec3::Vec3(0.1, 0.2, 0.3));
+        film.add_sample(2.5, 1.5, vec3::Vec3(1.0, 2.0, 3.0));
+        film.add_splat(1.5, 0.5, vec3::Vec3(0.0, 4.0, 0.0));
+
+        let mut data = Vec::new();
+        let mut checkpoint = Checkpoint::new(7, 42, film);
+        checkpoint.sampler = Some((String::from("stratified"), 16));
+        checkpoint.write(&mut data).unwrap();
+        let checkpoint = Checkpoint::read(&mut data.as_slice(), filter.clone(), 3, 2).unwrap();
+
+        assert_eq!(checkpoint.samples, 7);
+        assert_eq!(checkpoint.seed, 42);
+        assert_eq!(checkpoint.sampler, Some((String::from("stratified"), 16)));
+        assert_eq!(checkpoint.film.width, 3);
+        assert_eq!(checkpoint.film.height, 2);
+        assert_eq!(checkpoint.film.pixel(0, 0).y(), 0.2);
+        assert_eq!(checkpoint.film.pixel(2, 1).z(), 3.0);
+        // splat 按两个样本取平均
+        assert_eq!(checkpoint.film.pixel(1, 0).y(), 2.0);
+
+        assert!(Checkpoint::read(&mut data.as_slice(), filter.clone(), 2, 3).is_err());
+        // 损坏的尺寸在分配胶片之前被拒绝
+        let mut corrupt = data.clone();
+        corrupt[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Checkpoint::read(&mut corrupt.as_slice(), filter.clone(), 3, 2).is_err());
+
+        data[4] = 99;
+        assert!(Checkpoint::read(&mut data.as_slice(), filter, 3, 2).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ray_tracing_rs::film::ImageFormat;
+use ray_tracing_rs::renderer::{AdaptiveSettings, Renderer};
+use ray_tracing_rs::sampler::{self, SAMPLERS};
+
+pub const USAGE: &str = "\
+USAGE:
+    ray-tracing-rs [OPTIONS] <SCENE>
+    ray-tracing-rs [OPTIONS] render <FILE>
+
+ARGS:
+    <SCENE>    Scene number, or `*` to render every scene
+    <FILE>     Scene description (.json), pbrt (.pbrt), Mitsuba (.xml) or glTF scene
+               (.gltf, .glb), or PLY mesh (.ply), written next to it as .ppm unless it
+               sets an output
+
+OPTIONS:
+    -l, --list              List available scenes
+    -w, --width <N>         Image width, height follows the aspect ratio if not given
+        --height <N>        Image height, width follows the aspect ratio if not given
+    -s, --samples <N>       Samples per pixel
+    -d, --depth <N>         Maximum ray bounce depth
+    -j, --threads <N>       Number of worker threads, defaults to the number of cores
+    -o, --output <PATH>     Output image, format inferred from extension (ppm, png, pfm)
+        --export <PATH>     Write the scene as a description file (JSON) instead of rendering it
+        --sampler <NAME>    Sample generator: independent, stratified, halton, sobol or blue-noise
+                            [default: independent]
+        --seed <N>          Seed for the random scenes and the samplers [default: 0]
+        --pass-samples <N>  Samples per pixel rendered in each progressive pass
+        --snapshot-every <N>
+                            Write the current image to the output every N passes
+        --snapshot-interval <SECONDS>
+                            Write the current image to the output every SECONDS seconds
+        --time-limit <SECONDS>
+                            Stop after the pass that exceeds SECONDS seconds
+        --target-error <PERCENT>
+                            Stop once the estimated relative error drops below PERCENT.
+                            Without --samples these two render until the criterion is met
+        --adaptive <PERCENT>
+                            After --adaptive-min samples, only sample pixels whose 95% confidence
+                            interval is wider than PERCENT of their mean
+        --adaptive-min <N>  Samples per pixel before adaptive sampling starts [default: 16]
+        --heatmap <PATH>    Write the number of samples taken per pixel as an image
+        --checkpoint <PATH> Save the accumulated samples to PATH with every snapshot and at the end
+        --resume            Continue from the checkpoint, rendering up to --samples per pixel
+    -h, --help              Print this help";
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub scene: Option<String>,
+    pub scene_file: Option<String>,
+    pub list: bool,
+    pub help: bool,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+    pub depth: Option<u64>,
+    pub threads: Option<usize>,
+    pub output: Option<String>,
+    pub export: Option<String>,
+    pub sampler: Option<String>,
+    pub seed: Option<u64>,
+    pub pass_samples: Option<usize>,
+    pub snapshot_every: Option<usize>,
+    pub snapshot_interval: Option<u64>,
+    pub time_limit: Option<u64>,
+    pub target_error: Option<f64>,
+    pub adaptive: Option<f64>,
+    pub adaptive_min: Option<usize>,
+    pub heatmap: Option<String>,
+    pub checkpoint: Option<String>,
+    pub resume: bool,
+}
+
+fn parse_number<T>(flag: &str, value: &str, min: T) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + fmt::Display,
+{
+    match value.parse::<T>() {
+        Ok(n) if n >= min => Ok(n),
+        _ => Err(format!(
+            "invalid value `{}` for {}: expected a number >= {}",
+            value, flag, min
+        )),
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            // 同时支持 `--flag value` 与 `--flag=value`
+            let (flag, inline_value) = match arg.find('=') {
+                Some(i) if arg.starts_with("--") => {
+                    (arg[..i].to_string(), Some(arg[i + 1..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for {}", flag))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => options.help = true,
+                "-l" | "--list" => options.list = true,
+                "-w" | "--width" => options.width = Some(parse_number(&flag, &value()?, 2)?),
+                "--height" => options.height = Some(parse_number(&flag, &value()?, 2)?),
+                "-s" | "--samples" => options.samples = Some(parse_number(&flag, &value()?, 1)?),
+                "-d" | "--depth" => options.depth = Some(parse_number(&flag, &value()?, 1)?),
+                "-j" | "--threads" => options.threads = Some(parse_number(&flag, &value()?, 1)?),
+                "-o" | "--output" => {
+                    let output = value()?;
+                    if ImageFormat::from_path(&output).is_none() {
+                        return Err(format!(
+                            "cannot infer image format of `{}`: expected .ppm, .png or .pfm",
+                            output
+                        ));
+                    }
+                    options.output = Some(output);
+                }
+                "--export" => options.export = Some(value()?),
+                "--sampler" => {
+                    let sampler = value()?;
+                    if !SAMPLERS.contains(&sampler.as_str()) {
+                        return Err(format!(
+                            "unknown sampler `{}`, expected one of: {}",
+                            sampler,
+                            SAMPLERS.join(", ")
+                        ));
+                    }
+                    options.sampler = Some(sampler);
+                }
+                "--seed" => options.seed = Some(parse_number(&flag, &value()?, 0)?),
+                "--pass-samples" => options.pass_samples = Some(parse_number(&flag, &value()?, 1)?),
+                "--snapshot-every" => {
+                    options.snapshot_every = Some(parse_number(&flag, &value()?, 1)?)
+                }
+                "--snapshot-interval" => {
+                    options.snapshot_interval = Some(parse_number(&flag, &value()?, 1)?)
+                }
+                "--time-limit" => options.time_limit = Some(parse_number(&flag, &value()?, 1)?),
+                "--target-error" => {
+                    options.target_error = Some(parse_number(&flag, &value()?, 0.0)?)
+                }
+                "--adaptive" => options.adaptive = Some(parse_number(&flag, &value()?, 0.0)?),
+                "--adaptive-min" => options.adaptive_min = Some(parse_number(&flag, &value()?, 2)?),
+                "--heatmap" => {
+                    let heatmap = value()?;
+                    if ImageFormat::from_path(&heatmap).is_none() {
+                        return Err(format!(
+                            "cannot infer image format of `{}`: expected .ppm, .png or .pfm",
+                            heatmap
+                        ));
+                    }
+                    options.heatmap = Some(heatmap);
+                }
+                "--checkpoint" => options.checkpoint = Some(value()?),
+                "--resume" => options.resume = true,
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("unknown option {}", flag));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        match positional.next() {
+            Some(command) if command == "render" => match positional.next() {
+                Some(file) => options.scene_file = Some(file),
+                None => return Err(String::from("missing scene file after `render`")),
+            },
+            scene => options.scene = scene,
+        }
+        if let Some(arg) = positional.next() {
+            let first = options.scene_file.as_ref().or(options.scene.as_ref());
+            return Err(format!(
+                "unexpected argument `{}` after `{}`",
+                arg,
+                first.unwrap()
+            ));
+        }
+
+        if options.resume && options.checkpoint.is_none() {
+            return Err(String::from("--resume requires --checkpoint <PATH>"));
+        }
+
+        Ok(options)
+    }
+
+    pub fn has_render_options(&self) -> bool {
+        self.width.is_some()
+            || self.height.is_some()
+            || self.samples.is_some()
+            || self.depth.is_some()
+            || self.threads.is_some()
+            || self.output.is_some()
+            || self.export.is_some()
+            || self.sampler.is_some()
+            || self.seed.is_some()
+            || self.pass_samples.is_some()
+            || self.snapshot_every.is_some()
+            || self.snapshot_interval.is_some()
+            || self.time_limit.is_some()
+            || self.target_error.is_some()
+            || self.adaptive.is_some()
+            || self.adaptive_min.is_some()
+            || self.heatmap.is_some()
+            || self.checkpoint.is_some()
+    }
+
+    pub fn apply(&self, renderer: &mut Renderer) {
+        let image = &mut renderer.image;
+        let samples_per_pixel = image.samples_per_pixel;
+        let aspect_ratio = image.width as f64 / image.height as f64;
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                image.width = width;
+                image.height = height;
+            }
+            (Some(width), None) => {
+                image.width = width;
+                image.height = ((width as f64 / aspect_ratio) as usize).max(2);
+            }
+            (None, Some(height)) => {
+                image.width = ((height as f64 * aspect_ratio) as usize).max(2);
+                image.height = height;
+            }
+            (None, None) => {}
+        }
+        if let Some(samples) = self.samples {
+            image.samples_per_pixel = samples;
+        } else if self.time_limit.is_some() || self.target_error.is_some() {
+            image.samples_per_pixel = usize::MAX;
+        }
+        if let Some(depth) = self.depth {
+            image.max_depth = depth;
+        }
+        if let Some(threads) = self.threads {
+            renderer.threads = threads;
+        }
+        if let Some(output) = &self.output {
+            renderer.output = output.clone();
+        }
+        // 分层采样的层数取决于样本数，样本数改变时按同一名称重新创建采样器
+        if self.sampler.is_some() || image.samples_per_pixel != samples_per_pixel {
+            let name = self.sampler.as_deref().unwrap_or(renderer.sampler.name());
+            renderer.sampler = sampler::from_name(name, image.samples_per_pixel).unwrap();
+        }
+        if let Some(seed) = self.seed {
+            renderer.seed = seed;
+        }
+        if let Some(pass_samples) = self.pass_samples {
+            renderer.samples_per_pass = pass_samples;
+        }
+        if let Some(every) = self.snapshot_every {
+            renderer.snapshots.every_passes = Some(every);
+        }
+        if let Some(seconds) = self.snapshot_interval {
+            renderer.snapshots.interval = Some(Duration::from_secs(seconds));
+        }
+        if let Some(seconds) = self.time_limit {
+            renderer.stop.time_limit = Some(Duration::from_secs(seconds));
+        }
+        if let Some(percent) = self.target_error {
+            renderer.stop.target_error = Some(percent / 100.0);
+        }
+        if let Some(percent) = self.adaptive {
+            let min_samples = self.adaptive_min.unwrap_or(16);
+            renderer.adaptive = Some(AdaptiveSettings::new(min_samples, percent / 100.0));
+        }
+        if let Some(heatmap) = &self.heatmap {
+            renderer.heatmap = Some(heatmap.clone());
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            renderer.checkpoint = Some(checkpoint.clone());
+            renderer.resume = self.resume;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use ray_tracing_rs::camera::Camera;
+    use ray_tracing_rs::hittable_list::HittableList;
+    use ray_tracing_rs::integrator::Normals;
+    use ray_tracing_rs::renderer::ImageSettings;
+    use ray_tracing_rs::sampler::{Sampler, StratifiedSampler};
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&["21", "-w", "600", "--samples=10", "-o", "out.png"]).unwrap();
+        assert_eq!(options.scene.as_deref(), Some("21"));
+        assert_eq!(options.width, Some(600));
+        assert_eq!(options.samples, Some(10));
+        assert_eq!(options.output.as_deref(), Some("out.png"));
+        assert!(options.has_render_options());
+
+        let options = parse(&["20", "--snapshot-every", "5", "--snapshot-interval=60"]).unwrap();
+        assert_eq!(options.snapshot_every, Some(5));
+        assert_eq!(options.snapshot_interval, Some(60));
+
+        let options = parse(&["20", "--time-limit", "600", "--target-error", "0.5"]).unwrap();
+        assert_eq!(options.time_limit, Some(600));
+        assert_eq!(options.target_error, Some(0.5));
+
+        let options = parse(&["20", "--adaptive", "2", "--heatmap", "heat.png"]).unwrap();
+        assert_eq!(options.adaptive, Some(2.0));
+        assert_eq!(options.adaptive_min, None);
+        assert_eq!(options.heatmap.as_deref(), Some("heat.png"));
+
+        let options = parse(&["render", "scene.json", "-s", "8"]).unwrap();
+        assert_eq!(options.scene, None);
+        assert_eq!(options.scene_file.as_deref(), Some("scene.json"));
+
+        let options = parse(&["21", "--sampler", "sobol", "--seed", "42"]).unwrap();
+        assert_eq!(options.sampler.as_deref(), Some("sobol"));
+        assert_eq!(options.seed, Some(42));
+
+        let options = parse(&["21", "--seed", "7", "--export", "21.json"]).unwrap();
+        assert_eq!(options.export.as_deref(), Some("21.json"));
+        assert!(options.has_render_options());
+
+        assert!(parse(&["--list"]).unwrap().list);
+        assert!(!parse(&["7"]).unwrap().has_render_options());
+    }
+
+    #[test]
+    fn test_apply_rebuilds_sampler() {
+        let mut renderer = Renderer::new(
+            Arc::new(HittableList::new()),
+            Arc::new(Camera::new()),
+            ImageSettings::new(8, 1.0, 16, 4),
+            Arc::new(Normals::new()),
+            "",
+        );
+        renderer.sampler = Box::new(StratifiedSampler::new(16));
+        parse(&["21", "--samples", "100"])
+            .unwrap()
+            .apply(&mut renderer);
+        assert_eq!(renderer.sampler.name(), "stratified");
+
+        // 与按新的样本数创建的采样器给出相同的样本
+        let mut expected = StratifiedSampler::new(100);
+        for sampler in [&mut *renderer.sampler, &mut expected as &mut dyn Sampler] {
+            sampler.set_seed(1);
+            sampler.start_pixel_sample(0, 0, 3);
+        }
+        for _ in 0..4 {
+            assert_eq!(renderer.sampler.get_2d(), expected.get_2d());
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["21", "--samples"]).is_err());
+        assert!(parse(&["21", "--samples", "0"]).is_err());
+        assert!(parse(&["21", "--width", "abc"]).is_err());
+        assert!(parse(&["21", "--output", "out.jpg"]).is_err());
+        assert!(parse(&["21", "--frobnicate"]).is_err());
+        assert!(parse(&["21", "--sampler", "random"]).is_err());
+        assert!(parse(&["21", "22"]).is_err());
+        assert!(parse(&["render"]).is_err());
+        assert!(parse(&["render", "a.json", "b.json"]).is_err());
+        assert!(parse(&["21", "--resume"]).is_err());
+    }
+}
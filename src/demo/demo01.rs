@@ -2,7 +2,7 @@ use std::fs::File;
 use std::io;
 use std::io::Write;
 
-use crate::vec3::Color;
+use ray_tracing_rs::vec3::Color;
 
 const FILENAME: &str = "pic/01.ppm";
 
@@ -2,8 +2,8 @@ use std::fs::File;
 use std::io;
 use std::io::Write;
 
-use crate::ray::Ray;
-use crate::vec3;
+use ray_tracing_rs::ray::Ray;
+use ray_tracing_rs::vec3;
 
 const FILENAME: &str = "pic/03.ppm";
 
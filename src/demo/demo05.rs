@@ -2,8 +2,8 @@ use std::fs::File;
 use std::io;
 use std::io::Write;
 
-use crate::ray::Ray;
-use crate::vec3;
+use ray_tracing_rs::ray::Ray;
+use ray_tracing_rs::vec3;
 
 const FILENAME: &str = "pic/05.ppm";
 
@@ -34,7 +34,7 @@ fn ray_color(r: &Ray) -> vec3::Color {
         2: -1.0,
     };
     let radius = 0.5;
-    let mut t = hit_sphere(center, radius, &r);
+    let mut t = hit_sphere(center, radius, r);
     if t > 0.0 {
         let n = (r.at(t) - vec3::Vec3(0.0, 0.0, -1.0)).unit_vector();
         return 0.5
@@ -3,11 +3,11 @@ use std::io;
 use std::io::Write;
 use std::sync::Arc;
 
-use crate::hittable::{HitRecord, Hittable};
-use crate::hittable_list::HittableList;
-use crate::ray::Ray;
-use crate::sphere::Sphere;
-use crate::vec3;
+use ray_tracing_rs::hittable::{HitRecord, Hittable};
+use ray_tracing_rs::hittable_list::HittableList;
+use ray_tracing_rs::ray::Ray;
+use ray_tracing_rs::sphere::Sphere;
+use ray_tracing_rs::vec3;
 
 const FILENAME: &str = "pic/06.ppm";
 
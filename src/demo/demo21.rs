@@ -1,67 +1,19 @@
-use std::fs::File;
 use std::io;
-use std::io::Write;
-use std::sync::mpsc::channel;
 use std::sync::Arc;
-use threadpool::ThreadPool;
 
-use crate::camera::LensCamera;
-use crate::hittable::{HitRecord, Hittable};
-use crate::hittable_list::HittableList;
-use crate::material::{Dielectric, Lambertian, Metal};
-use crate::ray::Ray;
-use crate::sphere::Sphere;
-use crate::utils;
-use crate::vec3;
+use ray_tracing_rs::camera::LensCamera;
+use ray_tracing_rs::hittable_list::HittableList;
+use ray_tracing_rs::integrator::Recursive;
+use ray_tracing_rs::material::{Dielectric, Lambertian, Metal};
+use ray_tracing_rs::renderer::{ImageSettings, Renderer, DEFAULT_SEED};
+use ray_tracing_rs::sphere::Sphere;
+use ray_tracing_rs::utils;
+use ray_tracing_rs::vec3;
 
 const FILENAME: &str = "pic/21.ppm";
 
-// 线性插值
-fn lerp(t: f64, start: vec3::Color, end: vec3::Color) -> vec3::Color {
-    (1.0 - t) * start + t * end
-}
-
-fn ray_color<T: Hittable>(r: &Ray, world: &T, depth: u64) -> vec3::Color {
-    if depth == 0 {
-        return vec3::Color {
-            0: 0.0,
-            1: 0.0,
-            2: 0.0,
-        };
-    }
-
-    let mut rec = HitRecord::new();
-    if world.hit(r, 0.001, f64::INFINITY, &mut rec) {
-        let mut scattered = Ray::new(vec3::Point3::fill(0.0), vec3::Vec3::fill(0.0));
-        let mut attenuation = vec3::Vec3::fill(0.0);
-        if rec
-            .mat_ptr
-            .scatter(r, &rec, &mut attenuation, &mut scattered)
-        {
-            return attenuation * ray_color(&scattered, world, depth - 1);
-        } else {
-            return vec3::Color::fill(0.0);
-        }
-    }
-
-    let unit_direction = r.direction.unit_vector(); // 单位化
-    let t = 0.5 * (unit_direction.y() + 1.0); // 将y分量映射到[0, 1]
-
-    let from = vec3::Color {
-        0: 1.0,
-        1: 1.0,
-        2: 1.0,
-    }; // 白色
-    let to = vec3::Color {
-        0: 0.5,
-        1: 0.7,
-        2: 1.0,
-    }; // 蓝色
-
-    lerp(t, from, to)
-}
-
-fn random_scene() -> HittableList {
+pub(super) fn random_scene(seed: u64) -> HittableList {
+    let mut rng = utils::Rng::new(seed);
     let mut world = HittableList::new();
 
     let mut ground = Sphere::new(
@@ -85,11 +37,11 @@ fn random_scene() -> HittableList {
         for b in -11..11 {
             let a = a as f64;
             let b = b as f64;
-            let choose_mat = utils::random();
+            let choose_mat = rng.random();
             let center = vec3::Point3 {
-                0: a + 0.9 * utils::random(),
+                0: a + 0.9 * rng.random(),
                 1: 0.2,
-                2: b + 0.9 * utils::random(),
+                2: b + 0.9 * rng.random(),
             };
 
             let point = vec3::Point3 {
@@ -101,15 +53,15 @@ fn random_scene() -> HittableList {
             if (center - point).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = vec3::Color::random() * vec3::Color::random();
+                    let albedo = vec3::Color::random(&mut rng) * vec3::Color::random(&mut rng);
                     let sphere_material = Arc::new(Lambertian::new(albedo));
                     let mut sphere = Sphere::new(center, 0.2);
                     sphere.mat_ptr = sphere_material;
                     world.add(Arc::new(sphere));
                 } else if choose_mat < 0.95 {
                     // metal
-                    let albedo = vec3::Color::random_in(0.5, 1.0);
-                    let fuzz = utils::random_in(0.0, 0.5);
+                    let albedo = vec3::Color::random_in(&mut rng, 0.5, 1.0);
+                    let fuzz = rng.random_in(0.0, 0.5);
                     let sphere_material = Arc::new(Metal::new(albedo, fuzz));
                     let mut sphere = Sphere::new(center, 0.2);
                     sphere.mat_ptr = sphere_material;
@@ -174,19 +126,13 @@ fn random_scene() -> HittableList {
     world
 }
 
-type PixelColors = Vec<vec3::Color>;
-
-pub fn run() -> io::Result<()> {
+pub fn renderer(seed: u64) -> Renderer {
     // Image
     let aspect_ratio = 3.0 / 2.0;
-    let image_width = 1200;
-    let image_height = (image_width as f64 / aspect_ratio) as i32;
-    let image_size = (image_width * image_height) as usize;
-    let samples_per_pixel = 500;
-    let max_depth = 50;
+    let image = ImageSettings::new(1200, aspect_ratio, 500, 50);
 
     // World
-    let world = Arc::new(random_scene());
+    let world = random_scene(seed);
 
     // Camera
     let lookfrom = vec3::Point3 {
@@ -202,7 +148,7 @@ pub fn run() -> io::Result<()> {
     let vup = vec3::Vec3(0.0, 1.0, 0.0);
     let dist_to_focus = 10.0;
     let aperture = 0.1;
-    let cam = Arc::new(LensCamera::new(
+    let cam = LensCamera::new(
         lookfrom,
         lookat,
         vup,
@@ -210,52 +156,19 @@ pub fn run() -> io::Result<()> {
         aspect_ratio,
         aperture,
         dist_to_focus,
-    ));
-
-    // multithreading
-    let pool = ThreadPool::new(6);
-    let (sender, receiver) = channel::<PixelColors>();
+    );
 
     // Render
-    let part0 = format!("P3\n{} {}\n255\n", image_width, image_height);
-    let mut f = File::create(FILENAME)?;
-    f.write_all(part0.as_bytes())?;
-
-    for _ in 0..samples_per_pixel {
-        let sender = sender.clone();
-        let world = world.clone();
-        let cam = cam.clone();
-        let tracing = move || {
-            let mut ray_colors = PixelColors::with_capacity(image_size);
-            for row in (0..image_height).rev() {
-                eprint!("\rScanlines remaining: {} ", row);
-                for col in 0..image_width {
-                    let u = (col as f64 + utils::random()) / (image_width - 1) as f64;
-                    let v = (row as f64 + utils::random()) / (image_height - 1) as f64;
-                    let r = cam.get_ray(u, v);
-                    ray_colors.push(ray_color(&r, &*world, max_depth));
-                }
-            }
-            sender.send(ray_colors).expect("Ray tracing failed!");
-        };
-        pool.execute(tracing);
-    }
-
-    let mut pixel_colors = PixelColors::with_capacity(image_size);
-    for _ in 0..image_size {
-        pixel_colors.push(vec3::Color::fill(0.0));
-    }
-
-    for ray_colors in receiver.iter().take(samples_per_pixel as usize) {
-        for (i, pixel_color) in pixel_colors.iter_mut().enumerate() {
-            *pixel_color += ray_colors[i];
-        }
-    }
-
-    for pixel_color in pixel_colors {
-        pixel_color.write_color(&mut f, samples_per_pixel)?;
-    }
+    let integrator = Recursive::new();
+    Renderer::new(
+        Arc::new(world),
+        Arc::new(cam),
+        image,
+        Arc::new(integrator),
+        FILENAME,
+    )
+}
 
-    eprintln!("\nDone.");
-    Ok(())
+pub fn run() -> io::Result<()> {
+    renderer(DEFAULT_SEED).run()
 }
@@ -0,0 +1,82 @@
+use std::io;
+use std::sync::Arc;
+
+use ray_tracing_rs::camera::LensCamera;
+use ray_tracing_rs::film::{
+    BoxFilter, Filter, GaussianFilter, LanczosFilter, MitchellFilter, TentFilter,
+};
+use ray_tracing_rs::integrator::Recursive;
+use ray_tracing_rs::renderer::{ImageSettings, Renderer, DEFAULT_SEED};
+use ray_tracing_rs::vec3;
+
+use super::demo21;
+
+const FILENAME: &str = "pic/22.ppm";
+const FILENAME_PREFIX: &str = "pic/22";
+
+pub fn renderer(seed: u64) -> Renderer {
+    // Image
+    let aspect_ratio = 3.0 / 2.0;
+    let image = ImageSettings::new(400, aspect_ratio, 100, 50);
+
+    // World
+    let world = demo21::random_scene(seed);
+
+    // Camera
+    let lookfrom = vec3::Point3 {
+        0: 13.0,
+        1: 2.0,
+        2: 3.0,
+    };
+    let lookat = vec3::Point3 {
+        0: 0.0,
+        1: 0.0,
+        2: 0.0,
+    };
+    let vup = vec3::Vec3(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.1;
+    let cam = LensCamera::new(
+        lookfrom,
+        lookat,
+        vup,
+        20.0,
+        aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    // Render
+    let integrator = Recursive::new();
+    let mut renderer = Renderer::new(
+        Arc::new(world),
+        Arc::new(cam),
+        image,
+        Arc::new(integrator),
+        FILENAME,
+    );
+    renderer.filter = Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0));
+    renderer
+}
+
+pub fn run() -> io::Result<()> {
+    // Filters
+    let filters: Vec<(&str, Arc<dyn Filter>)> = vec![
+        ("box", Arc::new(BoxFilter::new(0.5))),
+        ("tent", Arc::new(TentFilter::new(1.0))),
+        ("gaussian", Arc::new(GaussianFilter::new(1.5, 2.0))),
+        (
+            "mitchell",
+            Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+        ),
+        ("lanczos", Arc::new(LanczosFilter::new(3.0, 3.0))),
+    ];
+
+    let mut renderer = renderer(DEFAULT_SEED);
+    for (name, filter) in filters {
+        renderer.filter = filter;
+        renderer.output = format!("{}-{}.ppm", FILENAME_PREFIX, name);
+        renderer.run()?;
+    }
+    Ok(())
+}
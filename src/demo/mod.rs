@@ -1,3 +1,7 @@
+use std::io;
+
+use ray_tracing_rs::renderer::Renderer;
+
 pub mod demo00;
 pub mod demo01;
 pub mod demo02;
@@ -20,3 +24,218 @@ pub mod demo18;
 pub mod demo19;
 pub mod demo20;
 pub mod demo21;
+pub mod demo22;
+
+pub struct Demo {
+    pub description: &'static str,
+    pub run: fn() -> io::Result<()>,
+    // 基于 Renderer 的场景可以被命令行参数调整，参数为随机场景的种子
+    pub renderer: Option<fn(u64) -> Renderer>,
+}
+
+pub fn all() -> Vec<Demo> {
+    vec![
+        Demo {
+            description: "Gradient written with fs::write",
+            run: demo00::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Gradient written with write_color",
+            run: demo01::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Sky background",
+            run: demo02::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Red sphere",
+            run: demo03::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Sphere shaded by normals",
+            run: demo04::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Sphere normals with simplified intersection",
+            run: demo05::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Hittable list of spheres",
+            run: demo06::run,
+            renderer: None,
+        },
+        Demo {
+            description: "Antialiasing",
+            run: demo07::run,
+            renderer: Some(demo07::renderer),
+        },
+        Demo {
+            description: "Diffuse spheres, rejection sampling in unit sphere",
+            run: demo08::run,
+            renderer: Some(demo08::renderer),
+        },
+        Demo {
+            description: "Lambertian diffuse with unit vectors",
+            run: demo09::run,
+            renderer: Some(demo09::renderer),
+        },
+        Demo {
+            description: "Hemispherical diffuse",
+            run: demo10::run,
+            renderer: Some(demo10::renderer),
+        },
+        Demo {
+            description: "Metal spheres",
+            run: demo11::run,
+            renderer: Some(demo11::renderer),
+        },
+        Demo {
+            description: "Fuzzy metal",
+            run: demo12::run,
+            renderer: Some(demo12::renderer),
+        },
+        Demo {
+            description: "Dielectric spheres",
+            run: demo13::run,
+            renderer: Some(demo13::renderer),
+        },
+        Demo {
+            description: "Glass, Lambertian and metal spheres",
+            run: demo14::run,
+            renderer: Some(demo14::renderer),
+        },
+        Demo {
+            description: "Hollow glass sphere",
+            run: demo15::run,
+            renderer: Some(demo15::renderer),
+        },
+        Demo {
+            description: "Wide-angle camera",
+            run: demo16::run,
+            renderer: Some(demo16::renderer),
+        },
+        Demo {
+            description: "Positionable camera",
+            run: demo17::run,
+            renderer: Some(demo17::renderer),
+        },
+        Demo {
+            description: "Zoomed positionable camera",
+            run: demo18::run,
+            renderer: Some(demo18::renderer),
+        },
+        Demo {
+            description: "Defocus blur",
+            run: demo19::run,
+            renderer: Some(demo19::renderer),
+        },
+        Demo {
+            description: "Final scene",
+            run: demo20::run,
+            renderer: Some(demo20::renderer),
+        },
+        Demo {
+            description: "Final scene, multithreaded",
+            run: demo21::run,
+            renderer: Some(demo21::renderer),
+        },
+        Demo {
+            description: "Reconstruction filter comparison",
+            run: demo22::run,
+            renderer: Some(demo22::renderer),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use ray_tracing_rs::film::Film;
+    use ray_tracing_rs::metrics;
+    use ray_tracing_rs::renderer::{ImageSettings, DEFAULT_SEED};
+
+    use super::*;
+
+    // 参考图像使用缩小的图像和较少的样本，使测试足够快
+    const WIDTH: usize = 48;
+    const SAMPLES: usize = 16;
+    const MAX_DEPTH: u64 = 8;
+
+    // 同一种子下渲染结果逐位相同。换一个种子时各场景的 RMSE 不超过 0.08、
+    // FLIP 不超过 0.1，阈值在此之上留出余量，使只改变随机数用法的重构仍能通过；
+    // 漫反射率改变 20% 时 FLIP 在 0.17 以上
+    const MAX_RMSE: f64 = 0.12;
+    const MIN_PSNR: f64 = 18.0;
+    const MAX_FLIP: f64 = 0.14;
+
+    fn reference_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/reference")
+    }
+
+    fn render_small(renderer: fn(u64) -> Renderer) -> Film {
+        let mut renderer = renderer(DEFAULT_SEED);
+        let image = renderer.image;
+        let aspect_ratio = image.width as f64 / image.height as f64;
+        let max_depth = image.max_depth.min(MAX_DEPTH);
+        renderer.image = ImageSettings::new(WIDTH, aspect_ratio, SAMPLES, max_depth);
+        renderer.render()
+    }
+
+    // 设置环境变量 UPDATE_REFERENCE_IMAGES=1 时重新生成参考图像。
+    // 不通过时在 target/regression 下写出渲染结果和 FLIP 误差图
+    #[test]
+    fn test_reference_images() {
+        let update = env::var_os("UPDATE_REFERENCE_IMAGES").is_some();
+        let output_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/regression");
+        let mut failures = Vec::new();
+
+        for (n, demo) in all().iter().enumerate() {
+            let renderer = match demo.renderer {
+                Some(renderer) => renderer,
+                None => continue,
+            };
+            let film = render_small(renderer);
+            let reference_path = reference_dir().join(format!("{:02}.pfm", n));
+            if update {
+                fs::create_dir_all(reference_dir()).unwrap();
+                film.save(&reference_path).unwrap();
+                continue;
+            }
+
+            let reference = Film::load_pfm(&reference_path)
+                .unwrap_or_else(|e| panic!("cannot read {}: {}", reference_path.display(), e));
+            let rmse = metrics::rmse(&reference, &film);
+            let psnr = metrics::psnr(&reference, &film);
+            let flip = metrics::flip(&reference, &film);
+            if rmse > MAX_RMSE || psnr < MIN_PSNR || flip > MAX_FLIP {
+                fs::create_dir_all(&output_dir).unwrap();
+                film.save(output_dir.join(format!("{:02}.png", n))).unwrap();
+                let errors = metrics::flip_map(&reference, &film);
+                metrics::error_image(film.width, film.height, &errors)
+                    .save(output_dir.join(format!("{:02}-flip.png", n)))
+                    .unwrap();
+                failures.push(format!(
+                    "scene {}: RMSE {:.4}, PSNR {:.2} dB, FLIP {:.4}",
+                    n, rmse, psnr, flip
+                ));
+            }
+        }
+
+        assert!(
+            failures.is_empty(),
+            "rendered images differ from the references (see {}):\n{}",
+            output_dir.display(),
+            failures.join("\n")
+        );
+    }
+}
@@ -0,0 +1,806 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::utils;
+use crate::vec3;
+
+// 像素重建滤波器，(x, y) 为样本相对像素中心的偏移
+pub trait Filter: Send + Sync {
+    fn radius(&self) -> f64;
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        BoxFilter { radius }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> Self {
+        TentFilter { radius }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0) * (self.radius - y.abs()).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GaussianFilter {
+    radius: f64,
+    alpha: f64,
+    exp_radius: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, alpha: f64) -> Self {
+        GaussianFilter {
+            radius,
+            alpha,
+            exp_radius: (-alpha * radius * radius).exp(),
+        }
+    }
+
+    // 减去半径处的值，使滤波器在边界处平滑降为0
+    fn gaussian(&self, d: f64) -> f64 {
+        ((-self.alpha * d * d).exp() - self.exp_radius).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> Self {
+        MitchellFilter { radius, b, c }
+    }
+
+    // x 已映射到 [-2, 2]
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        if x > 2.0 {
+            0.0
+        } else if x > 1.0 {
+            ((-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x.powi(2)
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                + (6.0 - 2.0 * b))
+                / 6.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(2.0 * x / self.radius) * self.mitchell(2.0 * y / self.radius)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LanczosFilter {
+    radius: f64,
+    tau: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64, tau: f64) -> Self {
+        LanczosFilter { radius, tau }
+    }
+
+    fn sinc(x: f64) -> f64 {
+        let x = x.abs();
+        if x < 1e-5 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    }
+
+    // 以 sinc(x / tau) 为窗口的 sinc 函数
+    fn windowed_sinc(&self, x: f64) -> f64 {
+        if x.abs() > self.radius {
+            0.0
+        } else {
+            LanczosFilter::sinc(x) * LanczosFilter::sinc(x / self.tau)
+        }
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.windowed_sinc(x) * self.windowed_sinc(y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    Pfm,
+}
+
+impl ImageFormat {
+    // 根据文件扩展名推断输出格式
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+// 图像上的矩形像素区域 [col0, col1) × [row0, row1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub col0: usize,
+    pub row0: usize,
+    pub col1: usize,
+    pub row1: usize,
+}
+
+impl Tile {
+    pub fn new(col0: usize, row0: usize, col1: usize, row1: usize) -> Self {
+        Tile {
+            col0,
+            row0,
+            col1,
+            row1,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.col1 - self.col0
+    }
+
+    pub fn height(&self) -> usize {
+        self.row1 - self.row0
+    }
+
+    pub fn area(&self) -> usize {
+        self.width() * self.height()
+    }
+
+    // 将图像切分为 tile_size × tile_size 的块，自上而下逐行排列
+    pub fn split(width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for row1 in (1..=height).rev().step_by(tile_size) {
+            let row0 = row1.saturating_sub(tile_size);
+            for col0 in (0..width).step_by(tile_size) {
+                tiles.push(Tile::new(col0, row0, (col0 + tile_size).min(width), row1));
+            }
+        }
+        tiles
+    }
+}
+
+// 用 Welford 算法在线估计像素内样本亮度的均值和方差
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PixelVariance {
+    pub count: u64,
+    pub mean: f64,
+    m2: f64,
+}
+
+impl PixelVariance {
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    // 合并两组独立的统计量 (Chan et al.)
+    pub fn merge(&mut self, other: &PixelVariance) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64;
+        self.count = count;
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            f64::INFINITY
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    // 均值的标准误差相对于均值的比例，暗像素的均值按 1e-3 计
+    pub fn relative_error(&self) -> f64 {
+        (self.variance() / self.count as f64).sqrt() / self.mean.max(1e-3)
+    }
+}
+
+// 将 [0, 1] 映射为 黑-红-黄-白 的颜色
+pub fn heat_color(t: f64) -> vec3::Color {
+    let t = 3.0 * t;
+    vec3::Vec3(
+        utils::clamp(t, 0.0, 1.0),
+        utils::clamp(t - 1.0, 0.0, 1.0),
+        utils::clamp(t - 2.0, 0.0, 1.0),
+    )
+}
+
+// 累积样本的胶片，row 0 为图像最下方一行
+// 胶片可以只覆盖图像的一部分(bounds)，用于分块渲染。
+// splats 是直接累加到整幅图像任意像素上、不经过滤波器的贡献（例如光线追踪），
+// 像素值中按胶片上的样本总数 samples 取平均。整幅图像的胶片在第一次累加时
+// 分配逐像素的 splats，tile 只按顺序记下 (像素序号, 贡献)，合并时再累加
+#[derive(Clone)]
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub filter: Arc<dyn Filter>,
+    pub bounds: Tile,
+    colors: Vec<vec3::Color>,
+    weights: Vec<f64>,
+    variances: Vec<PixelVariance>,
+    splats: Vec<vec3::Color>,
+    tile_splats: Option<Vec<(usize, vec3::Color)>>,
+    samples: u64,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Arc<dyn Filter>) -> Self {
+        Film::with_bounds(width, height, filter, Tile::new(0, 0, width, height))
+    }
+
+    fn with_bounds(width: usize, height: usize, filter: Arc<dyn Filter>, bounds: Tile) -> Self {
+        Film {
+            width,
+            height,
+            filter,
+            bounds,
+            colors: vec![vec3::Color::fill(0.0); bounds.area()],
+            weights: vec![0.0; bounds.area()],
+            variances: vec![PixelVariance::default(); bounds.area()],
+            splats: Vec::new(),
+            tile_splats: None,
+            samples: 0,
+        }
+    }
+
+    // 由已经重建好的像素值构造胶片，每个像素的权重为1
+    pub fn from_pixels(width: usize, height: usize, pixels: &[vec3::Color]) -> Self {
+        let mut film = Film::new(width, height, Arc::new(BoxFilter::new(0.5)));
+        film.colors.copy_from_slice(pixels);
+        film.weights.iter_mut().for_each(|w| *w = 1.0);
+        film
+    }
+
+    // 渲染 tile 用的空胶片，向外扩展滤波器半径以接收溢出到相邻像素的样本
+    pub fn new_tile(width: usize, height: usize, filter: Arc<dyn Filter>, tile: &Tile) -> Self {
+        let margin = (filter.radius() + 0.5).ceil() as usize;
+        let bounds = Tile::new(
+            tile.col0.saturating_sub(margin),
+            tile.row0.saturating_sub(margin),
+            (tile.col1 + margin).min(width),
+            (tile.row1 + margin).min(height),
+        );
+        let mut film = Film::with_bounds(width, height, filter, bounds);
+        film.tile_splats = Some(Vec::new());
+        film
+    }
+
+    fn contains(&self, col: usize, row: usize) -> bool {
+        (self.bounds.col0..self.bounds.col1).contains(&col)
+            && (self.bounds.row0..self.bounds.row1).contains(&row)
+    }
+
+    fn index(&self, col: usize, row: usize) -> usize {
+        (row - self.bounds.row0) * self.bounds.width() + (col - self.bounds.col0)
+    }
+
+    // (x, y) 为连续的像素坐标，像素 (col, row) 的中心位于 (col + 0.5, row + 0.5)
+    pub fn add_sample(&mut self, x: f64, y: f64, color: vec3::Color) {
+        let (col, row) = (x.floor() as usize, y.floor() as usize);
+        if self.contains(col, row) {
+            let i = self.index(col, row);
+            self.variances[i].add(color.luminance());
+            self.samples += 1;
+        }
+
+        let radius = self.filter.radius();
+        let x = x - 0.5;
+        let y = y - 0.5;
+
+        let col0 = (x - radius).ceil().max(self.bounds.col0 as f64) as usize;
+        let col1 = (x + radius).floor().min(self.bounds.col1 as f64 - 1.0);
+        let row0 = (y - radius).ceil().max(self.bounds.row0 as f64) as usize;
+        let row1 = (y + radius).floor().min(self.bounds.row1 as f64 - 1.0);
+        if col1 < 0.0 || row1 < 0.0 {
+            return;
+        }
+
+        for row in row0..=row1 as usize {
+            for col in col0..=col1 as usize {
+                let weight = self.filter.evaluate(col as f64 - x, row as f64 - y);
+                if weight != 0.0 {
+                    let i = self.index(col, row);
+                    self.colors[i] += weight * color;
+                    self.weights[i] += weight;
+                }
+            }
+        }
+    }
+
+    // 累加到 (x, y) 所在的像素上，可以位于 bounds 之外
+    pub fn add_splat(&mut self, x: f64, y: f64, color: vec3::Color) {
+        if !(0.0..self.width as f64).contains(&x) || !(0.0..self.height as f64).contains(&y) {
+            return;
+        }
+        self.splat(y as usize * self.width + x as usize, color);
+    }
+
+    fn splat(&mut self, i: usize, color: vec3::Color) {
+        if let Some(tile_splats) = &mut self.tile_splats {
+            tile_splats.push((i, color));
+            return;
+        }
+        if self.splats.is_empty() {
+            self.splats = vec![vec3::Color::fill(0.0); self.width * self.height];
+        }
+        self.splats[i] += color;
+    }
+
+    // other 的区域必须位于本胶片的区域之内
+    pub fn merge(&mut self, other: &Film) {
+        if !other.splats.is_empty() {
+            for (i, splat) in other.splats.iter().enumerate() {
+                self.splat(i, *splat);
+            }
+        }
+        for &(i, splat) in other.tile_splats.iter().flatten() {
+            self.splat(i, splat);
+        }
+        self.samples += other.samples;
+        let bounds = other.bounds;
+        for row in bounds.row0..bounds.row1 {
+            for col in bounds.col0..bounds.col1 {
+                let i = self.index(col, row);
+                let j = other.index(col, row);
+                self.colors[i] += other.colors[j];
+                self.weights[i] += other.weights[j];
+                self.variances[i].merge(&other.variances[j]);
+            }
+        }
+    }
+
+    pub fn variance(&self, col: usize, row: usize) -> PixelVariance {
+        self.variances[self.index(col, row)]
+    }
+
+    // 每个像素实际采样的平均样本数
+    pub fn mean_samples(&self) -> f64 {
+        self.samples as f64 / (self.width * self.height).max(1) as f64
+    }
+
+    // 整幅图像的估计相对误差：各像素相对误差的平均值
+    pub fn relative_error(&self) -> f64 {
+        let total: f64 = self.variances.iter().map(|v| v.relative_error()).sum();
+        total / self.variances.len() as f64
+    }
+
+    pub fn pixel(&self, col: usize, row: usize) -> vec3::Color {
+        let i = self.index(col, row);
+        // Mitchell 和 Lanczos 有负瓣，权重和可能接近0
+        let color = if self.weights[i].abs() < 1e-12 {
+            vec3::Color::fill(0.0)
+        } else {
+            self.colors[i] / self.weights[i]
+        };
+        if self.splats.is_empty() {
+            return color;
+        }
+        color + self.splats[row * self.width + col] / self.samples.max(1) as f64
+    }
+
+    // 将每个像素的样本数映射为颜色，用于观察自适应采样的分布
+    pub fn sample_heatmap(&self) -> Film {
+        let max = self
+            .variances
+            .iter()
+            .map(|v| v.count)
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let mut heatmap = Film::new(self.width, self.height, Arc::new(BoxFilter::new(0.5)));
+        for row in self.bounds.row0..self.bounds.row1 {
+            for col in self.bounds.col0..self.bounds.col1 {
+                let t = self.variance(col, row).count as f64 / max;
+                heatmap.add_sample(col as f64 + 0.5, row as f64 + 0.5, heat_color(t));
+            }
+        }
+        heatmap
+    }
+
+    // 以小端序写出累积的颜色、权重和亮度统计量，用于检查点
+    pub fn write_accumulated<T: io::Write>(&self, out: &mut T) -> io::Result<()> {
+        for i in 0..self.colors.len() {
+            let (color, v) = (self.colors[i], self.variances[i]);
+            for x in &[color.r(), color.g(), color.b(), self.weights[i]] {
+                out.write_all(&x.to_le_bytes())?;
+            }
+            out.write_all(&v.count.to_le_bytes())?;
+            out.write_all(&v.mean.to_le_bytes())?;
+            out.write_all(&v.m2.to_le_bytes())?;
+        }
+        out.write_all(&[!self.splats.is_empty() as u8])?;
+        for splat in &self.splats {
+            for x in &[splat.r(), splat.g(), splat.b()] {
+                out.write_all(&x.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    // 旧版本的检查点没有亮度统计量与 splat
+    pub fn read_accumulated<T: io::Read>(
+        &mut self,
+        input: &mut T,
+        with_variance: bool,
+        with_splats: bool,
+    ) -> io::Result<()> {
+        let mut buf = [0u8; 8];
+        let mut next = || -> io::Result<[u8; 8]> {
+            input.read_exact(&mut buf)?;
+            Ok(buf)
+        };
+        for i in 0..self.colors.len() {
+            let mut v = [0.0; 4];
+            for x in v.iter_mut() {
+                *x = f64::from_le_bytes(next()?);
+            }
+            self.colors[i] = vec3::Vec3(v[0], v[1], v[2]);
+            self.weights[i] = v[3];
+            if with_variance {
+                self.variances[i] = PixelVariance {
+                    count: u64::from_le_bytes(next()?),
+                    mean: f64::from_le_bytes(next()?),
+                    m2: f64::from_le_bytes(next()?),
+                };
+            }
+        }
+        self.samples = self.variances.iter().map(|v| v.count).sum();
+
+        if !with_splats {
+            return Ok(());
+        }
+        let mut flag = [0u8; 1];
+        input.read_exact(&mut flag)?;
+        if flag[0] != 0 {
+            let mut splats = Vec::with_capacity(self.width * self.height);
+            for _ in 0..self.width * self.height {
+                let mut v = [0.0; 3];
+                for x in v.iter_mut() {
+                    input.read_exact(&mut buf)?;
+                    *x = f64::from_le_bytes(buf);
+                }
+                splats.push(vec3::Vec3(v[0], v[1], v[2]));
+            }
+            self.splats = splats;
+        }
+        Ok(())
+    }
+
+    pub fn write_ppm<T: io::Write>(&self, out: &mut T) -> io::Result<()> {
+        let header = format!("P3\n{} {}\n255\n", self.width, self.height);
+        out.write_all(header.as_bytes())?;
+        for row in (0..self.height).rev() {
+            for col in 0..self.width {
+                self.pixel(col, row).write_color(out, 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_png<T: io::Write>(&self, out: &mut T) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut data = Vec::with_capacity(self.width * self.height * 3);
+        for row in (0..self.height).rev() {
+            for col in 0..self.width {
+                data.extend_from_slice(&self.pixel(col, row).to_rgb8(1));
+            }
+        }
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        Ok(())
+    }
+
+    // 线性的浮点颜色，PFM 的行顺序为自下而上
+    pub fn write_pfm<T: io::Write>(&self, out: &mut T) -> io::Result<()> {
+        let header = format!("PF\n{} {}\n-1.0\n", self.width, self.height);
+        out.write_all(header.as_bytes())?;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let color = self.pixel(col, row);
+                for c in &[color.r(), color.g(), color.b()] {
+                    out.write_all(&(*c as f32).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 读取 write_pfm 写出的彩色 PFM 图像
+    pub fn read_pfm<T: io::BufRead>(input: &mut T) -> io::Result<Film> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut header = Vec::new();
+        while header.len() < 4 {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("truncated PFM header"));
+            }
+            header.extend(line.split_whitespace().map(String::from));
+        }
+        if header[0] != "PF" {
+            return Err(invalid("not a color PFM image"));
+        }
+        let width: usize = header[1]
+            .parse()
+            .map_err(|_| invalid("invalid PFM width"))?;
+        let height: usize = header[2]
+            .parse()
+            .map_err(|_| invalid("invalid PFM height"))?;
+        let scale: f64 = header[3]
+            .parse()
+            .map_err(|_| invalid("invalid PFM scale"))?;
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut buf = [0u8; 4];
+        let mut next = || -> io::Result<f64> {
+            input.read_exact(&mut buf)?;
+            Ok(if scale < 0.0 {
+                f32::from_le_bytes(buf) as f64
+            } else {
+                f32::from_be_bytes(buf) as f64
+            })
+        };
+        for _ in 0..width * height {
+            pixels.push(vec3::Vec3(next()?, next()?, next()?));
+        }
+        Ok(Film::from_pixels(width, height, &pixels))
+    }
+
+    pub fn load_pfm<P: AsRef<Path>>(path: P) -> io::Result<Film> {
+        let mut f = BufReader::new(File::open(path)?);
+        Film::read_pfm(&mut f)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported image format: {}", path.display()),
+            )
+        })?;
+
+        let mut f = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Ppm => self.write_ppm(&mut f),
+            ImageFormat::Png => self.write_png(&mut f),
+            ImageFormat::Pfm => self.write_pfm(&mut f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_box_filter_matches_pixel_average() {
+        let mut film = Film::new(2, 1, Arc::new(BoxFilter::new(0.5)));
+        film.add_sample(0.25, 0.5, vec3::Color::fill(1.0));
+        film.add_sample(0.75, 0.5, vec3::Color::fill(0.5));
+        film.add_sample(1.5, 0.5, vec3::Color::fill(0.2));
+
+        assert_eq!(film.pixel(0, 0).x(), 0.75);
+        assert_eq!(film.pixel(1, 0).x(), 0.2);
+    }
+
+    #[test]
+    fn test_wide_filter_splats_to_neighbours() {
+        let mut film = Film::new(3, 3, Arc::new(TentFilter::new(1.5)));
+        film.add_sample(1.5, 1.5, vec3::Color::fill(1.0));
+
+        for row in 0..3 {
+            for col in 0..3 {
+                assert_eq!(film.pixel(col, row).x(), 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let tiles = Tile::split(70, 45, 32);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], Tile::new(0, 13, 32, 45));
+        assert_eq!(tiles[5], Tile::new(64, 0, 70, 13));
+        assert_eq!(tiles.iter().map(|tile| tile.area()).sum::<usize>(), 70 * 45);
+    }
+
+    #[test]
+    fn test_merge_tiles_matches_full_film() {
+        let filter: Arc<dyn Filter> = Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0));
+        let mut full = Film::new(8, 8, filter.clone());
+        let mut merged = Film::new(8, 8, filter.clone());
+
+        for tile in Tile::split(8, 8, 3) {
+            let mut film = Film::new_tile(8, 8, filter.clone(), &tile);
+            for row in tile.row0..tile.row1 {
+                for col in tile.col0..tile.col1 {
+                    let color = vec3::Vec3(col as f64, row as f64, 1.0);
+                    full.add_sample(col as f64 + 0.3, row as f64 + 0.6, color);
+                    film.add_sample(col as f64 + 0.3, row as f64 + 0.6, color);
+                    // splat 可以落在 tile 之外，tile 不为整幅图像分配空间
+                    let (x, y) = ((7 - col) as f64 + 0.5, (7 - row) as f64 + 0.5);
+                    full.add_splat(x, y, color);
+                    film.add_splat(x, y, color);
+                }
+            }
+            assert!(film.splats.is_empty());
+            merged.merge(&film);
+        }
+
+        for row in 0..8 {
+            for col in 0..8 {
+                let a = full.pixel(col, row);
+                let b = merged.pixel(col, row);
+                assert!((a - b).length() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixel_variance_merge() {
+        let xs = [0.5, 1.0, 3.0, 0.25, 2.0, 2.5];
+        let mut all = PixelVariance::default();
+        let mut a = PixelVariance::default();
+        let mut b = PixelVariance::default();
+        for (i, x) in xs.iter().enumerate() {
+            all.add(*x);
+            if i < 2 {
+                a.add(*x);
+            } else {
+                b.add(*x);
+            }
+        }
+        a.merge(&b);
+
+        assert_eq!(a.count, 6);
+        assert!((a.mean - 1.5416666666666667).abs() < 1e-12);
+        assert!((a.variance() - all.variance()).abs() < 1e-12);
+        assert!((all.variance() - 1.2604166666666667).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sample_heatmap() {
+        let mut film = Film::new(3, 1, Arc::new(BoxFilter::new(0.5)));
+        for _ in 0..3 {
+            film.add_sample(0.5, 0.5, vec3::Color::fill(1.0));
+        }
+        film.add_sample(1.5, 0.5, vec3::Color::fill(1.0));
+
+        let heatmap = film.sample_heatmap();
+        assert_eq!(heatmap.pixel(0, 0).z(), 1.0);
+        assert_eq!(heatmap.pixel(1, 0).x(), 1.0);
+        assert_eq!(heatmap.pixel(1, 0).y(), 0.0);
+        assert_eq!(heatmap.pixel(2, 0).x(), 0.0);
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let pixels = [vec3::Vec3(0.25, 0.5, 1.0), vec3::Vec3(2.0, 0.0, 0.125)];
+        let film = Film::from_pixels(1, 2, &pixels);
+        let mut data = Vec::new();
+        film.write_pfm(&mut data).unwrap();
+
+        let read = Film::read_pfm(&mut data.as_slice()).unwrap();
+        assert_eq!((read.width, read.height), (1, 2));
+        assert_eq!(read.pixel(0, 1).x(), 2.0);
+        assert_eq!(read.pixel(0, 0).z(), 1.0);
+    }
+
+    #[test]
+    fn test_image_format_from_path() {
+        assert_eq!(ImageFormat::from_path("pic/21.ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path("out.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("out.pfm"), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_path("out.jpg"), None);
+        assert_eq!(ImageFormat::from_path("out"), None);
+    }
+
+    #[test]
+    fn test_filters_peak_at_center() {
+        let filters: Vec<Box<dyn Filter>> = vec![
+            Box::new(TentFilter::new(2.0)),
+            Box::new(GaussianFilter::new(1.5, 2.0)),
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Box::new(LanczosFilter::new(3.0, 3.0)),
+        ];
+        for filter in filters {
+            let center = filter.evaluate(0.0, 0.0);
+            assert!(center > filter.evaluate(0.5, 0.0));
+            assert!(center > filter.evaluate(0.0, 0.5));
+            assert_eq!(filter.evaluate(filter.radius() + 0.1, 0.0), 0.0);
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::material::{DefaultMaterial, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::scene::SceneWriter;
 use crate::vec3;
+use std::io;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -8,6 +11,8 @@ pub struct HitRecord {
     pub p: vec3::Point3,    //交点
     pub normal: vec3::Vec3, //法向量
     pub t: f64,             //距离
+    pub u: f64,             //表面坐标
+    pub v: f64,
     pub front_face: bool,
     pub mat_ptr: Arc<dyn Material>,
 }
@@ -22,6 +27,8 @@ impl HitRecord {
             },
             normal: vec3::Vec3(0.0, 0.0, 0.0),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
             mat_ptr: Arc::new(DefaultMaterial::new()),
         }
@@ -36,6 +43,36 @@ impl HitRecord {
     }
 }
 
+impl Default for HitRecord {
+    fn default() -> Self {
+        HitRecord::new()
+    }
+}
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+    // 从 origin 出发沿 direction 击中自身的概率密度（对立体角），用于对光源采样
+    fn pdf_value(&self, _origin: vec3::Point3, _direction: vec3::Vec3) -> f64 {
+        0.0
+    }
+    // 从 origin 出发朝向自身的随机方向，分布与 pdf_value 一致
+    fn random(&self, _origin: vec3::Point3, _sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        vec3::Vec3(1.0, 0.0, 0.0)
+    }
+    // 在表面上按面积均匀采样一点，返回该点的记录（法向量朝外）与对面积的概率密度，
+    // 用于从光源出发追踪光线，不能采样的物体返回 None
+    fn random_point(&self, _sampler: &mut dyn Sampler) -> Option<(HitRecord, f64)> {
+        None
+    }
+    // random_point 采样到表面上的点 p 的概率密度（对面积）
+    fn pdf_point(&self, _p: vec3::Point3) -> f64 {
+        0.0
+    }
+    // 导出场景时将自身写入 scene
+    fn describe(&self, _scene: &mut SceneWriter) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "object cannot be exported",
+        ))
+    }
 }
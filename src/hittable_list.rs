@@ -1,5 +1,9 @@
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::scene::SceneWriter;
+use crate::vec3;
+use std::io;
 use std::sync::Arc;
 
 pub struct HittableList {
@@ -13,11 +17,17 @@ impl HittableList {
         }
     }
 
-    pub fn add(&mut self, object: Arc<dyn Hittable + Send>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
         self.objects.push(object);
     }
 }
 
+impl Default for HittableList {
+    fn default() -> Self {
+        HittableList::new()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
         let mut temp_rec = HitRecord::new();
@@ -34,4 +44,45 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    // 等概率地选择其中一个物体
+    fn pdf_value(&self, origin: vec3::Point3, direction: vec3::Vec3) -> f64 {
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len().max(1) as f64
+    }
+
+    fn random(&self, origin: vec3::Point3, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        if self.objects.is_empty() {
+            return vec3::Vec3(1.0, 0.0, 0.0);
+        }
+        let n = self.objects.len();
+        let index = ((sampler.get_1d() * n as f64) as usize).min(n - 1);
+        self.objects[index].random(origin, sampler)
+    }
+
+    fn random_point(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord, f64)> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        let n = self.objects.len();
+        let index = ((sampler.get_1d() * n as f64) as usize).min(n - 1);
+        let (rec, pdf) = self.objects[index].random_point(sampler)?;
+        Some((rec, pdf / n as f64))
+    }
+
+    fn pdf_point(&self, p: vec3::Point3) -> f64 {
+        let sum: f64 = self.objects.iter().map(|object| object.pdf_point(p)).sum();
+        sum / self.objects.len().max(1) as f64
+    }
+
+    fn describe(&self, scene: &mut SceneWriter) -> io::Result<()> {
+        for object in &self.objects {
+            object.describe(scene)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,1064 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use super::ImportedScene;
+use crate::aabb::Aabb;
+use crate::camera::LensCamera;
+use crate::hittable_list::HittableList;
+use crate::integrator::Background;
+use crate::material::{Dielectric, DiffuseLight, Material, MetallicRoughness};
+use crate::mesh::TriangleMesh;
+use crate::renderer::ImageSettings;
+use crate::scene::ImageDescription;
+use crate::sphere::Sphere;
+use crate::texture::{ImageTexture, Texture};
+use crate::transform::Transform;
+use crate::vec3;
+
+// glTF 2.0 场景（.gltf 与 .glb）：
+//   默认场景的节点层级与变换、三角形网格的位置/法向量/第一组纹理坐标
+//   金属度-粗糙度材质及其 PNG/JPEG 贴图、自发光强度与透射扩展
+//   透视相机、KHR_lights_punctual 的点光源与聚光灯
+// 其余内容被忽略或近似，并记录在 warnings 中
+
+// 支持的 extensionsRequired
+const EXTENSIONS: [&str; 4] = [
+    "KHR_lights_punctual",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+];
+
+// 光度单位与辐射度量之间的换算系数，单位 lm/W
+const LUMINOUS_EFFICACY: f64 = 683.0;
+
+fn one() -> f64 {
+    1.0
+}
+
+fn white() -> [f64; 4] {
+    [1.0; 4]
+}
+
+fn default_ior() -> f64 {
+    1.5
+}
+
+fn triangles() -> u32 {
+    4
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Document {
+    extensions_required: Vec<String>,
+    scene: Option<usize>,
+    scenes: Vec<SceneNodes>,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    materials: Vec<MaterialInfo>,
+    textures: Vec<TextureInfo>,
+    images: Vec<ImageInfo>,
+    cameras: Vec<CameraInfo>,
+    extensions: DocumentExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights_punctual: Option<Lights>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Lights {
+    lights: Vec<Light>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Light {
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default = "white3")]
+    color: [f64; 3],
+    #[serde(default = "one")]
+    intensity: f64,
+}
+
+fn white3() -> [f64; 3] {
+    [1.0; 3]
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SceneNodes {
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    // 按列存储
+    matrix: Option<[f64; 16]>,
+    translation: Option<[f64; 3]>,
+    rotation: Option<[f64; 4]>,
+    scale: Option<[f64; 3]>,
+    extensions: NodeExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights_punctual: Option<LightIndex>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LightIndex {
+    light: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Mesh {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    ty: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Buffer {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialInfo {
+    #[serde(default)]
+    pbr_metallic_roughness: Pbr,
+    #[serde(default)]
+    emissive_factor: [f64; 3],
+    emissive_texture: Option<TextureRef>,
+    normal_texture: Option<TextureRef>,
+    alpha_mode: Option<String>,
+    #[serde(default)]
+    extensions: MaterialExtensions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pbr {
+    #[serde(default = "white")]
+    base_color_factor: [f64; 4],
+    base_color_texture: Option<TextureRef>,
+    #[serde(default = "one")]
+    metallic_factor: f64,
+    #[serde(default = "one")]
+    roughness_factor: f64,
+    metallic_roughness_texture: Option<TextureRef>,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Pbr {
+            base_color_factor: white(),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrength>,
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<Transmission>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<Ior>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+    #[serde(default = "one")]
+    emissive_strength: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transmission {
+    #[serde(default)]
+    transmission_factor: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ior {
+    #[serde(default = "default_ior")]
+    ior: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextureRef {
+    index: usize,
+    #[serde(default)]
+    tex_coord: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextureInfo {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageInfo {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CameraInfo {
+    #[serde(rename = "type")]
+    ty: String,
+    perspective: Option<Perspective>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Perspective {
+    aspect_ratio: Option<f64>,
+    yfov: f64,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// 拆分 .glb 容器，返回 JSON 与可选的二进制块
+fn split_glb(bytes: &[u8]) -> io::Result<(&[u8], Option<&[u8]>)> {
+    let u32_at = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| invalid_data(String::from("truncated GLB file")))
+    };
+    if u32_at(4)? != 2 {
+        return Err(invalid_data(String::from("only glTF 2.0 is supported")));
+    }
+    let length = u32_at(8)?.min(bytes.len());
+    let mut offset = 12;
+    let mut json = None;
+    let mut bin = None;
+    while offset + 8 <= length {
+        let chunk_length = u32_at(offset)?;
+        let chunk_type = u32_at(offset + 4)?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| invalid_data(String::from("truncated GLB chunk")))?;
+        match chunk_type {
+            0x4e4f534a => json = json.or(Some(data)),
+            0x004e4942 => bin = bin.or(Some(data)),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    let json = json.ok_or_else(|| invalid_data(String::from("GLB file without JSON chunk")))?;
+    Ok((json, bin))
+}
+
+fn decode_base64(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err(invalid_data(String::from("invalid base64 data"))),
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+// URI 中的 %XX 转义
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 节点的局部变换，TRS 按先缩放、再旋转、最后平移的顺序应用
+fn node_transform(node: &Node) -> Transform {
+    if let Some(m) = node.matrix {
+        let mut transform = Transform::identity();
+        for (i, row) in transform.m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = m[j * 4 + i];
+            }
+        }
+        return transform;
+    }
+    let t = node.translation.unwrap_or([0.0; 3]);
+    let r = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let s = node.scale.unwrap_or([1.0; 3]);
+    Transform::translate(vec3::Vec3(t[0], t[1], t[2]))
+        * Transform::quaternion(r)
+        * Transform::scale(vec3::Vec3(s[0], s[1], s[2]))
+}
+
+struct Importer {
+    document: Document,
+    directory: PathBuf,
+    buffers: Vec<Vec<u8>>,
+    materials: Vec<Option<Arc<dyn Material>>>,
+    textures: HashMap<(usize, bool), Arc<dyn Texture>>,
+    world: HittableList,
+    bounds: Aabb,
+    camera: Option<(usize, Transform)>,
+    // 世界坐标中的位置与辐射强度
+    lights: Vec<(vec3::Point3, vec3::Color)>,
+    emissive: bool,
+    warnings: Vec<String>,
+}
+
+impl Importer {
+    fn warn(&mut self, message: String) {
+        if !self.warnings.contains(&message) {
+            self.warnings.push(message);
+        }
+    }
+
+    // 读取 data URI 或相对于场景文件的外部文件
+    fn read_uri(&self, uri: &str) -> io::Result<Vec<u8>> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            return match data.split_once(";base64,") {
+                Some((_, base64)) => decode_base64(base64),
+                None => Err(invalid_data(String::from(
+                    "only base64 data URIs are supported",
+                ))),
+            };
+        }
+        let path = self.directory.join(decode_percent(uri));
+        fs::read(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    fn buffer_view(&self, index: usize) -> io::Result<(&[u8], Option<usize>)> {
+        let view = self
+            .document
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| invalid_data(format!("buffer view {} does not exist", index)))?;
+        let data = self
+            .buffers
+            .get(view.buffer)
+            .and_then(|buffer| buffer.get(view.byte_offset..view.byte_offset + view.byte_length))
+            .ok_or_else(|| invalid_data(format!("buffer view {} is out of range", index)))?;
+        Ok((data, view.byte_stride))
+    }
+
+    // 读取访问器，返回每个元素的分量数与按元素排列的分量
+    fn accessor(&mut self, index: usize) -> io::Result<(usize, Vec<f64>)> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .ok_or_else(|| invalid_data(format!("accessor {} does not exist", index)))?;
+        let components = match accessor.ty.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            ty => return Err(invalid_data(format!("unknown accessor type `{}`", ty))),
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            ty => return Err(invalid_data(format!("unknown component type {}", ty))),
+        };
+        let (component_type, normalized, count) =
+            (accessor.component_type, accessor.normalized, accessor.count);
+        let (view, offset, sparse) = (
+            accessor.buffer_view,
+            accessor.byte_offset,
+            accessor.sparse.is_some(),
+        );
+        if sparse {
+            self.warn(String::from("sparse accessors are not supported"));
+        }
+        let view = match view {
+            Some(view) => view,
+            None => return Ok((components, vec![0.0; count * components])),
+        };
+
+        let (data, stride) = self.buffer_view(view)?;
+        let stride = stride.unwrap_or(size * components);
+        if count > 0 && offset + (count - 1) * stride + size * components > data.len() {
+            return Err(invalid_data(format!("accessor {} is out of range", index)));
+        }
+        let mut values = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for j in 0..components {
+                let b = &data[offset + i * stride + j * size..];
+                let value = match component_type {
+                    5120 if normalized => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                    5120 => b[0] as i8 as f64,
+                    5121 if normalized => b[0] as f64 / 255.0,
+                    5121 => b[0] as f64,
+                    5122 => {
+                        let v = i16::from_le_bytes([b[0], b[1]]) as f64;
+                        if normalized {
+                            (v / 32767.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    5123 => {
+                        let v = u16::from_le_bytes([b[0], b[1]]) as f64;
+                        if normalized {
+                            v / 65535.0
+                        } else {
+                            v
+                        }
+                    }
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                };
+                values.push(value);
+            }
+        }
+        Ok((components, values))
+    }
+
+    fn vectors(&mut self, index: usize) -> io::Result<Vec<vec3::Vec3>> {
+        let (components, values) = self.accessor(index)?;
+        if components != 3 {
+            return Err(invalid_data(format!("accessor {} is not VEC3", index)));
+        }
+        Ok(values
+            .chunks_exact(3)
+            .map(|v| vec3::Vec3(v[0], v[1], v[2]))
+            .collect())
+    }
+
+    fn texture(&mut self, info: &TextureRef, srgb: bool) -> io::Result<Option<Arc<dyn Texture>>> {
+        if info.tex_coord != 0 {
+            self.warn(String::from(
+                "only the first set of texture coordinates is supported",
+            ));
+        }
+        let source = self
+            .document
+            .textures
+            .get(info.index)
+            .ok_or_else(|| invalid_data(format!("texture {} does not exist", info.index)))?
+            .source;
+        let image = match source {
+            Some(image) => image,
+            None => {
+                self.warn(String::from(
+                    "textures without a PNG or JPEG source are ignored",
+                ));
+                return Ok(None);
+            }
+        };
+        if let Some(texture) = self.textures.get(&(image, srgb)) {
+            return Ok(Some(texture.clone()));
+        }
+
+        let info = self
+            .document
+            .images
+            .get(image)
+            .ok_or_else(|| invalid_data(format!("image {} does not exist", image)))?;
+        let bytes = match (&info.uri, info.buffer_view) {
+            (Some(uri), _) => self.read_uri(uri)?,
+            (None, Some(view)) => self.buffer_view(view)?.0.to_vec(),
+            (None, None) => return Err(invalid_data(format!("image {} has no data", image))),
+        };
+        let texture: Arc<dyn Texture> = Arc::new(
+            ImageTexture::decode(&bytes, srgb)
+                .map_err(|e| invalid_data(format!("image {}: {}", image, e)))?,
+        );
+        self.textures.insert((image, srgb), texture.clone());
+        Ok(Some(texture))
+    }
+
+    fn material(&mut self, index: Option<usize>) -> io::Result<Arc<dyn Material>> {
+        // glTF 的默认材质是白色的粗糙金属
+        let index = match index {
+            Some(index) => index,
+            None => {
+                return Ok(Arc::new(MetallicRoughness::new(
+                    vec3::Color::fill(1.0),
+                    1.0,
+                    1.0,
+                )))
+            }
+        };
+        if let Some(Some(material)) = self.materials.get(index) {
+            return Ok(material.clone());
+        }
+        let info = self
+            .document
+            .materials
+            .get_mut(index)
+            .ok_or_else(|| invalid_data(format!("material {} does not exist", index)))?;
+        let pbr = std::mem::take(&mut info.pbr_metallic_roughness);
+        let emissive_texture = info.emissive_texture.take();
+        let normal_texture = info.normal_texture.take();
+        let alpha_mode = info.alpha_mode.take();
+        let extensions = std::mem::take(&mut info.extensions);
+        let emissive_factor = info.emissive_factor;
+
+        if normal_texture.is_some() {
+            self.warn(String::from("normal textures are ignored"));
+        }
+        if alpha_mode.is_some_and(|mode| mode != "OPAQUE") {
+            self.warn(String::from("alpha modes are ignored"));
+        }
+
+        let material: Arc<dyn Material> = match extensions.transmission {
+            Some(transmission) if transmission.transmission_factor > 0.0 => {
+                if transmission.transmission_factor < 1.0 {
+                    self.warn(String::from(
+                        "partially transmissive materials are rendered as glass",
+                    ));
+                }
+                Arc::new(Dielectric::new(extensions.ior.map_or(1.5, |ior| ior.ior)))
+            }
+            _ => {
+                let c = pbr.base_color_factor;
+                let mut material = MetallicRoughness::new(
+                    vec3::Vec3(c[0], c[1], c[2]),
+                    pbr.metallic_factor,
+                    pbr.roughness_factor,
+                );
+                if let Some(info) = &pbr.base_color_texture {
+                    material.base_color_texture = self.texture(info, true)?;
+                }
+                if let Some(info) = &pbr.metallic_roughness_texture {
+                    material.metallic_roughness_texture = self.texture(info, false)?;
+                }
+                let strength = extensions
+                    .emissive_strength
+                    .map_or(1.0, |s| s.emissive_strength);
+                let e = emissive_factor;
+                material.emission = strength * vec3::Vec3(e[0], e[1], e[2]);
+                if let Some(info) = &emissive_texture {
+                    material.emissive_texture = self.texture(info, true)?;
+                }
+                if material.emission.length_squared() > 0.0 {
+                    self.emissive = true;
+                }
+                Arc::new(material)
+            }
+        };
+        if self.materials.len() <= index {
+            self.materials.resize(index + 1, None);
+        }
+        self.materials[index] = Some(material.clone());
+        Ok(material)
+    }
+
+    fn mesh(&mut self, index: usize, transform: &Transform) -> io::Result<()> {
+        let primitives = match self.document.meshes.get_mut(index) {
+            Some(mesh) => std::mem::take(&mut mesh.primitives),
+            None => return Err(invalid_data(format!("mesh {} does not exist", index))),
+        };
+        for primitive in &primitives {
+            self.primitive(primitive, transform)?;
+        }
+        // 同一网格可能被多个节点引用
+        self.document.meshes[index].primitives = primitives;
+        Ok(())
+    }
+
+    fn primitive(&mut self, primitive: &Primitive, transform: &Transform) -> io::Result<()> {
+        if !(4..=6).contains(&primitive.mode) {
+            self.warn(String::from("points and lines are not supported"));
+            return Ok(());
+        }
+        let position = match primitive.attributes.get("POSITION") {
+            Some(&position) => position,
+            None => return Ok(()),
+        };
+        let vertices = self.vectors(position)?;
+        let normals = match primitive.attributes.get("NORMAL") {
+            Some(&normal) => self.vectors(normal)?,
+            None => Vec::new(),
+        };
+        let uvs = match primitive.attributes.get("TEXCOORD_0") {
+            // glTF 的纹理坐标以图片左上角为原点
+            Some(&uv) => self
+                .accessor(uv)?
+                .1
+                .chunks_exact(2)
+                .map(|uv| [uv[0] as f32, 1.0 - uv[1] as f32])
+                .collect(),
+            None => Vec::new(),
+        };
+        let order: Vec<u32> = match primitive.indices {
+            Some(indices) => self
+                .accessor(indices)?
+                .1
+                .into_iter()
+                .map(|i| i as u32)
+                .collect(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        let indices: Vec<[u32; 3]> = match primitive.mode {
+            4 => order.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            // 三角形带中每隔一个三角形交换前两个顶点以保持绕向
+            5 => (0..order.len().saturating_sub(2))
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [order[i], order[i + 1], order[i + 2]]
+                    } else {
+                        [order[i + 1], order[i], order[i + 2]]
+                    }
+                })
+                .collect(),
+            _ => (1..order.len().saturating_sub(1))
+                .map(|i| [order[0], order[i], order[i + 1]])
+                .collect(),
+        };
+        if let Some(&i) = indices
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= vertices.len())
+        {
+            return Err(invalid_data(format!("vertex index {} out of range", i)));
+        }
+
+        let normal_transform = transform.inverse().map(|t| t.transpose());
+        let normals = match normal_transform {
+            Some(t) if normals.len() == vertices.len() => normals
+                .into_iter()
+                .map(|n| t.vector(n).unit_vector().to_f32_array())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let vertices = vertices
+            .into_iter()
+            .map(|p| transform.point(p).to_f32_array())
+            .collect();
+
+        let mut mesh = TriangleMesh::new(vertices, normals, indices);
+        self.bounds = self.bounds.union(&mesh.bounding_box());
+        if uvs.len() == mesh.vertices.len() {
+            mesh.uvs = uvs;
+        }
+        mesh.mat_ptr = self.material(primitive.material)?;
+        self.world.add(Arc::new(mesh));
+        Ok(())
+    }
+
+    fn node(&mut self, index: usize, parent: &Transform, depth: usize) -> io::Result<()> {
+        if depth > self.document.nodes.len() {
+            return Err(invalid_data(String::from(
+                "node hierarchy contains a cycle",
+            )));
+        }
+        let node = self
+            .document
+            .nodes
+            .get(index)
+            .ok_or_else(|| invalid_data(format!("node {} does not exist", index)))?;
+        let transform = *parent * node_transform(node);
+        let (mesh, camera, children) = (node.mesh, node.camera, node.children.clone());
+        let light = node.extensions.lights_punctual.as_ref().map(|l| l.light);
+
+        if let Some(mesh) = mesh {
+            self.mesh(mesh, &transform)?;
+        }
+        if let Some(camera) = camera {
+            if self.camera.is_none() {
+                self.camera = Some((camera, transform));
+            }
+        }
+        if let Some(light) = light {
+            self.light(light, &transform)?;
+        }
+        for child in children {
+            self.node(child, &transform, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn light(&mut self, index: usize, transform: &Transform) -> io::Result<()> {
+        let light = self
+            .document
+            .extensions
+            .lights_punctual
+            .as_ref()
+            .and_then(|lights| lights.lights.get(index))
+            .ok_or_else(|| invalid_data(format!("light {} does not exist", index)))?;
+        // 坎德拉换算为 W/sr
+        let c = light.color;
+        let intensity = light.intensity / LUMINOUS_EFFICACY * vec3::Vec3(c[0], c[1], c[2]);
+        match light.ty.as_str() {
+            "point" | "spot" => {
+                if light.ty == "spot" {
+                    self.warn(String::from("spot lights are rendered as point lights"));
+                }
+                self.warn(String::from(
+                    "point lights are approximated by small emissive spheres",
+                ));
+                let position = transform.point(vec3::Point3::fill(0.0));
+                self.lights.push((position, intensity));
+            }
+            ty => self.warn(format!("{} lights are not supported", ty)),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<ImportedScene> {
+        // 点光源的半径取场景尺寸的 1%，辐射强度为 π r² L
+        let size = (self.bounds.max - self.bounds.min).length();
+        let radius = if size.is_finite() && size > 0.0 {
+            0.01 * size
+        } else {
+            0.01
+        };
+        let mut lights = HittableList::new();
+        for &(position, intensity) in &self.lights {
+            let mut sphere = Sphere::new(position, radius);
+            let radiance = intensity / (std::f64::consts::PI * radius * radius);
+            sphere.mat_ptr = Arc::new(DiffuseLight::new(radiance));
+            let sphere = Arc::new(sphere);
+            lights.add(sphere.clone());
+            self.world.add(sphere);
+        }
+
+        let defaults = ImageDescription::default();
+        let camera = self.camera.take();
+        let (vfov, aspect_ratio, transform) = match camera {
+            Some((index, transform)) => {
+                let info = self
+                    .document
+                    .cameras
+                    .get(index)
+                    .ok_or_else(|| invalid_data(format!("camera {} does not exist", index)))?;
+                match &info.perspective {
+                    Some(perspective) => (
+                        perspective.yfov.to_degrees(),
+                        perspective.aspect_ratio.unwrap_or(defaults.aspect_ratio),
+                        transform,
+                    ),
+                    None => {
+                        let message =
+                            format!("{} cameras are rendered as perspective cameras", info.ty);
+                        self.warn(message);
+                        (40.0, defaults.aspect_ratio, transform)
+                    }
+                }
+            }
+            None => {
+                self.warn(String::from("the scene has no camera, framing all objects"));
+                let vfov = 40.0;
+                let transform = super::framing_transform(&self.bounds, vfov);
+                (vfov, defaults.aspect_ratio, transform)
+            }
+        };
+        let axis = |v: vec3::Vec3| transform.vector(v).unit_vector();
+        let camera = LensCamera::from_basis(
+            transform.point(vec3::Point3::fill(0.0)),
+            axis(vec3::Vec3(1.0, 0.0, 0.0)),
+            axis(vec3::Vec3(0.0, 1.0, 0.0)),
+            axis(vec3::Vec3(0.0, 0.0, 1.0)),
+            vfov,
+            aspect_ratio,
+            0.0,
+            1.0,
+        );
+
+        let background = if self.lights.is_empty() && !self.emissive {
+            Background::Sky
+        } else {
+            Background::Color(vec3::Color::fill(0.0))
+        };
+        Ok(ImportedScene {
+            world: self.world,
+            lights,
+            camera,
+            image: ImageSettings::new(
+                defaults.width,
+                aspect_ratio,
+                defaults.samples,
+                defaults.max_depth,
+            ),
+            background,
+            sampler: None,
+            output: None,
+            warnings: self.warnings,
+        })
+    }
+}
+
+// 解析 .gltf 或 .glb 场景，外部文件的路径相对于 path 所在的目录
+pub fn parse(bytes: &[u8], path: &Path) -> io::Result<ImportedScene> {
+    let (json, bin) = if bytes.starts_with(b"glTF") {
+        split_glb(bytes)?
+    } else {
+        (bytes, None)
+    };
+    let document: Document =
+        serde_json::from_slice(json).map_err(|e| invalid_data(e.to_string()))?;
+    if let Some(extension) = document
+        .extensions_required
+        .iter()
+        .find(|e| !EXTENSIONS.contains(&e.as_str()))
+    {
+        return Err(invalid_data(format!(
+            "required extension {} is not supported",
+            extension
+        )));
+    }
+
+    let mut importer = Importer {
+        document,
+        directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        buffers: Vec::new(),
+        materials: Vec::new(),
+        textures: HashMap::new(),
+        world: HittableList::new(),
+        bounds: Aabb::empty(),
+        camera: None,
+        lights: Vec::new(),
+        emissive: false,
+        warnings: Vec::new(),
+    };
+    for (i, buffer) in importer.document.buffers.iter().enumerate() {
+        let data = match (&buffer.uri, bin) {
+            (Some(uri), _) => importer.read_uri(uri)?,
+            (None, Some(bin)) if i == 0 => bin.to_vec(),
+            (None, _) => return Err(invalid_data(format!("buffer {} has no data", i))),
+        };
+        importer.buffers.push(data);
+    }
+
+    // 没有指定场景时使用第一个场景，没有场景时使用所有根节点
+    let roots = match importer.document.scene.or(Some(0)) {
+        Some(scene) if scene < importer.document.scenes.len() => {
+            importer.document.scenes[scene].nodes.clone()
+        }
+        _ => {
+            let mut is_root = vec![true; importer.document.nodes.len()];
+            for node in &importer.document.nodes {
+                for &child in &node.children {
+                    if let Some(root) = is_root.get_mut(child) {
+                        *root = false;
+                    }
+                }
+            }
+            (0..is_root.len()).filter(|&i| is_root[i]).collect()
+        }
+    };
+    for root in roots {
+        importer.node(root, &Transform::identity(), 0)?;
+    }
+    importer.finish()
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImportedScene> {
+    let path = path.as_ref();
+    parse(&fs::read(path)?, path)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::camera::RayGenerator;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+
+    // 一个 z = 0 平面上的正方形（两个三角形）、一个相机与一个点光源
+    fn scene() -> String {
+        let mut buffer = Vec::new();
+        for v in [
+            [-1.0f32, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ] {
+            for x in v {
+                buffer.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        for uv in [[0.0f32, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]] {
+            for x in uv {
+                buffer.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        for i in [0u16, 1, 2, 0, 2, 3] {
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+        const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut data = String::new();
+        for chunk in buffer.chunks(3) {
+            let bits = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    data.push(BASE64[(bits >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    data.push('=');
+                }
+            }
+        }
+
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0, 1] }}],
+                "nodes": [
+                    {{ "mesh": 0, "translation": [0, 0, -2], "scale": [2, 2, 2] }},
+                    {{ "children": [2, 3], "rotation": [0, 0.7071068, 0, 0.7071068] }},
+                    {{ "camera": 0, "rotation": [0, -0.7071068, 0, 0.7071068] }},
+                    {{ "extensions": {{ "KHR_lights_punctual": {{ "light": 0 }} }} }}
+                ],
+                "meshes": [{{ "primitives": [{{
+                    "attributes": {{ "POSITION": 0, "TEXCOORD_0": 1 }},
+                    "indices": 2,
+                    "material": 0
+                }}] }}],
+                "materials": [{{
+                    "pbrMetallicRoughness": {{ "baseColorFactor": [0.8, 0.2, 0.2, 1], "metallicFactor": 0 }},
+                    "emissiveFactor": [1, 1, 1],
+                    "extensions": {{ "KHR_materials_emissive_strength": {{ "emissiveStrength": 3 }} }}
+                }}],
+                "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 0.5, "aspectRatio": 2, "znear": 0.1 }} }}],
+                "extensions": {{ "KHR_lights_punctual": {{ "lights": [
+                    {{ "type": "point", "intensity": 683 }}
+                ] }} }},
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" }},
+                    {{ "bufferView": 0, "byteOffset": 48, "componentType": 5126, "count": 4, "type": "VEC2" }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR" }}
+                ],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 80 }},
+                    {{ "buffer": 0, "byteOffset": 80, "byteLength": 12 }}
+                ],
+                "buffers": [{{ "byteLength": 92, "uri": "data:application/octet-stream;base64,{}" }}]
+            }}"#,
+            data
+        )
+    }
+
+    #[test]
+    fn test_parse_gltf() {
+        let path = Path::new("scene.gltf");
+        let scene = parse(scene().as_bytes(), path).unwrap();
+        assert_eq!(scene.world.objects.len(), 2);
+        assert_eq!((scene.image.width, scene.image.height), (400, 200));
+        assert_eq!(scene.warnings.len(), 1);
+
+        // 平移与缩放后正方形位于 z = -2，边长为 4
+        let mut rec = HitRecord::new();
+        let r = Ray::new(vec3::Vec3(1.5, 1.5, 0.0), vec3::Vec3(0.0, 0.0, -1.0));
+        assert!(scene.world.hit(&r, 0.0, f64::INFINITY, &mut rec));
+        assert!((rec.t - 2.0).abs() < 1e-6);
+        assert!((rec.u - 0.875).abs() < 1e-6 && (rec.v - 0.875).abs() < 1e-6);
+        assert_eq!(
+            rec.mat_ptr.emitted(rec.u, rec.v, &rec.p).to_array(),
+            [3.0, 3.0, 3.0]
+        );
+
+        // 两次旋转相互抵消，相机位于原点看向 -z
+        let mut sampler = IndependentSampler::new();
+        let r = scene.camera.get_ray(0.5, 0.5, &mut sampler);
+        assert!((r.direction.unit_vector().z() + 1.0).abs() < 1e-6);
+        let r = scene.camera.get_ray(1.0, 0.5, &mut sampler);
+        assert!(r.direction.x() > 0.0);
+
+        let glb_error = parse(b"glTF\x01\x00\x00\x00", path).err().unwrap();
+        assert!(glb_error.to_string().contains("2.0"));
+        let required = r#"{ "extensionsRequired": ["KHR_draco_mesh_compression"] }"#;
+        assert!(parse(required.as_bytes(), path).is_err());
+    }
+}
@@ -0,0 +1,958 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use roxmltree::{Document, Node};
+
+use super::{obj, ply, ImportedScene, MeshData, METALS};
+use crate::aabb::Aabb;
+use crate::camera::LensCamera;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::integrator::Background;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, MetallicRoughness};
+use crate::quad::Quad;
+use crate::renderer::ImageSettings;
+use crate::scene::ImageDescription;
+use crate::sphere::Sphere;
+use crate::texture::ImageTexture;
+use crate::transform::Transform;
+use crate::vec3;
+
+// Mitsuba 0.6 与 3 场景文件（XML）的常用子集：
+//   perspective/thinlens 传感器及其 film 与 sampler、积分器的 max_depth
+//   shape obj/ply/sphere/rectangle/cube，bsdf diffuse/conductor/dielectric/plastic
+//   及其粗糙版本与 twosided，area/constant/envmap 光源，default、include、ref 与各种变换
+// 0.6 的驼峰式参数名（toWorld、sampleCount 等）按 3 的写法处理，
+// 其余内容被忽略或近似，并记录在 warnings 中
+
+// 常见介质的折射率
+const IORS: [(&str, f64); 12] = [
+    ("vacuum", 1.0),
+    ("air", 1.000277),
+    ("water", 1.3330),
+    ("water ice", 1.31),
+    ("ethanol", 1.361),
+    ("fused quartz", 1.458),
+    ("pyrex", 1.470),
+    ("acrylic glass", 1.49),
+    ("polypropylene", 1.49),
+    ("bk7", 1.5046),
+    ("sodium chloride", 1.544),
+    ("diamond", 2.419),
+];
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// toWorld -> to_world，intIOR -> int_ior
+fn snake_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len() + 4);
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            result.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+// tag 为 tags 之一且名为 name 的子元素
+fn param<'a, 'input>(
+    node: Node<'a, 'input>,
+    tags: &[&str],
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| {
+        child.is_element()
+            && tags.contains(&child.tag_name().name())
+            && child.attribute("name").map(snake_case).as_deref() == Some(name)
+    })
+}
+
+fn elements<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == tag)
+}
+
+// Mitsuba 的 lookat 变换（相机坐标到世界坐标），相机的 +x 指向图像左侧
+fn look_at(origin: vec3::Point3, target: vec3::Point3, up: vec3::Vec3) -> Option<Transform> {
+    let dir = (target - origin).unit_vector();
+    let left = up.unit_vector().cross(dir);
+    if left.length() == 0.0 {
+        return None;
+    }
+    let left = left.unit_vector();
+    let new_up = dir.cross(left);
+    Some(Transform::new([
+        [left.x(), new_up.x(), dir.x(), origin.x()],
+        [left.y(), new_up.y(), dir.y(), origin.y()],
+        [left.z(), new_up.z(), dir.z(), origin.z()],
+        [0.0, 0.0, 0.0, 1.0],
+    ]))
+}
+
+struct Importer {
+    path: PathBuf,
+    version: u32,
+    defaults: HashMap<String, String>,
+    bsdfs: HashMap<String, Arc<dyn Material>>,
+    world: HittableList,
+    lights: HittableList,
+    bounds: Aabb,
+    camera: Option<LensCamera>,
+    image: Option<ImageSettings>,
+    sampler: Option<String>,
+    max_depth: Option<u64>,
+    background: Option<vec3::Color>,
+    warnings: Vec<String>,
+}
+
+impl Importer {
+    fn warn(&mut self, message: String) {
+        if !self.warnings.contains(&message) {
+            self.warnings.push(message);
+        }
+    }
+
+    fn error(&self, node: Node, message: &str) -> io::Error {
+        let pos = node.document().text_pos_at(node.range().start);
+        invalid_data(format!(
+            "{}:{}:{}: {}",
+            self.path.display(),
+            pos.row,
+            pos.col,
+            message
+        ))
+    }
+
+    // 属性值，$name 替换为 default 中的值
+    fn attribute(&self, node: Node, key: &str) -> io::Result<String> {
+        let value = node
+            .attribute(key)
+            .ok_or_else(|| self.error(node, &format!("missing attribute `{}`", key)))?;
+        if !value.contains('$') {
+            return Ok(value.to_string());
+        }
+        let mut names: Vec<&String> = self.defaults.keys().collect();
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let mut value = value.to_string();
+        for name in names {
+            value = value.replace(&format!("${}", name), &self.defaults[name]);
+        }
+        Ok(value)
+    }
+
+    fn numbers(&self, node: Node, text: &str) -> io::Result<Vec<f64>> {
+        text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| self.error(node, &format!("invalid number `{}`", s)))
+            })
+            .collect()
+    }
+
+    fn number(&self, node: Node, key: &str) -> io::Result<f64> {
+        match self.numbers(node, &self.attribute(node, key)?)?.as_slice() {
+            [value] => Ok(*value),
+            _ => Err(self.error(node, &format!("expected a single number in `{}`", key))),
+        }
+    }
+
+    fn float(&self, node: Node, name: &str) -> io::Result<Option<f64>> {
+        match param(node, &["float", "integer"], name) {
+            Some(p) => self.number(p, "value").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, node: Node, name: &str) -> io::Result<Option<String>> {
+        match param(node, &["string"], name) {
+            Some(p) => self.attribute(p, "value").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn boolean(&self, node: Node, name: &str) -> io::Result<bool> {
+        match param(node, &["boolean"], name) {
+            Some(p) => Ok(self.attribute(p, "value")? == "true"),
+            None => Ok(false),
+        }
+    }
+
+    // 由 x/y/z 属性或 value 中的三个数给出的向量，缺少的分量取 default
+    fn vector(&self, node: Node, default: f64) -> io::Result<vec3::Vec3> {
+        if node.attribute("value").is_some() {
+            return match self
+                .numbers(node, &self.attribute(node, "value")?)?
+                .as_slice()
+            {
+                [v] => Ok(vec3::Vec3::fill(*v)),
+                [x, y, z] => Ok(vec3::Vec3(*x, *y, *z)),
+                _ => Err(self.error(node, "expected one or three numbers")),
+            };
+        }
+        let mut v = [default; 3];
+        for (value, key) in v.iter_mut().zip(["x", "y", "z"]) {
+            if node.attribute(key).is_some() {
+                *value = self.number(node, key)?;
+            }
+        }
+        Ok(vec3::Vec3(v[0], v[1], v[2]))
+    }
+
+    fn point(&self, node: Node, key: &str) -> io::Result<vec3::Vec3> {
+        match self.numbers(node, &self.attribute(node, key)?)?.as_slice() {
+            [x, y, z] => Ok(vec3::Vec3(*x, *y, *z)),
+            _ => Err(self.error(node, &format!("expected three numbers in `{}`", key))),
+        }
+    }
+
+    // rgb、srgb、spectrum 或 float 参数，纹理以灰色代替
+    fn color(&mut self, node: Node, name: &str) -> io::Result<Option<vec3::Color>> {
+        let p = match param(node, &["rgb", "srgb", "spectrum", "float", "texture"], name) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let tag = p.tag_name().name();
+        if tag == "texture" {
+            self.warn(String::from("textures are not supported, using gray"));
+            return Ok(Some(vec3::Color::fill(0.5)));
+        }
+        if tag == "spectrum" && p.attribute("filename").is_some() {
+            self.warn(String::from("spectrum files are not supported, using gray"));
+            return Ok(Some(vec3::Color::fill(0.5)));
+        }
+        let value = self.attribute(p, "value")?;
+        // 按波长给出的光谱取平均值
+        if tag == "spectrum" && value.contains(':') {
+            let samples = value
+                .split(',')
+                .map(|pair| match pair.split_once(':') {
+                    Some((_, v)) => self
+                        .numbers(p, v)
+                        .map(|v| v.first().copied().unwrap_or(0.0)),
+                    None => Err(self.error(p, &format!("invalid spectrum `{}`", value))),
+                })
+                .collect::<io::Result<Vec<f64>>>()?;
+            let average = samples.iter().sum::<f64>() / samples.len().max(1) as f64;
+            return Ok(Some(vec3::Color::fill(average)));
+        }
+        let color = match self.numbers(p, &value)?.as_slice() {
+            [v] => vec3::Color::fill(*v),
+            [r, g, b] => vec3::Vec3(*r, *g, *b),
+            _ => return Err(self.error(p, "expected one or three numbers")),
+        };
+        if tag == "srgb" {
+            let linear = |c: f64| {
+                if c <= 0.04045 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            return Ok(Some(vec3::Vec3(
+                linear(color.0),
+                linear(color.1),
+                linear(color.2),
+            )));
+        }
+        Ok(Some(color))
+    }
+
+    // 折射率，可以是数值或介质名称
+    fn ior(&mut self, node: Node, name: &str, default: f64) -> io::Result<f64> {
+        if let Some(ior) = self.float(node, name)? {
+            return Ok(ior);
+        }
+        let name = match self.string(node, name)? {
+            Some(name) => name,
+            None => return Ok(default),
+        };
+        match IORS.iter().find(|(medium, _)| *medium == name) {
+            Some((_, ior)) => Ok(*ior),
+            None => {
+                self.warn(format!("unknown medium `{}`", name));
+                Ok(default)
+            }
+        }
+    }
+
+    // transform 元素中的变换按出现顺序依次作用
+    fn transform(&self, node: Node) -> io::Result<Transform> {
+        let mut transform = Transform::identity();
+        for child in node.children().filter(|c| c.is_element()) {
+            let step = match child.tag_name().name() {
+                "translate" => Transform::translate(self.vector(child, 0.0)?),
+                "scale" => Transform::scale(self.vector(child, 1.0)?),
+                "rotate" => {
+                    let axis = if child.attribute("axis").is_some() {
+                        self.point(child, "axis")?
+                    } else {
+                        self.vector(child, 0.0)?
+                    };
+                    if axis.length() == 0.0 {
+                        return Err(self.error(child, "rotation without an axis"));
+                    }
+                    Transform::rotate(self.number(child, "angle")?, axis)
+                }
+                "matrix" => {
+                    let values = self.numbers(child, &self.attribute(child, "value")?)?;
+                    if values.len() != 16 {
+                        return Err(self.error(child, "expected 16 numbers"));
+                    }
+                    let mut m = Transform::identity();
+                    for (i, value) in values.into_iter().enumerate() {
+                        m.m[i / 4][i % 4] = value;
+                    }
+                    m
+                }
+                "lookat" => {
+                    let up = match child.attribute("up") {
+                        Some(_) => self.point(child, "up")?,
+                        None => vec3::Vec3(0.0, 1.0, 0.0),
+                    };
+                    look_at(
+                        self.point(child, "origin")?,
+                        self.point(child, "target")?,
+                        up,
+                    )
+                    .ok_or_else(|| self.error(child, "degenerate lookat"))?
+                }
+                tag => return Err(self.error(child, &format!("unknown transform `{}`", tag))),
+            };
+            transform = step * transform;
+        }
+        Ok(transform)
+    }
+
+    fn to_world(&self, node: Node) -> io::Result<Transform> {
+        match param(node, &["transform"], "to_world") {
+            Some(t) => self.transform(t),
+            None => Ok(Transform::identity()),
+        }
+    }
+
+    // 路径相对于场景文件所在的目录
+    fn file(&self, node: Node) -> io::Result<PathBuf> {
+        let name = self
+            .string(node, "filename")?
+            .ok_or_else(|| self.error(node, "missing filename"))?;
+        Ok(self.path.parent().unwrap_or(Path::new("")).join(name))
+    }
+
+    fn scene(&mut self, root: Node) -> io::Result<()> {
+        for node in root.children().filter(|c| c.is_element()) {
+            match node.tag_name().name() {
+                "default" => {
+                    let name = self.attribute(node, "name")?;
+                    let value = self.attribute(node, "value")?;
+                    self.defaults.entry(name).or_insert(value);
+                }
+                "bsdf" => {
+                    let id = self.attribute(node, "id")?;
+                    let material = self.bsdf(node)?;
+                    self.bsdfs.insert(id, material);
+                }
+                "shape" => self.shape(node)?,
+                "sensor" => self.sensor(node)?,
+                "emitter" => self.emitter(node)?,
+                "integrator" => {
+                    // 积分器可能嵌套在 aov 等积分器中
+                    let depth = node
+                        .descendants()
+                        .filter(|n| n.tag_name().name() == "integrator")
+                        .find_map(|n| param(n, &["integer"], "max_depth"));
+                    if let Some(depth) = depth {
+                        let depth = self.number(depth, "value")?;
+                        self.max_depth = if depth < 0.0 {
+                            None
+                        } else {
+                            Some(depth as u64)
+                        };
+                    }
+                }
+                "include" => {
+                    let name = self.attribute(node, "filename")?;
+                    let path = self.path.parent().unwrap_or(Path::new("")).join(name);
+                    let text = fs::read_to_string(&path)
+                        .map_err(|e| self.error(node, &format!("{}: {}", path.display(), e)))?;
+                    let parent = std::mem::replace(&mut self.path, path);
+                    let result = parse_document(&text, &self.path)
+                        .and_then(|document| self.scene(document.root_element()));
+                    self.path = parent;
+                    result?;
+                }
+                tag => self.warn(format!("{} elements are not supported", tag)),
+            }
+        }
+        Ok(())
+    }
+
+    fn bsdf(&mut self, node: Node) -> io::Result<Arc<dyn Material>> {
+        let ty = self.attribute(node, "type")?;
+        let rough = ty.starts_with("rough");
+        let alpha = |importer: &Importer| -> io::Result<f64> {
+            match importer.float(node, "alpha")? {
+                Some(alpha) => Ok(alpha),
+                None => {
+                    let u = importer.float(node, "alpha_u")?;
+                    let v = importer.float(node, "alpha_v")?;
+                    Ok(match (u, v) {
+                        (Some(u), Some(v)) => 0.5 * (u + v),
+                        _ => 0.1,
+                    })
+                }
+            }
+        };
+        let material: Arc<dyn Material> = match ty.as_str() {
+            "twosided" | "bumpmap" | "normalmap" | "mask" => {
+                if ty != "twosided" {
+                    self.warn(format!("{} BSDFs are ignored", ty));
+                }
+                return self.material(node);
+            }
+            "diffuse" | "roughdiffuse" => {
+                let albedo = self.color(node, "reflectance")?;
+                Arc::new(Lambertian::new(albedo.unwrap_or(vec3::Color::fill(0.5))))
+            }
+            "conductor" | "roughconductor" => {
+                let eta = self.color(node, "eta")?;
+                let k = self.color(node, "k")?;
+                let name = self.string(node, "material")?;
+                let f0 = match (eta, k, name) {
+                    (Some(eta), Some(k), _) => {
+                        let f0 = |n: f64, k: f64| {
+                            ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k)
+                        };
+                        vec3::Vec3(f0(eta.0, k.0), f0(eta.1, k.1), f0(eta.2, k.2))
+                    }
+                    (_, _, Some(name)) if name == "none" => vec3::Color::fill(1.0),
+                    (_, _, name) => {
+                        // 0.6 的默认导体是铜，3 的默认导体是理想镜面
+                        let name = name.unwrap_or_else(|| {
+                            String::from(if self.version < 2 { "Cu" } else { "none" })
+                        });
+                        match METALS.iter().find(|(metal, _)| *metal == name) {
+                            Some((_, f0)) => vec3::Vec3(f0[0], f0[1], f0[2]),
+                            None if name == "none" => vec3::Color::fill(1.0),
+                            None => {
+                                self.warn(format!("unknown metal `{}`", name));
+                                vec3::Color::fill(0.9)
+                            }
+                        }
+                    }
+                };
+                let specular = self.color(node, "specular_reflectance")?;
+                let fuzz = if rough { alpha(self)? } else { 0.0 };
+                Arc::new(Metal::new(
+                    f0 * specular.unwrap_or(vec3::Color::fill(1.0)),
+                    fuzz,
+                ))
+            }
+            "dielectric" | "roughdielectric" | "thindielectric" => {
+                if rough {
+                    self.warn(String::from(
+                        "rough dielectrics are rendered as smooth dielectrics",
+                    ));
+                }
+                let int_ior = self.ior(node, "int_ior", 1.5046)?;
+                let ext_ior = self.ior(node, "ext_ior", 1.000277)?;
+                Arc::new(Dielectric::new(int_ior / ext_ior))
+            }
+            // 塑料的镜面层按法线方向反射率 0.04 的金属度-粗糙度材质近似
+            "plastic" | "roughplastic" => {
+                let albedo = self.color(node, "diffuse_reflectance")?;
+                let roughness = if rough { alpha(self)?.sqrt() } else { 0.0 };
+                Arc::new(MetallicRoughness::new(
+                    albedo.unwrap_or(vec3::Color::fill(0.5)),
+                    0.0,
+                    roughness,
+                ))
+            }
+            "principled" => {
+                let base_color = self.color(node, "base_color")?;
+                Arc::new(MetallicRoughness::new(
+                    base_color.unwrap_or(vec3::Color::fill(0.5)),
+                    self.float(node, "metallic")?.unwrap_or(0.0),
+                    self.float(node, "roughness")?.unwrap_or(0.5),
+                ))
+            }
+            _ => {
+                self.warn(format!("{} BSDFs are not supported, using diffuse", ty));
+                Arc::new(Lambertian::new(vec3::Color::fill(0.5)))
+            }
+        };
+        Ok(material)
+    }
+
+    // 元素内嵌的 bsdf 或引用的 bsdf，都没有时为灰色漫反射
+    fn material(&mut self, node: Node) -> io::Result<Arc<dyn Material>> {
+        for child in node.children().filter(|c| c.is_element()) {
+            match child.tag_name().name() {
+                "bsdf" => return self.bsdf(child),
+                "ref" => {
+                    let id = self.attribute(child, "id")?;
+                    if let Some(material) = self.bsdfs.get(&id) {
+                        return Ok(material.clone());
+                    }
+                    return Err(self.error(child, &format!("unknown reference `{}`", id)));
+                }
+                _ => {}
+            }
+        }
+        Ok(Arc::new(Lambertian::new(vec3::Color::fill(0.5))))
+    }
+
+    fn shape(&mut self, node: Node) -> io::Result<()> {
+        let ty = self.attribute(node, "type")?;
+        let to_world = self.to_world(node)?;
+        let emitter = elements(node, "emitter").next();
+        let material = match emitter {
+            Some(emitter) => {
+                let ty = self.attribute(emitter, "type")?;
+                if ty != "area" {
+                    return Err(self.error(
+                        emitter,
+                        &format!("{} emitters cannot be attached to shapes", ty),
+                    ));
+                }
+                let radiance = self.color(emitter, "radiance")?;
+                Arc::new(DiffuseLight::new(
+                    radiance.unwrap_or(vec3::Color::fill(1.0)),
+                ))
+            }
+            None => self.material(node)?,
+        };
+
+        let mesh = match ty.as_str() {
+            "obj" | "ply" => {
+                let path = self.file(node)?;
+                let mesh = if ty == "obj" {
+                    obj::load(&path)
+                } else {
+                    ply::load(&path)
+                };
+                let mut mesh =
+                    mesh.map_err(|e| self.error(node, &format!("{}: {}", path.display(), e)))?;
+                if self.boolean(node, "face_normals")? {
+                    mesh.normals.clear();
+                }
+                mesh
+            }
+            "rectangle" => {
+                let mut quad = Quad::new(
+                    to_world.point(vec3::Vec3(-1.0, -1.0, 0.0)),
+                    to_world.vector(vec3::Vec3(2.0, 0.0, 0.0)),
+                    to_world.vector(vec3::Vec3(0.0, 2.0, 0.0)),
+                );
+                quad.mat_ptr = material;
+                self.bounds = self.bounds.union(&quad.bounding_box());
+                let quad: Arc<dyn Hittable> = Arc::new(quad);
+                if emitter.is_some() {
+                    self.lights.add(quad.clone());
+                }
+                self.world.add(quad);
+                return Ok(());
+            }
+            "cube" => {
+                let vertices = (0..8)
+                    .map(|i| {
+                        let c = |bit: usize| if i >> bit & 1 == 1 { 1.0 } else { -1.0 };
+                        vec3::Vec3(c(0), c(1), c(2))
+                    })
+                    .collect();
+                // 每个面的四个角，按顶点下标的二进制位选取
+                let faces = [
+                    [0, 2, 6, 4],
+                    [1, 5, 7, 3],
+                    [0, 4, 5, 1],
+                    [2, 3, 7, 6],
+                    [0, 1, 3, 2],
+                    [4, 6, 7, 5],
+                ];
+                let indices = faces
+                    .iter()
+                    .flat_map(|f| [[f[0], f[1], f[2]], [f[0], f[2], f[3]]])
+                    .collect();
+                MeshData::new(vertices, Vec::new(), indices)
+            }
+            "sphere" => {
+                let center = match param(node, &["point"], "center") {
+                    Some(p) => self.vector(p, 0.0)?,
+                    None => vec3::Point3::fill(0.0),
+                };
+                let radius = self.float(node, "radius")?.unwrap_or(1.0);
+                let axes = [
+                    vec3::Vec3(1.0, 0.0, 0.0),
+                    vec3::Vec3(0.0, 1.0, 0.0),
+                    vec3::Vec3(0.0, 0.0, 1.0),
+                ];
+                let scales: Vec<f64> = axes.iter().map(|&a| to_world.vector(a).length()).collect();
+                if scales.iter().any(|s| (s / scales[0] - 1.0).abs() > 1e-6) {
+                    self.warn(String::from(
+                        "non-uniformly scaled spheres are approximated by spheres",
+                    ));
+                }
+                let scale = scales.iter().sum::<f64>() / 3.0;
+                let (center, radius) = (to_world.point(center), radius * scale);
+                let extent = vec3::Vec3::fill(radius);
+                self.bounds = self
+                    .bounds
+                    .union(&Aabb::new(center - extent, center + extent));
+                let mut sphere = Sphere::new(center, radius);
+                sphere.mat_ptr = material;
+                let sphere: Arc<dyn Hittable> = Arc::new(sphere);
+                if emitter.is_some() {
+                    self.lights.add(sphere.clone());
+                }
+                self.world.add(sphere);
+                return Ok(());
+            }
+            _ => {
+                self.warn(format!("{} shapes are not supported", ty));
+                return Ok(());
+            }
+        };
+
+        let mut mesh = mesh
+            .into_mesh(&to_world)
+            .map_err(|e| self.error(node, &e.to_string()))?;
+        self.bounds = self.bounds.union(&mesh.bounding_box());
+        mesh.mat_ptr = material;
+        self.world.add(Arc::new(mesh));
+        Ok(())
+    }
+
+    fn emitter(&mut self, node: Node) -> io::Result<()> {
+        let ty = self.attribute(node, "type")?;
+        let color = match ty.as_str() {
+            "constant" => self
+                .color(node, "radiance")?
+                .unwrap_or(vec3::Color::fill(1.0)),
+            "envmap" => {
+                self.warn(String::from(
+                    "environment maps are approximated by their average color",
+                ));
+                let path = self.file(node)?;
+                let average = fs::read(&path)
+                    .and_then(|bytes| ImageTexture::decode(&bytes, true))
+                    .map(|texture| texture.average());
+                let color = match average {
+                    Ok(color) => color,
+                    Err(e) => {
+                        self.warn(format!(
+                            "cannot load environment map `{}` ({}), using white",
+                            path.display(),
+                            e
+                        ));
+                        vec3::Color::fill(1.0)
+                    }
+                };
+                self.float(node, "scale")?.unwrap_or(1.0) * color
+            }
+            _ => {
+                self.warn(format!("{} emitters are not supported", ty));
+                return Ok(());
+            }
+        };
+        self.background = Some(self.background.unwrap_or(vec3::Color::fill(0.0)) + color);
+        Ok(())
+    }
+
+    fn sensor(&mut self, node: Node) -> io::Result<()> {
+        let ty = self.attribute(node, "type")?;
+        if ty != "perspective" && ty != "thinlens" {
+            self.warn(format!(
+                "{} sensors are rendered as perspective sensors",
+                ty
+            ));
+        }
+        let to_world = self.to_world(node)?;
+
+        let film = elements(node, "film").next();
+        let (width, height) = match film {
+            Some(film) => (
+                self.float(film, "width")?.unwrap_or(768.0),
+                self.float(film, "height")?.unwrap_or(576.0),
+            ),
+            None => (768.0, 576.0),
+        };
+        let (width, height) = (width.max(2.0) as usize, height.max(2.0) as usize);
+        let aspect_ratio = width as f64 / height as f64;
+
+        // 视角默认对应图像的宽度，焦距按 35mm 胶片换算
+        let fov = match self.float(node, "fov")? {
+            Some(fov) => fov,
+            None => {
+                let focal_length = self
+                    .string(node, "focal_length")?
+                    .unwrap_or_else(|| String::from("50mm"));
+                let millimeters: f64 =
+                    focal_length.trim_end_matches("mm").parse().map_err(|_| {
+                        self.error(node, &format!("invalid focal length `{}`", focal_length))
+                    })?;
+                2.0 * (18.0 / millimeters).atan().to_degrees()
+            }
+        };
+        let tan = (fov.to_radians() / 2.0).tan();
+        let axis = self
+            .string(node, "fov_axis")?
+            .unwrap_or_else(|| String::from("x"));
+        let tan_y = match axis.as_str() {
+            "y" => tan,
+            "diagonal" => tan / (1.0 + aspect_ratio * aspect_ratio).sqrt(),
+            "smaller" if aspect_ratio >= 1.0 => tan,
+            "larger" if aspect_ratio < 1.0 => tan,
+            "x" | "smaller" | "larger" => tan / aspect_ratio,
+            _ => return Err(self.error(node, &format!("unknown fov_axis `{}`", axis))),
+        };
+        let vfov = 2.0 * tan_y.atan().to_degrees();
+
+        // 针孔相机的对焦距离不影响成像，取 1 使光线方向保持单位长度左右
+        let (aperture, focus_distance) = if ty == "thinlens" {
+            let radius = self.float(node, "aperture_radius")?.unwrap_or(0.0);
+            let distance = self.float(node, "focus_distance")?.unwrap_or(1.0);
+            (2.0 * radius, distance)
+        } else {
+            (0.0, 1.0)
+        };
+        let direction = |v: vec3::Vec3| to_world.vector(v).unit_vector();
+        self.camera = Some(LensCamera::from_basis(
+            to_world.point(vec3::Point3::fill(0.0)),
+            -direction(vec3::Vec3(1.0, 0.0, 0.0)),
+            direction(vec3::Vec3(0.0, 1.0, 0.0)),
+            -direction(vec3::Vec3(0.0, 0.0, 1.0)),
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_distance,
+        ));
+
+        let mut samples = 4;
+        if let Some(sampler) = elements(node, "sampler").next() {
+            samples = self.float(sampler, "sample_count")?.unwrap_or(4.0).max(1.0) as usize;
+            let ty = self.attribute(sampler, "type")?;
+            self.sampler = match ty.as_str() {
+                "independent" => Some("independent"),
+                "stratified" | "multijitter" => Some("stratified"),
+                "ldsampler" | "sobol" => Some("sobol"),
+                "halton" | "hammersley" => Some("halton"),
+                _ => {
+                    self.warn(format!("unknown sampler `{}`", ty));
+                    None
+                }
+            }
+            .map(str::to_string);
+        }
+        let mut image = ImageSettings::new(width, aspect_ratio, samples, 0);
+        image.height = height;
+        self.image = Some(image);
+        Ok(())
+    }
+
+    fn finish(mut self) -> ImportedScene {
+        let defaults = ImageDescription::default();
+        let camera = match self.camera.take() {
+            Some(camera) => camera,
+            None => {
+                self.warn(String::from("the scene has no sensor, framing all objects"));
+                let vfov = 40.0;
+                let transform = super::framing_transform(&self.bounds, vfov);
+                LensCamera::from_basis(
+                    transform.point(vec3::Point3::fill(0.0)),
+                    vec3::Vec3(1.0, 0.0, 0.0),
+                    vec3::Vec3(0.0, 1.0, 0.0),
+                    vec3::Vec3(0.0, 0.0, 1.0),
+                    vfov,
+                    defaults.aspect_ratio,
+                    0.0,
+                    1.0,
+                )
+            }
+        };
+        let mut image = self.image.unwrap_or_else(|| {
+            ImageSettings::new(defaults.width, defaults.aspect_ratio, defaults.samples, 0)
+        });
+        image.max_depth = self.max_depth.unwrap_or(defaults.max_depth);
+
+        ImportedScene {
+            world: self.world,
+            lights: self.lights,
+            camera,
+            image,
+            background: Background::Color(self.background.unwrap_or(vec3::Color::fill(0.0))),
+            sampler: self.sampler,
+            output: None,
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn parse_document<'input>(text: &'input str, path: &Path) -> io::Result<Document<'input>> {
+    let document =
+        Document::parse(text).map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+    if document.root_element().tag_name().name() != "scene" {
+        return Err(invalid_data(format!(
+            "{}: expected a <scene> element",
+            path.display()
+        )));
+    }
+    Ok(document)
+}
+
+// 解析 Mitsuba 场景，include 与网格文件的路径相对于 path 所在的目录
+pub fn parse(text: &str, path: &Path) -> io::Result<ImportedScene> {
+    let document = parse_document(text, path)?;
+    let root = document.root_element();
+    let version = root
+        .attribute("version")
+        .and_then(|v| v.split('.').next())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut importer = Importer {
+        path: path.to_path_buf(),
+        version,
+        defaults: HashMap::new(),
+        bsdfs: HashMap::new(),
+        world: HittableList::new(),
+        lights: HittableList::new(),
+        bounds: Aabb::empty(),
+        camera: None,
+        image: None,
+        sampler: None,
+        max_depth: None,
+        background: None,
+        warnings: Vec::new(),
+    };
+    importer.scene(root)?;
+    Ok(importer.finish())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImportedScene> {
+    let path = path.as_ref();
+    parse(&fs::read_to_string(path)?, path)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::camera::RayGenerator;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+
+    const SCENE: &str = r#"
+        <scene version="0.6.0">
+            <default name="spp" value="8"/>
+            <integrator type="path">
+                <integer name="maxDepth" value="4"/>
+            </integrator>
+            <sensor type="perspective">
+                <float name="fov" value="60"/>
+                <transform name="toWorld">
+                    <lookat origin="0, 0, 5" target="0, 0, 0" up="0, 1, 0"/>
+                </transform>
+                <sampler type="ldsampler">
+                    <integer name="sampleCount" value="$spp"/>
+                </sampler>
+                <film type="hdrfilm">
+                    <integer name="width" value="40"/>
+                    <integer name="height" value="20"/>
+                </film>
+            </sensor>
+            <bsdf type="twosided" id="gold">
+                <bsdf type="roughconductor">
+                    <string name="material" value="Au"/>
+                    <float name="alpha" value="0.2"/>
+                </bsdf>
+            </bsdf>
+            <shape type="cube">
+                <transform name="toWorld">
+                    <scale value="0.5"/>
+                    <translate x="-1"/>
+                </transform>
+                <ref id="gold"/>
+            </shape>
+            <shape type="sphere">
+                <point name="center" x="1" y="0" z="0"/>
+                <float name="radius" value="0.25"/>
+                <bsdf type="dielectric">
+                    <string name="intIOR" value="water"/>
+                </bsdf>
+            </shape>
+            <shape type="rectangle">
+                <transform name="toWorld">
+                    <rotate x="1" angle="90"/>
+                    <translate y="2"/>
+                </transform>
+                <emitter type="area">
+                    <rgb name="radiance" value="4, 4, 4"/>
+                </emitter>
+            </shape>
+            <emitter type="constant">
+                <spectrum name="radiance" value="400:0.1, 700:0.3"/>
+            </emitter>
+            <emitter type="point"/>
+        </scene>
+    "#;
+
+    #[test]
+    fn test_parse_mitsuba() {
+        let scene = parse(SCENE, Path::new("scene.xml")).unwrap();
+        assert_eq!((scene.image.width, scene.image.height), (40, 20));
+        assert_eq!(scene.image.samples_per_pixel, 8);
+        assert_eq!(scene.image.max_depth, 4);
+        assert_eq!(scene.sampler.as_deref(), Some("sobol"));
+        assert_eq!(scene.world.objects.len(), 3);
+        assert_eq!(scene.warnings.len(), 1);
+        match scene.background {
+            Background::Color(color) => assert!((color.x() - 0.2).abs() < 1e-12),
+            Background::Sky => panic!("expected a constant background"),
+        }
+
+        // 缩放后平移的立方体的右侧面位于 x = -0.5
+        let mut rec = HitRecord::new();
+        let r = Ray::new(vec3::Vec3(0.0, 0.0, 0.0), vec3::Vec3(-1.0, 0.0, 0.0));
+        assert!(scene.world.hit(&r, 0.0, f64::INFINITY, &mut rec));
+        assert!((rec.t - 0.5).abs() < 1e-6);
+
+        let light = Ray::new(vec3::Vec3(0.0, 0.0, 0.0), vec3::Vec3(0.0, 1.0, 0.0));
+        assert!(scene.world.hit(&light, 0.0, f64::INFINITY, &mut rec));
+        assert!((rec.t - 2.0).abs() < 1e-6);
+        assert_eq!(
+            rec.mat_ptr.emitted(rec.u, rec.v, &rec.p).to_array(),
+            [4.0, 4.0, 4.0]
+        );
+
+        // 水平视角为 60 度，图像右侧对应世界坐标的 +x
+        let mut sampler = IndependentSampler::new();
+        let r = scene.camera.get_ray(1.0, 0.5, &mut sampler);
+        let d = r.direction.unit_vector();
+        assert!((d.x() / -d.z() - (30.0f64).to_radians().tan()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mitsuba_errors() {
+        let path = Path::new("scene.xml");
+        assert!(parse("<scene><shape", path).is_err());
+        assert!(parse("<sphere/>", path).is_err());
+        let error = parse(
+            "<scene>\n  <shape type=\"cube\"><ref id=\"missing\"/></shape>\n</scene>",
+            path,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().starts_with("scene.xml:2:"));
+    }
+}
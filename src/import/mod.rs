@@ -0,0 +1,212 @@
+use std::convert::TryFrom;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::camera::LensCamera;
+use crate::hittable_list::HittableList;
+use crate::integrator::{Background, Recursive};
+use crate::material::Lambertian;
+use crate::mesh::TriangleMesh;
+use crate::renderer::{ImageSettings, Renderer};
+use crate::sampler;
+use crate::scene::ImageDescription;
+use crate::texture;
+use crate::transform::Transform;
+use crate::vec3;
+
+pub mod gltf;
+pub mod mitsuba;
+pub mod obj;
+pub mod pbrt;
+pub mod ply;
+
+// 常见金属在法线方向的反射率
+pub const METALS: [(&str, [f64; 3]); 5] = [
+    ("Ag", [0.972, 0.960, 0.915]),
+    ("Al", [0.913, 0.922, 0.924]),
+    ("Au", [1.000, 0.766, 0.336]),
+    ("Cu", [0.955, 0.638, 0.538]),
+    ("CuZn", [0.910, 0.778, 0.423]),
+];
+
+// 导入的三角形网格数据，以单精度坐标与 32 位下标存放，颜色分量在 [0, 1] 内，
+// normals、colors 与 uvs 为空表示没有对应的属性
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl MeshData {
+    pub fn new(
+        vertices: Vec<vec3::Point3>,
+        normals: Vec<vec3::Vec3>,
+        indices: Vec<[usize; 3]>,
+    ) -> Self {
+        MeshData {
+            vertices: vertices.iter().map(vec3::Vec3::to_f32_array).collect(),
+            normals: normals.iter().map(vec3::Vec3::to_f32_array).collect(),
+            indices: indices
+                .iter()
+                .map(|t| t.map(|i| u32::try_from(i).unwrap_or(u32::MAX)))
+                .collect(),
+            ..MeshData::default()
+        }
+    }
+
+    // 原地变换到世界坐标后构造网格，法向量的逆转置矩阵只计算一次
+    pub fn into_mesh(mut self, transform: &Transform) -> io::Result<TriangleMesh> {
+        let vertices = self.vertices.len();
+        if let Some(&i) = self
+            .indices
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= vertices)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("vertex index {} out of range", i),
+            ));
+        }
+        if self.normals.len() == vertices {
+            let normal_transform = transform
+                .inverse()
+                .map_or(Transform::identity(), |m| m.transpose());
+            for n in &mut self.normals {
+                *n = normal_transform
+                    .vector((*n).into())
+                    .unit_vector()
+                    .to_f32_array();
+            }
+        } else {
+            self.normals.clear();
+        }
+        for p in &mut self.vertices {
+            *p = transform.point((*p).into()).to_f32_array();
+        }
+        let mut mesh = TriangleMesh::new(self.vertices, self.normals, self.indices);
+        if self.uvs.len() == vertices {
+            mesh.uvs = self.uvs;
+        }
+        Ok(mesh)
+    }
+}
+
+// 从其他渲染器的场景文件导入的场景，lights 为 world 中可以被采样的光源，
+// warnings 记录无法转换而被忽略或近似的内容
+pub struct ImportedScene {
+    pub world: HittableList,
+    pub lights: HittableList,
+    pub camera: LensCamera,
+    pub image: ImageSettings,
+    pub background: Background,
+    pub sampler: Option<String>,
+    pub output: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ImportedScene {
+    // 文件中没有指定 output 时写出到 output
+    pub fn renderer(self, output: &str) -> io::Result<Renderer> {
+        let image = self.image;
+        image.validate()?;
+        let mut integrator = Recursive::new();
+        integrator.background = self.background;
+        if !self.lights.objects.is_empty() {
+            integrator.lights = Some(Arc::new(self.lights));
+        }
+        let mut renderer = Renderer::new(
+            Arc::new(self.world),
+            Arc::new(self.camera),
+            image,
+            Arc::new(integrator),
+            self.output.as_deref().unwrap_or(output),
+        );
+        if let Some(sampler) = self
+            .sampler
+            .and_then(|name| sampler::from_name(&name, image.samples_per_pixel))
+        {
+            renderer.sampler = sampler;
+        }
+        Ok(renderer)
+    }
+}
+
+// 按扩展名选择导入器
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImportedScene> {
+    let path = path.as_ref();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("gltf") | Some("glb") => gltf::load(path),
+        Some("pbrt") => pbrt::load(path),
+        Some("ply") => load_ply(path),
+        Some("xml") => mitsuba::load(path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unknown scene format, expected .json, .pbrt, .xml, .gltf, .glb or .ply",
+        )),
+    }
+}
+
+// 从 +z 方向看向整个包围盒的相机，返回相机坐标到世界坐标的变换
+pub fn framing_transform(bounds: &Aabb, vfov: f64) -> Transform {
+    let size = (bounds.max - bounds.min).length();
+    let (center, size) = if size.is_finite() {
+        (bounds.centroid(), size.max(1e-3))
+    } else {
+        (vec3::Point3::fill(0.0), 1.0)
+    };
+    let distance = 0.5 * size / (vfov.to_radians() / 2.0).sin();
+    Transform::translate(center + vec3::Vec3(0.0, 0.0, distance))
+}
+
+// 单独的 PLY 网格在天空背景下渲染，有顶点颜色时以 sRGB 编码的顶点颜色为反照率，
+// 否则使用灰色漫反射材质
+fn load_ply(path: &Path) -> io::Result<ImportedScene> {
+    let mut mesh = ply::load(path)?;
+    let colors = std::mem::take(&mut mesh.colors);
+    let mut triangles = mesh.into_mesh(&Transform::identity())?;
+    triangles.mat_ptr = Arc::new(Lambertian::new(vec3::Color::fill(0.5)));
+    if colors.len() == triangles.vertices.len() {
+        triangles.colors = colors
+            .iter()
+            .map(|c| c.map(|x| texture::srgb_to_linear(x as f64) as f32))
+            .collect();
+    }
+
+    let defaults = ImageDescription::default();
+    let vfov = 40.0;
+    let transform = framing_transform(&triangles.bounding_box(), vfov);
+    let camera = LensCamera::from_basis(
+        transform.point(vec3::Point3::fill(0.0)),
+        vec3::Vec3(1.0, 0.0, 0.0),
+        vec3::Vec3(0.0, 1.0, 0.0),
+        vec3::Vec3(0.0, 0.0, 1.0),
+        vfov,
+        defaults.aspect_ratio,
+        0.0,
+        1.0,
+    );
+    let mut world = HittableList::new();
+    world.add(Arc::new(triangles));
+
+    Ok(ImportedScene {
+        world,
+        lights: HittableList::new(),
+        camera,
+        image: ImageSettings::new(
+            defaults.width,
+            defaults.aspect_ratio,
+            defaults.samples,
+            defaults.max_depth,
+        ),
+        background: Background::Sky,
+        sampler: None,
+        output: None,
+        warnings: Vec::new(),
+    })
+}
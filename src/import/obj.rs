@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use super::MeshData;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// OBJ 的下标从 1 开始，负数表示相对于当前末尾的位置
+fn resolve(token: &str, len: usize, line: usize) -> io::Result<usize> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| invalid_data(format!("line {}: invalid index `{}`", line, token)))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(invalid_data(format!(
+            "line {}: index {} out of range",
+            line, index
+        )));
+    }
+    Ok(resolved as usize)
+}
+
+fn floats<const N: usize>(words: &[&str], line: usize) -> io::Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let word = words
+            .get(i)
+            .ok_or_else(|| invalid_data(format!("line {}: expected {} numbers", line, N)))?;
+        *value = word
+            .parse()
+            .map_err(|_| invalid_data(format!("line {}: invalid number `{}`", line, word)))?;
+    }
+    Ok(values)
+}
+
+// 读取 v、vt、vn 与 f 语句，多边形按扇形剖分为三角形，其余语句被忽略。
+// 位置、纹理坐标与法向量下标都相同的面顶点只存放一次
+pub fn read<R: BufRead>(reader: R) -> io::Result<MeshData> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut mesh = MeshData::default();
+    // 所有面顶点都带有纹理坐标或法向量时才保留
+    let (mut has_uvs, mut has_normals) = (true, true);
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = number + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["v", rest @ ..] => positions.push(floats::<3>(rest, number)?),
+            ["vt", rest @ ..] => uvs.push(floats::<2>(rest, number)?),
+            ["vn", rest @ ..] => normals.push(floats::<3>(rest, number)?),
+            ["f", rest @ ..] => {
+                let mut polygon = Vec::with_capacity(rest.len());
+                for word in rest {
+                    let mut parts = word.split('/');
+                    let p = resolve(parts.next().unwrap_or(""), positions.len(), number)?;
+                    let t = match parts.next() {
+                        Some(t) if !t.is_empty() => Some(resolve(t, uvs.len(), number)?),
+                        _ => None,
+                    };
+                    let n = match parts.next() {
+                        Some(n) if !n.is_empty() => Some(resolve(n, normals.len(), number)?),
+                        _ => None,
+                    };
+                    has_uvs &= t.is_some();
+                    has_normals &= n.is_some();
+                    let index = *vertices.entry((p, t, n)).or_insert_with(|| {
+                        mesh.vertices.push(positions[p]);
+                        mesh.uvs.push(t.map_or([0.0; 2], |t| uvs[t]));
+                        mesh.normals.push(n.map_or([0.0; 3], |n| normals[n]));
+                        (mesh.vertices.len() - 1) as u32
+                    });
+                    polygon.push(index);
+                }
+                for k in 1..polygon.len().saturating_sub(1) {
+                    mesh.indices.push([polygon[0], polygon[k], polygon[k + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !has_uvs {
+        mesh.uvs.clear();
+    }
+    if !has_normals {
+        mesh.normals.clear();
+    }
+    Ok(mesh)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<MeshData> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_read_obj() {
+        let obj = "# square\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+                   g square\nf 1/1/1 2/1/1 3/2/1 4/2/1\nf -4/1/1 -2/2/1 -1/2/1\n";
+        let mesh = read(obj.as_bytes()).unwrap();
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3], [0, 2, 3]]);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.uvs[2], [1.0, 1.0]);
+        assert_eq!(mesh.normals[3], [0.0, 0.0, 1.0]);
+
+        let mixed = read("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2 3\n".as_bytes()).unwrap();
+        assert!(mixed.normals.is_empty() && mixed.uvs.is_empty());
+
+        let error = read("v 0 0 0\nf 1 2 3\n".as_bytes()).err().unwrap();
+        assert!(error.to_string().starts_with("line 2:"));
+    }
+}
@@ -0,0 +1,1087 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{ply, ImportedScene, MeshData, METALS};
+use crate::camera::LensCamera;
+use crate::film::ImageFormat;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::integrator::Background;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::quad::Quad;
+use crate::renderer::ImageSettings;
+use crate::sphere::Sphere;
+use crate::transform::Transform;
+use crate::vec3;
+
+// pbrt (v3/v4) 场景文件的常用子集：
+//   Camera perspective、Film、Sampler、Integrator 的 maxdepth
+//   Shape sphere/trianglemesh/bilinearmesh/plymesh
+//   Material/MakeNamedMaterial diffuse/conductor/dielectric（以及 v3 的同类材质）
+//   LightSource infinite、AreaLightSource diffuse、常量纹理
+//   AttributeBegin/End、TransformBegin/End、ObjectBegin/End/Instance、Include 与各种变换
+// 其余内容被忽略或近似，并记录在 warnings 中
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Str(String),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    file: usize,
+    line: usize,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn tokenize(text: &str, file: usize, name: &Path) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(&c) = chars.peek() {
+        let kind = match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+                continue;
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '[' => {
+                chars.next();
+                TokenKind::Open
+            }
+            ']' => {
+                chars.next();
+                TokenKind::Close
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(invalid_data(format!(
+                                "{}:{}: unterminated string",
+                                name.display(),
+                                line
+                            )))
+                        }
+                        Some(c) => s.push(c),
+                    }
+                }
+                TokenKind::Str(s)
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"[]\"#".contains(c)) {
+                    s.push(c);
+                }
+                TokenKind::Word(s)
+            }
+        };
+        tokens.push(Token { kind, file, line });
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+}
+
+// 参数 "type name" value...
+#[derive(Debug, Clone)]
+struct Param {
+    ty: String,
+    name: String,
+    values: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ParamList(Vec<Param>);
+
+impl ParamList {
+    fn get(&self, name: &str) -> Option<&Param> {
+        self.0.iter().find(|p| p.name == name)
+    }
+
+    fn floats(&self, name: &str) -> Vec<f64> {
+        self.get(name).map_or_else(Vec::new, |p| {
+            p.values
+                .iter()
+                .filter_map(|v| match v {
+                    Value::Number(x) => Some(*x),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    fn float(&self, name: &str, default: f64) -> f64 {
+        self.floats(name).first().copied().unwrap_or(default)
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(|p| match p.values.first() {
+            Some(Value::Str(s)) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    fn points(&self, name: &str) -> Vec<vec3::Point3> {
+        self.floats(name)
+            .chunks_exact(3)
+            .map(|p| vec3::Vec3(p[0], p[1], p[2]))
+            .collect()
+    }
+
+    // 下标须为非负整数，是否超出顶点数由 MeshData::into_mesh 检查
+    fn indices(&self, name: &str) -> io::Result<Vec<usize>> {
+        self.floats(name)
+            .iter()
+            .map(|&i| {
+                if i >= 0.0 && i.fract() == 0.0 {
+                    Ok(i as usize)
+                } else {
+                    Err(invalid_data(format!(
+                        "invalid vertex index {} in `{}`",
+                        i, name
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+// AttributeBegin/End 保存与恢复的状态
+#[derive(Debug, Clone)]
+struct GraphicsState {
+    ctm: Transform,
+    material: Arc<dyn Material>,
+    area_light: Option<vec3::Color>,
+}
+
+// 记录下来的形状，ObjectInstance 时以新的变换重新创建
+#[derive(Debug, Clone)]
+struct ShapeEntity {
+    name: String,
+    params: ParamList,
+    ctm: Transform,
+    material: Arc<dyn Material>,
+    area_light: Option<vec3::Color>,
+    file: usize,
+}
+
+// 常见玻璃的折射率
+const GLASSES: [(&str, f64); 7] = [
+    ("glass-BK7", 1.5168),
+    ("glass-BAF10", 1.67),
+    ("glass-FK51A", 1.4866),
+    ("glass-LASF9", 1.85),
+    ("glass-SF5", 1.6727),
+    ("glass-SF10", 1.7283),
+    ("glass-SF11", 1.7847),
+];
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(vec3::Color::fill(0.5)))
+}
+
+struct Parser {
+    files: Vec<PathBuf>,
+    tokens: Vec<Token>,
+    pos: usize,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    transforms: Vec<Transform>,
+    coordinate_systems: HashMap<String, Transform>,
+    named_materials: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<String, vec3::Color>,
+    objects: HashMap<String, Vec<ShapeEntity>>,
+    current_object: Option<(String, Vec<ShapeEntity>)>,
+    camera: Option<(String, ParamList, Transform)>,
+    film: ParamList,
+    sampler: Option<(String, ParamList)>,
+    integrator: ParamList,
+    background: Option<vec3::Color>,
+    world: HittableList,
+    // 可以被采样的光源，同时也在 world 中
+    lights: HittableList,
+    warnings: Vec<String>,
+}
+
+impl Parser {
+    fn new(file: PathBuf, tokens: Vec<Token>) -> Self {
+        Parser {
+            files: vec![file],
+            tokens,
+            pos: 0,
+            state: GraphicsState {
+                ctm: Transform::identity(),
+                material: default_material(),
+                area_light: None,
+            },
+            stack: Vec::new(),
+            transforms: Vec::new(),
+            coordinate_systems: HashMap::new(),
+            named_materials: HashMap::new(),
+            textures: HashMap::new(),
+            objects: HashMap::new(),
+            current_object: None,
+            camera: None,
+            film: ParamList::default(),
+            sampler: None,
+            integrator: ParamList::default(),
+            background: None,
+            world: HittableList::new(),
+            lights: HittableList::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        if !self.warnings.contains(&message) {
+            self.warnings.push(message);
+        }
+    }
+
+    fn error(&self, token: Option<&Token>, message: &str) -> io::Error {
+        match token {
+            Some(token) => invalid_data(format!(
+                "{}:{}: {}",
+                self.files[token.file].display(),
+                token.line,
+                message
+            )),
+            None => invalid_data(format!("{}: {}", self.files[0].display(), message)),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        match self.next() {
+            Some(Token {
+                kind: TokenKind::Str(s),
+                ..
+            }) => Ok(s),
+            token => Err(self.error(token.as_ref(), "expected a quoted string")),
+        }
+    }
+
+    fn number(&mut self) -> io::Result<f64> {
+        let token = self.next();
+        if let Some(TokenKind::Word(word)) = token.as_ref().map(|t| &t.kind) {
+            if let Ok(x) = word.parse() {
+                return Ok(x);
+            }
+        }
+        Err(self.error(token.as_ref(), "expected a number"))
+    }
+
+    // count 个数，可以放在方括号里
+    fn numbers(&mut self, count: usize) -> io::Result<Vec<f64>> {
+        let bracketed = self.peek() == Some(&TokenKind::Open);
+        if bracketed {
+            self.pos += 1;
+        }
+        let values = (0..count)
+            .map(|_| self.number())
+            .collect::<io::Result<Vec<f64>>>()?;
+        if bracketed {
+            let token = self.next();
+            if token.as_ref().map(|t| &t.kind) != Some(&TokenKind::Close) {
+                return Err(self.error(token.as_ref(), "expected `]`"));
+            }
+        }
+        Ok(values)
+    }
+
+    fn matrix(&mut self) -> io::Result<Transform> {
+        let values = self.numbers(16)?;
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in values.into_iter().enumerate() {
+            m[i / 4][i % 4] = value;
+        }
+        // pbrt 按列给出矩阵
+        Ok(Transform::new(m).transpose())
+    }
+
+    fn value(&mut self, ty: &str) -> io::Result<Value> {
+        let token = self.next();
+        match token.as_ref().map(|t| &t.kind) {
+            Some(TokenKind::Str(s)) if ty == "bool" => Ok(Value::Bool(s == "true")),
+            Some(TokenKind::Str(s)) => Ok(Value::Str(s.clone())),
+            Some(TokenKind::Word(w)) if w == "true" || w == "false" => Ok(Value::Bool(w == "true")),
+            Some(TokenKind::Word(w)) => w
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| self.error(token.as_ref(), &format!("invalid value `{}`", w))),
+            _ => Err(self.error(token.as_ref(), "expected a parameter value")),
+        }
+    }
+
+    fn params(&mut self) -> io::Result<ParamList> {
+        let mut params = ParamList::default();
+        while let Some(TokenKind::Str(declaration)) = self.peek() {
+            let words: Vec<&str> = declaration.split_whitespace().collect();
+            if words.len() != 2 {
+                break;
+            }
+            let (ty, name) = (words[0].to_string(), words[1].to_string());
+            self.pos += 1;
+
+            let mut values = Vec::new();
+            if self.peek() == Some(&TokenKind::Open) {
+                self.pos += 1;
+                while self.peek().is_some() && self.peek() != Some(&TokenKind::Close) {
+                    values.push(self.value(&ty)?);
+                }
+                self.pos += 1;
+            } else {
+                values.push(self.value(&ty)?);
+            }
+            params.0.push(Param { ty, name, values });
+        }
+        Ok(params)
+    }
+
+    fn include(&mut self, token: &Token, name: &str) -> io::Result<()> {
+        let directory = self.files[token.file]
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let path = directory.join(name);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| self.error(Some(token), &format!("{}: {}", path.display(), e)))?;
+        let tokens = tokenize(&text, self.files.len(), &path)?;
+        self.files.push(path);
+        self.tokens.splice(self.pos..self.pos, tokens);
+        Ok(())
+    }
+
+    fn parse(&mut self) -> io::Result<()> {
+        while let Some(token) = self.next() {
+            let directive = match &token.kind {
+                TokenKind::Word(word) => word.clone(),
+                _ => return Err(self.error(Some(&token), "expected a directive")),
+            };
+            match directive.as_str() {
+                "Identity" => self.state.ctm = Transform::identity(),
+                "Translate" => {
+                    let v = self.numbers(3)?;
+                    self.state.ctm =
+                        self.state.ctm * Transform::translate(vec3::Vec3(v[0], v[1], v[2]));
+                }
+                "Scale" => {
+                    let v = self.numbers(3)?;
+                    self.state.ctm =
+                        self.state.ctm * Transform::scale(vec3::Vec3(v[0], v[1], v[2]));
+                }
+                "Rotate" => {
+                    let v = self.numbers(4)?;
+                    self.state.ctm =
+                        self.state.ctm * Transform::rotate(v[0], vec3::Vec3(v[1], v[2], v[3]));
+                }
+                "LookAt" => {
+                    let v = self.numbers(9)?;
+                    let look_at = look_at(
+                        vec3::Vec3(v[0], v[1], v[2]),
+                        vec3::Vec3(v[3], v[4], v[5]),
+                        vec3::Vec3(v[6], v[7], v[8]),
+                    )
+                    .ok_or_else(|| self.error(Some(&token), "degenerate LookAt"))?;
+                    self.state.ctm = self.state.ctm * look_at;
+                }
+                "Transform" => self.state.ctm = self.matrix()?,
+                "ConcatTransform" => self.state.ctm = self.state.ctm * self.matrix()?,
+                "CoordinateSystem" => {
+                    let name = self.string()?;
+                    self.coordinate_systems.insert(name, self.state.ctm);
+                }
+                "CoordSysTransform" => {
+                    let name = self.string()?;
+                    match self.coordinate_systems.get(&name) {
+                        Some(&ctm) => self.state.ctm = ctm,
+                        None => self.warn(format!("unknown coordinate system `{}`", name)),
+                    }
+                }
+                "TransformBegin" => self.transforms.push(self.state.ctm),
+                "TransformEnd" => match self.transforms.pop() {
+                    Some(ctm) => self.state.ctm = ctm,
+                    None => return Err(self.error(Some(&token), "unmatched TransformEnd")),
+                },
+                "AttributeBegin" => self.stack.push(self.state.clone()),
+                "AttributeEnd" => match self.stack.pop() {
+                    Some(state) => self.state = state,
+                    None => return Err(self.error(Some(&token), "unmatched AttributeEnd")),
+                },
+                "Camera" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    let camera_from_world = self.state.ctm;
+                    let world_from_camera = camera_from_world
+                        .inverse()
+                        .ok_or_else(|| self.error(Some(&token), "singular camera transform"))?;
+                    self.coordinate_systems
+                        .insert(String::from("camera"), world_from_camera);
+                    self.camera = Some((name, params, camera_from_world));
+                }
+                "Film" => {
+                    self.string()?;
+                    self.film = self.params()?;
+                }
+                "Sampler" => {
+                    let name = self.string()?;
+                    self.sampler = Some((name, self.params()?));
+                }
+                "Integrator" => {
+                    self.string()?;
+                    self.integrator = self.params()?;
+                }
+                "WorldBegin" => {
+                    self.state.ctm = Transform::identity();
+                    self.coordinate_systems
+                        .insert(String::from("world"), Transform::identity());
+                }
+                "WorldEnd" | "ReverseOrientation" => {}
+                "Include" | "Import" => {
+                    let name = self.string()?;
+                    self.include(&token, &name)?;
+                }
+                "Material" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    self.state.material = self.material(&name, &params);
+                }
+                "MakeNamedMaterial" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    let ty = params.string("type").unwrap_or("").to_string();
+                    let material = self.material(&ty, &params);
+                    self.named_materials.insert(name, material);
+                }
+                "NamedMaterial" => {
+                    let name = self.string()?;
+                    match self.named_materials.get(&name) {
+                        Some(material) => self.state.material = material.clone(),
+                        None => self.warn(format!("unknown named material `{}`", name)),
+                    }
+                }
+                "Texture" => {
+                    let name = self.string()?;
+                    let ty = self.string()?;
+                    let class = self.string()?;
+                    let params = self.params()?;
+                    match (ty.as_str(), class.as_str()) {
+                        ("spectrum", "constant") | ("color", "constant") => {
+                            if let Some(color) = self.color(&params, "value") {
+                                self.textures.insert(name, color);
+                            }
+                        }
+                        _ => self.warn(format!(
+                            "{} texture `{}` is not supported, using a constant instead",
+                            class, name
+                        )),
+                    }
+                }
+                "LightSource" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    self.light(&name, &params);
+                }
+                "AreaLightSource" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    if name != "diffuse" {
+                        self.warn(format!("{} area lights are not supported", name));
+                    }
+                    let emission = self.color(&params, "L").unwrap_or(vec3::Color::fill(1.0));
+                    self.state.area_light = Some(params.float("scale", 1.0) * emission);
+                }
+                "Shape" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    let shape = ShapeEntity {
+                        name,
+                        params,
+                        ctm: self.state.ctm,
+                        material: self.state.material.clone(),
+                        area_light: self.state.area_light,
+                        file: token.file,
+                    };
+                    match &mut self.current_object {
+                        Some((_, shapes)) => shapes.push(shape),
+                        None => {
+                            if let Some(object) = self.shape(&shape, &Transform::identity())? {
+                                self.world.add(object);
+                            }
+                        }
+                    }
+                }
+                "ObjectBegin" => {
+                    let name = self.string()?;
+                    self.stack.push(self.state.clone());
+                    self.current_object = Some((name, Vec::new()));
+                }
+                "ObjectEnd" => {
+                    if let Some((name, shapes)) = self.current_object.take() {
+                        self.objects.insert(name, shapes);
+                    }
+                    if let Some(state) = self.stack.pop() {
+                        self.state = state;
+                    }
+                }
+                "ObjectInstance" => {
+                    let name = self.string()?;
+                    let shapes = match self.objects.get(&name) {
+                        Some(shapes) => shapes.clone(),
+                        None => {
+                            self.warn(format!("unknown object `{}`", name));
+                            continue;
+                        }
+                    };
+                    let instance = self.state.ctm;
+                    for shape in &shapes {
+                        if let Some(object) = self.shape(shape, &instance)? {
+                            self.world.add(object);
+                        }
+                    }
+                }
+                "PixelFilter" | "Accelerator" | "MakeNamedMedium" | "ColorSpace" | "Attribute" => {
+                    self.string()?;
+                    self.params()?;
+                    self.warn(format!("{} is ignored", directive));
+                }
+                "Option" => {
+                    self.params()?;
+                }
+                "MediumInterface" => {
+                    self.string()?;
+                    if let Some(TokenKind::Str(_)) = self.peek() {
+                        self.pos += 1;
+                    }
+                    self.warn(format!("{} is ignored", directive));
+                }
+                "TransformTimes" => {
+                    self.numbers(2)?;
+                }
+                "ActiveTransform" => {
+                    self.next();
+                }
+                _ => {
+                    return Err(
+                        self.error(Some(&token), &format!("unknown directive `{}`", directive))
+                    )
+                }
+            }
+        }
+        if self.camera.is_none() {
+            return Err(self.error(None, "missing Camera"));
+        }
+        Ok(())
+    }
+
+    // 颜色参数，纹理只支持常量
+    fn color(&mut self, params: &ParamList, name: &str) -> Option<vec3::Color> {
+        let param = params.get(name)?;
+        let numbers = params.floats(name);
+        match param.ty.as_str() {
+            "rgb" | "color" if numbers.len() == 3 => {
+                Some(vec3::Vec3(numbers[0], numbers[1], numbers[2]))
+            }
+            "float" if !numbers.is_empty() => Some(vec3::Color::fill(numbers[0])),
+            // 黑体辐射按归一化后的白色处理
+            "blackbody" => Some(vec3::Color::fill(1.0)),
+            "spectrum" if numbers.len() >= 2 => {
+                let values: Vec<f64> = numbers.iter().skip(1).step_by(2).copied().collect();
+                Some(vec3::Color::fill(
+                    values.iter().sum::<f64>() / values.len() as f64,
+                ))
+            }
+            "spectrum" => match params.string(name) {
+                Some(s) if s.starts_with("stdillum") || s.starts_with("illum") => {
+                    Some(vec3::Color::fill(1.0))
+                }
+                Some(s) => {
+                    self.warn(format!("spectrum `{}` is not supported", s));
+                    None
+                }
+                None => None,
+            },
+            "texture" => {
+                let texture = params.string(name).unwrap_or("").to_string();
+                let color = self.textures.get(&texture).copied();
+                if color.is_none() {
+                    self.warn(format!("texture `{}` is not supported", texture));
+                }
+                color
+            }
+            _ => {
+                self.warn(format!("invalid color parameter `{} {}`", param.ty, name));
+                None
+            }
+        }
+    }
+
+    fn material(&mut self, name: &str, params: &ParamList) -> Arc<dyn Material> {
+        let reflectance = |parser: &mut Parser, default: f64| {
+            parser
+                .color(params, "reflectance")
+                .or_else(|| parser.color(params, "Kd"))
+                .unwrap_or(vec3::Color::fill(default))
+        };
+        match name {
+            "diffuse" | "matte" => Arc::new(Lambertian::new(reflectance(self, 0.5))),
+            "conductor" | "metal" | "coatedconductor" => {
+                if name == "coatedconductor" {
+                    self.warn(String::from(
+                        "coatedconductor is approximated by a conductor",
+                    ));
+                }
+                let albedo = match self.color(params, "reflectance") {
+                    Some(albedo) => albedo,
+                    None => self.conductor_reflectance(params),
+                };
+                let roughness = match params.get("roughness") {
+                    Some(_) => params.float("roughness", 0.0),
+                    None => {
+                        0.5 * (params.float("uroughness", 0.0) + params.float("vroughness", 0.0))
+                    }
+                };
+                let remap = !matches!(
+                    params.get("remaproughness").and_then(|p| p.values.first()),
+                    Some(Value::Bool(false))
+                );
+                let fuzz = if remap { roughness.sqrt() } else { roughness };
+                Arc::new(Metal::new(albedo, fuzz))
+            }
+            "mirror" => {
+                let albedo = self.color(params, "Kr").unwrap_or(vec3::Color::fill(0.9));
+                Arc::new(Metal::new(albedo, 0.0))
+            }
+            "dielectric" | "thindielectric" | "glass" => {
+                let ir = match params.get("eta").or_else(|| params.get("index")) {
+                    Some(param) => match param.values.first() {
+                        Some(Value::Number(ir)) => *ir,
+                        Some(Value::Str(s)) => match GLASSES.iter().find(|(name, _)| name == s) {
+                            Some((_, ir)) => *ir,
+                            None => {
+                                self.warn(format!("unknown glass `{}`, using eta 1.5", s));
+                                1.5
+                            }
+                        },
+                        _ => 1.5,
+                    },
+                    None => 1.5,
+                };
+                Arc::new(Dielectric::new(ir))
+            }
+            "coateddiffuse" | "plastic" | "substrate" | "uber" => {
+                self.warn(format!("{} is approximated by a diffuse material", name));
+                Arc::new(Lambertian::new(reflectance(self, 0.5)))
+            }
+            _ => {
+                self.warn(format!(
+                    "{} materials are not supported, using diffuse",
+                    name
+                ));
+                default_material()
+            }
+        }
+    }
+
+    // 由折射率 eta 与消光系数 k 计算法线方向的菲涅尔反射率
+    fn conductor_reflectance(&mut self, params: &ParamList) -> vec3::Color {
+        let named = params
+            .string("eta")
+            .and_then(|s| s.strip_prefix("metal-"))
+            .and_then(|s| s.strip_suffix("-eta"))
+            .map(str::to_string);
+        match (params.get("eta"), named) {
+            (_, Some(metal)) => match METALS.iter().find(|(name, _)| *name == metal) {
+                Some((_, f0)) => vec3::Vec3(f0[0], f0[1], f0[2]),
+                None => {
+                    self.warn(format!("unknown metal `{}`", metal));
+                    vec3::Color::fill(0.9)
+                }
+            },
+            (Some(_), None) => {
+                let eta = self.color(params, "eta").unwrap_or(vec3::Color::fill(0.2));
+                let k = self.color(params, "k").unwrap_or(vec3::Color::fill(3.9));
+                let f0 = |n: f64, k: f64| ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k);
+                vec3::Vec3(f0(eta.0, k.0), f0(eta.1, k.1), f0(eta.2, k.2))
+            }
+            // pbrt 的默认导体是铜
+            (None, None) => {
+                let f0 = METALS.iter().find(|(name, _)| *name == "Cu").unwrap().1;
+                vec3::Vec3(f0[0], f0[1], f0[2])
+            }
+        }
+    }
+
+    fn light(&mut self, name: &str, params: &ParamList) {
+        match name {
+            "infinite" => {
+                if params.get("filename").is_some() {
+                    self.warn(String::from(
+                        "environment maps are not supported, using a constant color",
+                    ));
+                }
+                let color = self.color(params, "L").unwrap_or(vec3::Color::fill(1.0));
+                let color = params.float("scale", 1.0) * color;
+                self.background = Some(self.background.unwrap_or(vec3::Color::fill(0.0)) + color);
+            }
+            _ => self.warn(format!("{} lights are not supported", name)),
+        }
+    }
+
+    fn shape(
+        &mut self,
+        shape: &ShapeEntity,
+        instance: &Transform,
+    ) -> io::Result<Option<Arc<dyn Hittable>>> {
+        let ctm = *instance * shape.ctm;
+        let material: Arc<dyn Material> = match shape.area_light {
+            Some(emission) => Arc::new(DiffuseLight::new(emission)),
+            None => shape.material.clone(),
+        };
+        let params = &shape.params;
+
+        let mesh = match shape.name.as_str() {
+            "sphere" => {
+                if params.get("zmin").is_some()
+                    || params.get("zmax").is_some()
+                    || params.get("phimax").is_some()
+                {
+                    self.warn(String::from("partial spheres are rendered as full spheres"));
+                }
+                let radius = params.float("radius", 1.0);
+                let axes = [
+                    vec3::Vec3(1.0, 0.0, 0.0),
+                    vec3::Vec3(0.0, 1.0, 0.0),
+                    vec3::Vec3(0.0, 0.0, 1.0),
+                ];
+                let scales: Vec<f64> = axes.iter().map(|&a| ctm.vector(a).length()).collect();
+                if scales.iter().any(|s| (s / scales[0] - 1.0).abs() > 1e-6) {
+                    self.warn(String::from(
+                        "non-uniformly scaled spheres are approximated by spheres",
+                    ));
+                }
+                let scale = scales.iter().sum::<f64>() / 3.0;
+                let mut sphere = Sphere::new(ctm.point(vec3::Point3::fill(0.0)), radius * scale);
+                sphere.mat_ptr = material;
+                let sphere: Arc<dyn Hittable> = Arc::new(sphere);
+                if shape.area_light.is_some() {
+                    self.lights.add(sphere.clone());
+                }
+                return Ok(Some(sphere));
+            }
+            "trianglemesh" => {
+                let vertices = params.points("P");
+                let mut indices = params.indices("indices")?;
+                if indices.is_empty() && vertices.len() == 3 {
+                    indices = vec![0, 1, 2];
+                }
+                let indices: Vec<[usize; 3]> = indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect();
+                MeshData::new(vertices, params.points("N"), indices)
+            }
+            // 双线性面片按两个三角形处理，单个平行四边形的面片用 Quad 表示，作为光源时可以被采样
+            "bilinearmesh" => {
+                let vertices = params.points("P");
+                let mut indices = params.indices("indices")?;
+                if indices.is_empty() && vertices.len() == 4 {
+                    indices = vec![0, 1, 2, 3];
+                }
+                if indices == [0, 1, 2, 3] && vertices.len() >= 4 && params.points("N").is_empty() {
+                    let p: Vec<vec3::Point3> = vertices.iter().map(|&v| ctm.point(v)).collect();
+                    let (u, v) = (p[1] - p[0], p[2] - p[0]);
+                    if (p[3] - p[0] - u - v).length() <= 1e-6 * (u.length() + v.length()) {
+                        let mut quad = Quad::new(p[0], u, v);
+                        quad.mat_ptr = material;
+                        let quad: Arc<dyn Hittable> = Arc::new(quad);
+                        if shape.area_light.is_some() {
+                            self.lights.add(quad.clone());
+                        }
+                        return Ok(Some(quad));
+                    }
+                }
+                let indices: Vec<[usize; 3]> = indices
+                    .chunks_exact(4)
+                    .flat_map(|q| [[q[0], q[1], q[3]], [q[0], q[3], q[2]]])
+                    .collect();
+                MeshData::new(vertices, params.points("N"), indices)
+            }
+            "plymesh" => {
+                let name = params.string("filename").unwrap_or("");
+                let directory = self.files[shape.file].parent().unwrap_or(Path::new(""));
+                let path = directory.join(name);
+                ply::load(&path).map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?
+            }
+            _ => {
+                self.warn(format!("{} shapes are not supported", shape.name));
+                return Ok(None);
+            }
+        };
+
+        let mut mesh = mesh
+            .into_mesh(&ctm)
+            .map_err(|e| invalid_data(format!("{}: {}", self.files[shape.file].display(), e)))?;
+        mesh.mat_ptr = material;
+        Ok(Some(Arc::new(mesh)))
+    }
+
+    fn finish(mut self) -> ImportedScene {
+        let width = self.film.float("xresolution", 1280.0).max(2.0) as usize;
+        let height = self.film.float("yresolution", 720.0).max(2.0) as usize;
+        let aspect_ratio = width as f64 / height as f64;
+
+        let (name, params, camera_from_world) = self.camera.clone().unwrap();
+        if name != "perspective" {
+            self.warn(format!(
+                "{} cameras are rendered as perspective cameras",
+                name
+            ));
+        }
+        // pbrt 的 fov 对应较短的一边
+        let fov = params.float("fov", 90.0);
+        let vfov = if aspect_ratio >= 1.0 {
+            fov
+        } else {
+            2.0 * ((fov.to_radians() / 2.0).tan() / aspect_ratio)
+                .atan()
+                .to_degrees()
+        };
+        // 针孔相机的对焦距离不影响成像，取 1 使光线方向保持单位长度左右
+        let lens_radius = params.float("lensradius", 0.0);
+        let focus_distance = if lens_radius > 0.0 {
+            params.float("focaldistance", 1e6)
+        } else {
+            1.0
+        };
+        let world_from_camera = camera_from_world.inverse().unwrap();
+        let camera = LensCamera::from_basis(
+            world_from_camera.point(vec3::Point3::fill(0.0)),
+            world_from_camera
+                .vector(vec3::Vec3(1.0, 0.0, 0.0))
+                .unit_vector(),
+            world_from_camera
+                .vector(vec3::Vec3(0.0, 1.0, 0.0))
+                .unit_vector(),
+            -world_from_camera
+                .vector(vec3::Vec3(0.0, 0.0, 1.0))
+                .unit_vector(),
+            vfov,
+            aspect_ratio,
+            2.0 * lens_radius,
+            focus_distance,
+        );
+
+        let (sampler, samples) = match &self.sampler {
+            Some((name, params)) => {
+                let samples = if name == "stratified" {
+                    params.float("xsamples", 4.0) * params.float("ysamples", 4.0)
+                } else {
+                    params.float("pixelsamples", 16.0)
+                };
+                let sampler = match name.as_str() {
+                    "independent" | "random" => Some("independent"),
+                    "stratified" => Some("stratified"),
+                    "halton" => Some("halton"),
+                    "sobol" | "zsobol" | "paddedsobol" | "02sequence" | "lowdiscrepancy" => {
+                        Some("sobol")
+                    }
+                    "pmj02bn" => Some("blue-noise"),
+                    _ => None,
+                };
+                if sampler.is_none() {
+                    self.warn(format!("unknown sampler `{}`", name));
+                }
+                (sampler.map(str::to_string), samples.max(1.0) as usize)
+            }
+            None => (None, 16),
+        };
+        // pbrt 的 maxdepth 不计相机光线
+        let max_depth = self.integrator.float("maxdepth", 5.0).max(0.0) as u64 + 1;
+        let mut image = ImageSettings::new(width, aspect_ratio, samples, max_depth);
+        image.height = height;
+
+        let output = self.film.string("filename").map(str::to_string);
+        let output = match output {
+            Some(name) if ImageFormat::from_path(&name).is_none() => {
+                self.warn(format!(
+                    "cannot write `{}`, expected .ppm, .png or .pfm",
+                    name
+                ));
+                None
+            }
+            output => output,
+        };
+
+        ImportedScene {
+            world: self.world,
+            lights: self.lights,
+            camera,
+            image,
+            background: Background::Color(self.background.unwrap_or(vec3::Color::fill(0.0))),
+            sampler,
+            output,
+            warnings: self.warnings,
+        }
+    }
+}
+
+// pbrt 的 LookAt 变换（世界坐标到相机坐标），相机坐标系是左手系
+fn look_at(eye: vec3::Point3, look: vec3::Point3, up: vec3::Vec3) -> Option<Transform> {
+    let dir = (look - eye).unit_vector();
+    let right = up.unit_vector().cross(dir);
+    if right.length() == 0.0 {
+        return None;
+    }
+    let right = right.unit_vector();
+    let new_up = dir.cross(right);
+    let world_from_camera = Transform::new([
+        [right.x(), new_up.x(), dir.x(), eye.x()],
+        [right.y(), new_up.y(), dir.y(), eye.y()],
+        [right.z(), new_up.z(), dir.z(), eye.z()],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+    world_from_camera.inverse()
+}
+
+// 解析 pbrt 场景，Include 与 plymesh 的路径相对于 path 所在的目录
+pub fn parse(text: &str, path: &Path) -> io::Result<ImportedScene> {
+    let tokens = tokenize(text, 0, path)?;
+    let mut parser = Parser::new(path.to_path_buf(), tokens);
+    parser.parse()?;
+    Ok(parser.finish())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImportedScene> {
+    let path = path.as_ref();
+    parse(&fs::read_to_string(path)?, path)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::camera::RayGenerator;
+    use crate::hittable::HitRecord;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+
+    const SCENE: &str = r#"
+        LookAt 0 0 5  0 0 0  0 1 0 # 相机
+        Camera "perspective" "float fov" [ 30 ]
+        Film "rgb" "integer xresolution" [ 40 ] "integer yresolution" 30
+            "string filename" "out.exr"
+        Sampler "halton" "integer pixelsamples" 8
+        Integrator "volpath" "integer maxdepth" [ 3 ]
+        WorldBegin
+        LightSource "infinite" "rgb L" [ 0.1 0.1 0.1 ]
+        MakeNamedMaterial "gold" "string type" "conductor"
+            "spectrum eta" "metal-Au-eta" "spectrum k" "metal-Au-k" "float roughness" 0.01
+        AttributeBegin
+            Translate 1 0 0
+            Scale 2 2 2
+            Material "dielectric" "float eta" 1.33
+            Shape "sphere" "float radius" 0.25
+        AttributeEnd
+        AttributeBegin
+            NamedMaterial "gold"
+            Shape "trianglemesh" "point3 P" [ -1 -1 -2  1 -1 -2  1 1 -2  -1 1 -2 ]
+                "integer indices" [ 0 1 2  0 2 3 ]
+        AttributeEnd
+        AttributeBegin
+            AreaLightSource "diffuse" "rgb L" [ 4 4 4 ] "float scale" 2
+            Shape "bilinearmesh" "point3 P" [ -1 2 -1  1 2 -1  -1 2 1  1 2 1 ]
+        AttributeEnd
+        Shape "disk"
+    "#;
+
+    #[test]
+    fn test_parse_pbrt() {
+        let scene = parse(SCENE, Path::new("scene.pbrt")).unwrap();
+        assert_eq!((scene.image.width, scene.image.height), (40, 30));
+        assert_eq!(scene.image.samples_per_pixel, 8);
+        assert_eq!(scene.image.max_depth, 4);
+        assert_eq!(scene.sampler.as_deref(), Some("halton"));
+        assert_eq!(scene.output, None);
+        assert_eq!(scene.world.objects.len(), 3);
+        assert_eq!(scene.warnings.len(), 2);
+
+        let mut rec = HitRecord::new();
+        let sphere = Ray::new(vec3::Vec3(1.0, 0.0, 5.0), vec3::Vec3(0.0, 0.0, -1.0));
+        assert!(scene.world.hit(&sphere, 0.0, f64::INFINITY, &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-9);
+
+        let light = Ray::new(vec3::Vec3(0.0, 0.0, 0.0), vec3::Vec3(0.0, 1.0, 0.0));
+        assert!(scene.world.hit(&light, 0.0, f64::INFINITY, &mut rec));
+        assert_eq!(
+            rec.mat_ptr.emitted(rec.u, rec.v, &rec.p).to_array(),
+            [8.0, 8.0, 8.0]
+        );
+
+        // pbrt 的图像左侧对应相机坐标的 -x，即这里世界坐标的 +x
+        let mut sampler = IndependentSampler::new();
+        let r = scene.camera.get_ray(0.0, 0.5, &mut sampler);
+        assert!(r.direction.x() > 0.0);
+        let r = scene.camera.get_ray(0.5, 0.5, &mut sampler);
+        assert!((r.direction.unit_vector().z() + 1.0).abs() < 1e-9);
+        assert!((r.direction.length() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pbrt_errors() {
+        let path = Path::new("scene.pbrt");
+        assert!(parse("WorldBegin", path).is_err());
+        assert!(parse("Camera \"perspective\" Frobnicate", path).is_err());
+        assert!(parse("Camera \"perspective\" Translate 1 2", path).is_err());
+        let error = parse("Camera \"perspective\"\nAttributeEnd", path)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("scene.pbrt:2:"));
+
+        // 顶点不足或下标为负的网格
+        for shape in [
+            r#"Shape "bilinearmesh" "point3 P" [ 0 0 0  1 0 0 ] "integer indices" [ 0 1 2 3 ]"#,
+            r#"Shape "trianglemesh" "point3 P" [ 0 0 0  1 0 0  0 1 0 ] "integer indices" [ 0 -1 2 ]"#,
+        ] {
+            let text = format!("WorldBegin\n{}", shape);
+            let error = parse(&text, path).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use super::MeshData;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, ScalarType),
+    List(String, ScalarType, ScalarType),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(name, _) | Property::List(name, _, _) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> io::Result<(Format, Vec<Element>)> {
+    let mut line = String::new();
+    let mut next_line = |reader: &mut R| -> io::Result<String> {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data(String::from("unexpected end of PLY header")));
+        }
+        Ok(line.trim().to_string())
+    };
+
+    if next_line(reader)? != "ply" {
+        return Err(invalid_data(String::from("not a PLY file")));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        let line = next_line(reader)?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(invalid_data(format!("unknown PLY format `{}`", name))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid element count `{}`", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data(String::from("property before element")))?;
+                let property = match (ScalarType::parse(count), ScalarType::parse(item)) {
+                    (Some(count), Some(item)) => Property::List(name.to_string(), count, item),
+                    _ => return Err(invalid_data(format!("invalid property `{}`", line))),
+                };
+                element.properties.push(property);
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data(String::from("property before element")))?;
+                let ty = ScalarType::parse(ty)
+                    .ok_or_else(|| invalid_data(format!("invalid property `{}`", line)))?;
+                element
+                    .properties
+                    .push(Property::Scalar(name.to_string(), ty));
+            }
+            _ => return Err(invalid_data(format!("invalid PLY header line `{}`", line))),
+        }
+    }
+    let format = format.ok_or_else(|| invalid_data(String::from("missing PLY format")))?;
+    Ok((format, elements))
+}
+
+// 按格式逐个读取数值
+struct ValueReader<R> {
+    reader: R,
+    format: Format,
+    tokens: VecDeque<f64>,
+}
+
+impl<R: BufRead> ValueReader<R> {
+    fn read(&mut self, ty: ScalarType) -> io::Result<f64> {
+        match self.format {
+            Format::Ascii => {
+                while self.tokens.is_empty() {
+                    let mut line = String::new();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        return Err(invalid_data(String::from("unexpected end of PLY data")));
+                    }
+                    for token in line.split_whitespace() {
+                        let value = token
+                            .parse()
+                            .map_err(|_| invalid_data(format!("invalid number `{}`", token)))?;
+                        self.tokens.push_back(value);
+                    }
+                }
+                Ok(self.tokens.pop_front().unwrap())
+            }
+            Format::BinaryLittleEndian => self.read_binary(ty, true),
+            Format::BinaryBigEndian => self.read_binary(ty, false),
+        }
+    }
+
+    fn read_binary(&mut self, ty: ScalarType, little: bool) -> io::Result<f64> {
+        macro_rules! read {
+            ($t:ty) => {{
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                self.reader.read_exact(&mut bytes)?;
+                if little {
+                    <$t>::from_le_bytes(bytes) as f64
+                } else {
+                    <$t>::from_be_bytes(bytes) as f64
+                }
+            }};
+        }
+        Ok(match ty {
+            ScalarType::I8 => read!(i8),
+            ScalarType::U8 => read!(u8),
+            ScalarType::I16 => read!(i16),
+            ScalarType::U16 => read!(u16),
+            ScalarType::I32 => read!(i32),
+            ScalarType::U32 => read!(u32),
+            ScalarType::F32 => read!(f32),
+            ScalarType::F64 => read!(f64),
+        })
+    }
+}
+
+// 多边形按扇形剖分为三角形
+pub fn read<R: BufRead>(mut reader: R) -> io::Result<MeshData> {
+    let (format, elements) = read_header(&mut reader)?;
+    let mut values = ValueReader {
+        reader,
+        format,
+        tokens: VecDeque::new(),
+    };
+    let mut mesh = MeshData::default();
+    let vertex_count = elements
+        .iter()
+        .find(|e| e.name == "vertex")
+        .map_or(0, |e| e.count);
+    if vertex_count > u32::MAX as usize {
+        return Err(invalid_data(format!(
+            "too many vertices ({})",
+            vertex_count
+        )));
+    }
+
+    for element in &elements {
+        let position = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|&name| element.properties.iter().position(|p| p.name() == name))
+        };
+        let xyz = [position(&["x"]), position(&["y"]), position(&["z"])];
+        let normal = [position(&["nx"]), position(&["ny"]), position(&["nz"])];
+        let color = [
+            position(&["red", "r", "diffuse_red"]),
+            position(&["green", "g", "diffuse_green"]),
+            position(&["blue", "b", "diffuse_blue"]),
+        ];
+        let uv = [
+            position(&["u", "s", "texture_u", "texture_s"]),
+            position(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let is_vertex = element.name == "vertex";
+        let has_normals = is_vertex && normal.iter().all(Option::is_some);
+        let has_colors = is_vertex && color.iter().all(Option::is_some);
+        let has_uvs = is_vertex && uv.iter().all(Option::is_some);
+        let face_indices = position(&["vertex_indices", "vertex_index"]);
+        // 整数颜色映射到 [0, 1]
+        let color_scale = color.map(|i| match i.map(|i| &element.properties[i]) {
+            Some(Property::Scalar(_, ScalarType::U8)) => 1.0 / 255.0,
+            Some(Property::Scalar(_, ScalarType::U16)) => 1.0 / 65535.0,
+            _ => 1.0,
+        });
+
+        if is_vertex {
+            mesh.vertices.reserve(element.count);
+        } else if element.name == "face" {
+            mesh.indices.reserve(element.count);
+        }
+        let mut scalars = vec![0.0; element.properties.len()];
+        let mut polygon: Vec<i64> = Vec::new();
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match *property {
+                    Property::Scalar(_, ty) => scalars[i] = values.read(ty)?,
+                    Property::List(_, count, item) => {
+                        let count = values.read(count)? as usize;
+                        let is_face = element.name == "face" && face_indices == Some(i);
+                        if is_face {
+                            polygon.clear();
+                        }
+                        for _ in 0..count {
+                            let value = values.read(item)?;
+                            if is_face {
+                                polygon.push(value as i64);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let get = |i: Option<usize>| i.map_or(0.0, |i| scalars[i]) as f32;
+            if is_vertex {
+                mesh.vertices.push(xyz.map(get));
+                if has_normals {
+                    mesh.normals.push(normal.map(get));
+                }
+                if has_colors {
+                    let c = color.map(get);
+                    mesh.colors.push([
+                        c[0] * color_scale[0] as f32,
+                        c[1] * color_scale[1] as f32,
+                        c[2] * color_scale[2] as f32,
+                    ]);
+                }
+                if has_uvs {
+                    mesh.uvs.push(uv.map(get));
+                }
+            } else if element.name == "face" {
+                if let Some(&i) = polygon
+                    .iter()
+                    .find(|&&i| i < 0 || i as usize >= vertex_count)
+                {
+                    return Err(invalid_data(format!(
+                        "vertex index {} out of range, the mesh has {} vertices",
+                        i, vertex_count
+                    )));
+                }
+                for k in 1..polygon.len().saturating_sub(1) {
+                    mesh.indices.push([
+                        polygon[0] as u32,
+                        polygon[k] as u32,
+                        polygon[k + 1] as u32,
+                    ]);
+                }
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<MeshData> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const HEADER: &str = "ply\nformat {} 1.0\ncomment square\nelement vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    #[test]
+    fn test_read_ply() {
+        let ascii = HEADER.replace("{}", "ascii") + "0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3\n";
+        let mesh = read(ascii.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+
+        let mut binary = HEADER.replace("{}", "binary_big_endian").into_bytes();
+        for v in [
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ] {
+            for x in v {
+                binary.extend_from_slice(&x.to_be_bytes());
+            }
+        }
+        binary.push(4);
+        for i in [0i32, 1, 2, 3] {
+            binary.extend_from_slice(&i.to_be_bytes());
+        }
+        let binary_mesh = read(binary.as_slice()).unwrap();
+        assert_eq!(binary_mesh.indices, mesh.indices);
+        assert_eq!(binary_mesh.vertices[2], [1.0, 1.0, 0.0]);
+
+        let out_of_range = ascii.replace("4 0 1 2 3", "3 0 1 7");
+        assert!(read(out_of_range.as_bytes()).is_err());
+
+        let colored = HEADER
+            .replace("{}", "ascii")
+            .replace("vertex 4", "vertex 3")
+            .replace(
+                "property float z\n",
+                "property float z\nproperty uchar red\nproperty uchar green\n\
+                 property uchar blue\nproperty float s\nproperty float t\n",
+            )
+            + "0 0 0 255 0 0 0 0\n1 0 0 0 255 0 1 0\n0 1 0 0 0 255 0 1\n3 0 1 2\n";
+        let mesh = read(colored.as_bytes()).unwrap();
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.colors[1], [0.0, 1.0, 0.0]);
+        assert_eq!(mesh.uvs[2], [0.0, 1.0]);
+    }
+}
@@ -0,0 +1,1685 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use crate::camera::{RayGenerator, Viewport};
+use crate::hittable::{HitRecord, Hittable};
+use crate::kdtree::KdTree;
+use crate::onb::Onb;
+use crate::pdf::{HittablePdf, MaterialPdf, MixturePdf, Pdf};
+use crate::ray::Ray;
+use crate::sampler::{self, IndependentSampler, Sampler};
+use crate::scene::{BackgroundDescription, IntegratorDescription};
+use crate::vec3;
+
+// 计算一条光线带回的颜色，散射等随机数取自 sampler
+pub trait Integrator: Send + Sync {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color;
+    // 除返回的颜色外，还可以通过 splat 把贡献累加到视口坐标 (s, t) 处，例如把从光源出发的
+    // 路径连接到相机。splat 的贡献按胶片上的样本总数平均，每个样本应恰好追踪一条这样的路径。
+    // index 为像素样本的序号。默认只计算 ray_color
+    #[allow(clippy::too_many_arguments)]
+    fn sample(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        _index: usize,
+        _camera: &dyn RayGenerator,
+        _splat: &mut dyn FnMut(f64, f64, vec3::Color),
+    ) -> vec3::Color {
+        self.ray_color(r, world, depth, sampler)
+    }
+    // 渲染器在一轮中的任何样本开始之前调用 start_pass，samples 为这一轮的像素样本序号，
+    // 这一轮合并完成后调用 end_pass。积分器可以在其中准备与释放各轮共享的数据。
+    // 前一轮尚未合并完时下一轮可能已经开始
+    fn start_pass(&self, _world: &dyn Hittable, _depth: u64, _seed: u64, _samples: Range<usize>) {}
+    fn end_pass(&self, _samples: Range<usize>) {}
+    // 导出场景时的描述，无法描述的积分器返回 None
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        None
+    }
+}
+
+// 线性插值
+fn lerp(t: f64, start: vec3::Color, end: vec3::Color) -> vec3::Color {
+    (1.0 - t) * start + t * end
+}
+
+// 未击中物体时的天空背景
+pub fn sky(r: &Ray) -> vec3::Color {
+    let unit_direction = r.direction.unit_vector(); // 单位化
+    let t = 0.5 * (unit_direction.y() + 1.0); // 将y分量映射到[0, 1]
+
+    let from = vec3::Color {
+        0: 1.0,
+        1: 1.0,
+        2: 1.0,
+    }; // 白色
+    let to = vec3::Color {
+        0: 0.5,
+        1: 0.7,
+        2: 1.0,
+    }; // 蓝色
+
+    lerp(t, from, to)
+}
+
+// 未击中物体时的背景
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    Sky,
+    Color(vec3::Color),
+}
+
+impl Background {
+    pub fn color(&self, r: &Ray) -> vec3::Color {
+        match self {
+            Background::Sky => sky(r),
+            Background::Color(color) => *color,
+        }
+    }
+
+    pub fn describe(&self) -> BackgroundDescription {
+        match self {
+            Background::Sky => BackgroundDescription::Sky,
+            Background::Color(color) => BackgroundDescription::Color {
+                color: color.to_array(),
+            },
+        }
+    }
+}
+
+// 将法向量映射为颜色
+#[derive(Debug, Clone, Copy)]
+pub struct Normals {}
+
+impl Normals {
+    pub fn new() -> Self {
+        Normals {}
+    }
+}
+
+impl Default for Normals {
+    fn default() -> Self {
+        Normals::new()
+    }
+}
+
+impl Integrator for Normals {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        _depth: u64,
+        _sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        let mut rec = HitRecord::new();
+        if world.hit(r, 0.0, f64::INFINITY, &mut rec) {
+            return 0.5 * (rec.normal + vec3::Vec3(1.0, 1.0, 1.0));
+        }
+
+        sky(r)
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((IntegratorDescription::Normals, BackgroundDescription::Sky))
+    }
+}
+
+// 漫反射方向的采样方式
+#[derive(Debug, Clone, Copy)]
+pub enum DiffuseScatter {
+    InUnitSphere,
+    UnitVector,
+    InHemisphere,
+}
+
+// 不使用材质，所有物体都按反射率0.5的漫反射处理
+#[derive(Debug, Clone, Copy)]
+pub struct Diffuse {
+    pub scatter: DiffuseScatter,
+    pub t_min: f64,
+}
+
+impl Diffuse {
+    pub fn new(scatter: DiffuseScatter, t_min: f64) -> Self {
+        Diffuse { scatter, t_min }
+    }
+}
+
+impl Integrator for Diffuse {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        if depth == 0 {
+            return vec3::Color::fill(0.0);
+        }
+
+        let mut rec = HitRecord::new();
+        if world.hit(r, self.t_min, f64::INFINITY, &mut rec) {
+            let target = match self.scatter {
+                DiffuseScatter::InUnitSphere => {
+                    rec.p + rec.normal + sampler::in_unit_sphere(sampler.get_2d(), sampler.get_1d())
+                }
+                DiffuseScatter::UnitVector => {
+                    rec.p + rec.normal + sampler::unit_vector(sampler.get_2d())
+                }
+                DiffuseScatter::InHemisphere => {
+                    rec.p + sampler::in_hemisphere(&rec.normal, sampler.get_2d(), sampler.get_1d())
+                }
+            };
+            let scattered = Ray::new(rec.p, target - rec.p);
+            return 0.5 * self.ray_color(&scattered, world, depth - 1, sampler);
+        }
+
+        sky(r)
+    }
+}
+
+// 根据材质递归追踪散射光线。
+// 指定 lights 时，非镜面散射的方向一半按材质采样、一半朝向光源采样，再除以混合分布的概率密度
+#[derive(Clone)]
+pub struct Recursive {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+}
+
+impl Recursive {
+    pub fn new() -> Self {
+        Recursive {
+            background: Background::Sky,
+            lights: None,
+        }
+    }
+}
+
+impl Default for Recursive {
+    fn default() -> Self {
+        Recursive::new()
+    }
+}
+
+impl Integrator for Recursive {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        if depth == 0 {
+            return vec3::Color::fill(0.0);
+        }
+
+        let mut rec = HitRecord::new();
+        if world.hit(r, 0.001, f64::INFINITY, &mut rec) {
+            let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+            let srec = match rec.mat_ptr.scatter(r, &rec, sampler) {
+                Some(srec) => srec,
+                None => return emitted,
+            };
+            let lights = match &self.lights {
+                Some(lights) if !srec.is_specular => lights,
+                _ => {
+                    return emitted
+                        + srec.attenuation
+                            * self.ray_color(&srec.scattered, world, depth - 1, sampler)
+                }
+            };
+
+            let light_pdf = HittablePdf::new(&**lights, rec.p);
+            let material_pdf = MaterialPdf::new(&*rec.mat_ptr, r, &rec);
+            let mixture = MixturePdf::new(&light_pdf, &material_pdf);
+            let scattered = Ray::new(rec.p, mixture.generate(sampler));
+            let pdf = mixture.value(scattered.direction);
+            if pdf <= 0.0 {
+                return emitted;
+            }
+            let f = rec.mat_ptr.eval(r, &rec, &scattered);
+            return emitted + f * self.ray_color(&scattered, world, depth - 1, sampler) / pdf;
+        }
+
+        self.background.color(r)
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((IntegratorDescription::Recursive, self.background.describe()))
+    }
+}
+
+// 朝光源采样一个方向，阴影光线击中的第一个物体的自发光即为该方向的入射光。
+// 返回阴影光线、其概率密度与 材质项 × 入射光，贡献为零时返回 None
+fn sample_light(
+    r: &Ray,
+    rec: &HitRecord,
+    lights: &dyn Hittable,
+    world: &dyn Hittable,
+    sampler: &mut dyn Sampler,
+) -> Option<(Ray, f64, vec3::Color)> {
+    let light_pdf = HittablePdf::new(lights, rec.p);
+    let shadow = Ray::new(rec.p, light_pdf.generate(sampler));
+    let pdf = light_pdf.value(shadow.direction);
+    if pdf <= 0.0 {
+        return None;
+    }
+    let f = rec.mat_ptr.eval(r, rec, &shadow);
+    if f.length_squared() == 0.0 {
+        return None;
+    }
+    let mut light_rec = HitRecord::new();
+    if !world.hit(&shadow, 0.001, f64::INFINITY, &mut light_rec) {
+        return None;
+    }
+    let emitted = light_rec
+        .mat_ptr
+        .emitted(light_rec.u, light_rec.v, &light_rec.p);
+    Some((shadow, pdf, f * emitted))
+}
+
+// 路径上每次非镜面散射时朝光源采样一个方向，追踪阴影光线并加上直接光照（下一事件估计）。
+// 之后按材质散射的光线若沿光源采样能覆盖的方向击中发光物体，自发光已经计入直接光照，不再重复计算
+#[derive(Clone)]
+pub struct NextEvent {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+}
+
+impl NextEvent {
+    pub fn new() -> Self {
+        NextEvent {
+            background: Background::Sky,
+            lights: None,
+        }
+    }
+
+    // after_diffuse 表示 r 由非镜面散射产生
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        after_diffuse: bool,
+    ) -> vec3::Color {
+        if depth == 0 {
+            return vec3::Color::fill(0.0);
+        }
+
+        let mut rec = HitRecord::new();
+        if !world.hit(r, 0.001, f64::INFINITY, &mut rec) {
+            return self.background.color(r);
+        }
+
+        let mut emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+        if let Some(lights) = &self.lights {
+            if after_diffuse
+                && emitted.length_squared() > 0.0
+                && lights.pdf_value(r.origin, r.direction) > 0.0
+            {
+                emitted = vec3::Color::fill(0.0);
+            }
+        }
+        let srec = match rec.mat_ptr.scatter(r, &rec, sampler) {
+            Some(srec) => srec,
+            None => return emitted,
+        };
+        if srec.is_specular {
+            return emitted
+                + srec.attenuation * self.trace(&srec.scattered, world, depth - 1, sampler, false);
+        }
+
+        let direct = match &self.lights {
+            Some(lights) => match sample_light(r, &rec, &**lights, world, sampler) {
+                Some((_, pdf, contribution)) => contribution / pdf,
+                None => vec3::Color::fill(0.0),
+            },
+            None => vec3::Color::fill(0.0),
+        };
+        emitted
+            + direct
+            + srec.attenuation
+                * self.trace(
+                    &srec.scattered,
+                    world,
+                    depth - 1,
+                    sampler,
+                    self.lights.is_some(),
+                )
+    }
+}
+
+impl Default for NextEvent {
+    fn default() -> Self {
+        NextEvent::new()
+    }
+}
+
+impl Integrator for NextEvent {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        self.trace(r, world, depth, sampler, false)
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((IntegratorDescription::NextEvent, self.background.describe()))
+    }
+}
+
+// 多重重要性采样中按两种策略的概率密度分配样本权重的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    Balance,
+    Power,
+}
+
+impl Heuristic {
+    // 样本由概率密度为 pdf 的策略采样，另一种策略的概率密度为 other
+    pub fn weight(&self, pdf: f64, other: f64) -> f64 {
+        match self {
+            Heuristic::Balance => pdf / (pdf + other),
+            Heuristic::Power => pdf * pdf / (pdf * pdf + other * other),
+        }
+    }
+}
+
+// 与 NextEvent 一样每次非镜面散射都朝光源采样并按材质散射，
+// 但两种策略都计入光源的自发光，按 heuristic 加权合并（多重重要性采样），
+// 材质采样适合小光泽度的金属，光源采样适合大面积的漫反射光源
+#[derive(Clone)]
+pub struct Mis {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+    pub heuristic: Heuristic,
+}
+
+impl Mis {
+    pub fn new() -> Self {
+        Mis {
+            background: Background::Sky,
+            lights: None,
+            heuristic: Heuristic::Power,
+        }
+    }
+
+    // bsdf_pdf 为按材质散射出 r 的概率密度，相机光线与镜面散射为 0
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        bsdf_pdf: f64,
+    ) -> vec3::Color {
+        if depth == 0 {
+            return vec3::Color::fill(0.0);
+        }
+
+        let mut rec = HitRecord::new();
+        if !world.hit(r, 0.001, f64::INFINITY, &mut rec) {
+            return self.background.color(r);
+        }
+
+        let mut emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+        if let Some(lights) = &self.lights {
+            if bsdf_pdf > 0.0 && emitted.length_squared() > 0.0 {
+                let light_pdf = lights.pdf_value(r.origin, r.direction);
+                emitted = self.heuristic.weight(bsdf_pdf, light_pdf) * emitted;
+            }
+        }
+        let srec = match rec.mat_ptr.scatter(r, &rec, sampler) {
+            Some(srec) => srec,
+            None => return emitted,
+        };
+        if srec.is_specular {
+            return emitted
+                + srec.attenuation * self.trace(&srec.scattered, world, depth - 1, sampler, 0.0);
+        }
+
+        let mut direct = vec3::Color::fill(0.0);
+        if let Some(lights) = &self.lights {
+            if let Some((shadow, pdf, contribution)) =
+                sample_light(r, &rec, &**lights, world, sampler)
+            {
+                let weight = self
+                    .heuristic
+                    .weight(pdf, rec.mat_ptr.pdf(r, &rec, &shadow));
+                direct = weight * contribution / pdf;
+            }
+        }
+        emitted
+            + direct
+            + srec.attenuation * self.trace(&srec.scattered, world, depth - 1, sampler, srec.pdf)
+    }
+}
+
+impl Default for Mis {
+    fn default() -> Self {
+        Mis::new()
+    }
+}
+
+impl Integrator for Mis {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        self.trace(r, world, depth, sampler, 0.0)
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        let integrator = match self.heuristic {
+            Heuristic::Balance => IntegratorDescription::MisBalance,
+            Heuristic::Power => IntegratorDescription::Mis,
+        };
+        Some((integrator, self.background.describe()))
+    }
+}
+
+// 迭代地追踪路径并累计吞吐量（路径上各次散射的衰减之积），与 Recursive 的采样方式相同。
+// 前 min_depth 次散射之后按吞吐量的最大分量决定路径是否继续（俄罗斯轮盘赌），
+// 继续的路径除以继续的概率，因此结果无偏，路径长度也不受 depth 限制
+#[derive(Clone)]
+pub struct PathTracer {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+    pub min_depth: u64,
+}
+
+impl PathTracer {
+    pub fn new() -> Self {
+        PathTracer {
+            background: Background::Sky,
+            lights: None,
+            min_depth: 3,
+        }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new()
+    }
+}
+
+impl Integrator for PathTracer {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        _depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        let mut color = vec3::Color::fill(0.0);
+        let mut throughput = vec3::Color::fill(1.0);
+        let mut r = *r;
+        let mut bounces = 0;
+        loop {
+            let mut rec = HitRecord::new();
+            if !world.hit(&r, 0.001, f64::INFINITY, &mut rec) {
+                return color + throughput * self.background.color(&r);
+            }
+            color += throughput * rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+            let srec = match rec.mat_ptr.scatter(&r, &rec, sampler) {
+                Some(srec) => srec,
+                None => return color,
+            };
+
+            let (scattered, attenuation) = match &self.lights {
+                Some(lights) if !srec.is_specular => {
+                    let light_pdf = HittablePdf::new(&**lights, rec.p);
+                    let material_pdf = MaterialPdf::new(&*rec.mat_ptr, &r, &rec);
+                    let mixture = MixturePdf::new(&light_pdf, &material_pdf);
+                    let scattered = Ray::new(rec.p, mixture.generate(sampler));
+                    let pdf = mixture.value(scattered.direction);
+                    if pdf <= 0.0 {
+                        return color;
+                    }
+                    (scattered, rec.mat_ptr.eval(&r, &rec, &scattered) / pdf)
+                }
+                _ => (srec.scattered, srec.attenuation),
+            };
+            throughput *= attenuation;
+
+            bounces += 1;
+            if bounces >= self.min_depth {
+                // 继续的概率不超过 0.95，反照率为 1 的场景中路径也会结束
+                let p = throughput
+                    .x()
+                    .max(throughput.y())
+                    .max(throughput.z())
+                    .min(0.95);
+                if sampler.get_1d() >= p {
+                    return color;
+                }
+                throughput /= p;
+            }
+            r = scattered;
+        }
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((IntegratorDescription::Path, self.background.describe()))
+    }
+}
+
+// 双向路径追踪中子路径的顶点种类
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+// 子路径的顶点。相机顶点的 rec.p 为镜头上的点，光源顶点的法向量朝外，自发光计入 beta
+#[derive(Clone)]
+struct Vertex {
+    kind: VertexKind,
+    rec: HitRecord,
+    // 沿子路径到达该顶点的方向
+    incoming: vec3::Vec3,
+    // 子路径到该顶点为止的吞吐量
+    beta: vec3::Color,
+    delta: bool,
+    // 沿子路径方向与反方向生成该顶点的概率密度（对面积）
+    pdf_fwd: f64,
+    pdf_rev: f64,
+}
+
+// 双面发光的光源按余弦分布向两侧发射光线的概率密度（对立体角）
+fn emission_pdf(normal: vec3::Vec3, direction: vec3::Vec3) -> f64 {
+    normal.dot(direction.unit_vector()).abs() / (2.0 * PI)
+}
+
+impl Vertex {
+    fn new(kind: VertexKind, rec: HitRecord, incoming: vec3::Vec3, beta: vec3::Color) -> Self {
+        Vertex {
+            kind,
+            rec,
+            incoming,
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn p(&self) -> vec3::Point3 {
+        self.rec.p
+    }
+
+    // 在该顶点处以对立体角的概率密度 pdf 采样方向时，next 处对面积的概率密度
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let w = next.p() - self.p();
+        let distance_squared = w.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let pdf = pdf / distance_squared;
+        if next.kind == VertexKind::Camera {
+            return pdf;
+        }
+        pdf * next.rec.normal.dot(w).abs() / distance_squared.sqrt()
+    }
+
+    // 从 prev 到达该顶点后朝 next 采样的概率密度（对面积），prev 为 None 时该顶点是子路径的起点
+    fn pdf(&self, prev: Option<&Vertex>, next: &Vertex, viewport: Option<&Viewport>) -> f64 {
+        let direction = next.p() - self.p();
+        let pdf = match (self.kind, prev) {
+            (VertexKind::Camera, _) => {
+                viewport.map_or(0.0, |v| v.pdf_direction(v.cos_theta(direction)))
+            }
+            (VertexKind::Light, _) => emission_pdf(self.rec.normal, direction),
+            (VertexKind::Surface, Some(prev)) => {
+                let r_in = Ray::new(prev.p(), self.p() - prev.p());
+                let scattered = Ray::new(self.p(), direction);
+                self.rec.mat_ptr.pdf(&r_in, &self.rec, &scattered)
+            }
+            (VertexKind::Surface, None) => 0.0,
+        };
+        self.convert_density(pdf, next)
+    }
+
+    fn f(&self, to_camera: vec3::Vec3, to_light: vec3::Vec3) -> vec3::Color {
+        if self.kind != VertexKind::Surface {
+            return vec3::Color::fill(1.0);
+        }
+        material_f(&self.rec, to_camera, to_light)
+    }
+}
+
+// 交点处的材质项（不含余弦），to_camera 与 to_light 分别指向路径上相机一侧与光源一侧。
+// 非镜面的材质只反射，两个方向位于表面两侧时为零
+fn material_f(rec: &HitRecord, to_camera: vec3::Vec3, to_light: vec3::Vec3) -> vec3::Color {
+    let cos_light = rec.normal.dot(to_light.unit_vector());
+    if rec.normal.dot(to_camera) * cos_light <= 0.0 {
+        return vec3::Color::fill(0.0);
+    }
+    let r_in = Ray::new(rec.p + to_camera, -to_camera);
+    let scattered = Ray::new(rec.p, to_light);
+    rec.mat_ptr.eval(&r_in, rec, &scattered) / cos_light.abs()
+}
+
+// MIS 权重计算中一个顶点的概率密度
+#[derive(Debug, Clone, Copy)]
+struct VertexPdf {
+    fwd: f64,
+    rev: f64,
+    delta: bool,
+}
+
+// 双向路径追踪：从相机与光源各追踪一条子路径，把两条子路径上的每一对顶点连接起来，
+// 每种连接方式按 heuristic 加权合并。光源一侧的顶点与镜头相连的贡献通过 splat 累加到图像上，
+// 只调用 ray_color 时不使用这种连接方式。前 min_depth 次散射之后按俄罗斯轮盘赌结束子路径
+#[derive(Clone)]
+pub struct Bidirectional {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+    pub heuristic: Heuristic,
+    pub min_depth: u64,
+}
+
+impl Bidirectional {
+    pub fn new() -> Self {
+        Bidirectional {
+            background: Background::Sky,
+            lights: None,
+            heuristic: Heuristic::Power,
+            min_depth: 3,
+        }
+    }
+
+    // 从最后一个顶点沿 ray 继续随机游走，ray 的方向以对立体角的概率密度 pdf 采样，
+    // 顶点数达到 max_vertices 时停止。from_light 表示子路径从光源出发，此时按伴随的方式
+    // 计算材质项。返回离开场景的光线与其吞吐量
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        world: &dyn Hittable,
+        mut ray: Ray,
+        mut beta: vec3::Color,
+        mut pdf: f64,
+        max_vertices: usize,
+        from_light: bool,
+        sampler: &mut dyn Sampler,
+        path: &mut Vec<Vertex>,
+    ) -> Option<(Ray, vec3::Color)> {
+        while path.len() < max_vertices {
+            let mut rec = HitRecord::new();
+            if !world.hit(&ray, 0.001, f64::INFINITY, &mut rec) {
+                return Some((ray, beta));
+            }
+            let mut vertex = Vertex::new(VertexKind::Surface, rec, ray.direction, beta);
+            let prev = path.len() - 1;
+            vertex.pdf_fwd = path[prev].convert_density(pdf, &vertex);
+            path.push(vertex);
+            if path.len() >= max_vertices {
+                break;
+            }
+
+            let current = &path[prev + 1];
+            let rec = &current.rec;
+            let srec = match rec.mat_ptr.scatter(&ray, rec, sampler) {
+                Some(srec) => srec,
+                None => break,
+            };
+            let direction = srec.scattered.direction;
+            let (attenuation, pdf_rev) = if srec.is_specular {
+                pdf = 0.0;
+                (srec.attenuation, 0.0)
+            } else {
+                pdf = srec.pdf;
+                let reverse = Ray::new(rec.p + direction, -direction);
+                let pdf_rev = rec
+                    .mat_ptr
+                    .pdf(&reverse, rec, &Ray::new(rec.p, -ray.direction));
+                let attenuation = if from_light {
+                    let cosine = rec.normal.dot(direction.unit_vector()).abs();
+                    current.f(direction, -ray.direction) * cosine / srec.pdf
+                } else {
+                    srec.attenuation
+                };
+                (attenuation, pdf_rev)
+            };
+            let pdf_rev = current.convert_density(pdf_rev, &path[prev]);
+            path[prev].pdf_rev = pdf_rev;
+            path[prev + 1].delta = srec.is_specular;
+
+            beta *= attenuation;
+            if path.len() as u64 > self.min_depth {
+                let p = attenuation
+                    .x()
+                    .max(attenuation.y())
+                    .max(attenuation.z())
+                    .min(0.95);
+                if sampler.get_1d() >= p {
+                    break;
+                }
+                beta /= p;
+            }
+            ray = srec.scattered;
+        }
+        None
+    }
+
+    // 从光源上随机一点出发的子路径，没有光源时为空
+    fn light_subpath(
+        &self,
+        world: &dyn Hittable,
+        max_vertices: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<Vertex> {
+        let mut path = Vec::new();
+        let lights = match &self.lights {
+            Some(lights) => lights,
+            None => return path,
+        };
+        let (rec, pdf_area) = match lights.random_point(sampler) {
+            Some(sample) => sample,
+            None => return path,
+        };
+        let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+        let normal = rec.normal;
+        let mut light = Vertex::new(
+            VertexKind::Light,
+            rec,
+            vec3::Vec3::fill(0.0),
+            emitted / pdf_area,
+        );
+        light.pdf_fwd = pdf_area;
+        let origin = light.p();
+        let beta = light.beta;
+        path.push(light);
+
+        // 随机选择发光的一侧，按余弦分布采样方向
+        let side = if sampler.get_1d() < 0.5 {
+            normal
+        } else {
+            -normal
+        };
+        let direction = Onb::new(side).local(sampler::cosine_direction(sampler.get_2d()));
+        let pdf = emission_pdf(normal, direction);
+        if pdf <= 0.0 {
+            return path;
+        }
+        let beta = beta * normal.dot(direction.unit_vector()).abs() / pdf;
+        let ray = Ray::new(origin, direction);
+        self.random_walk(
+            world,
+            ray,
+            beta,
+            pdf,
+            max_vertices,
+            true,
+            sampler,
+            &mut path,
+        );
+        path
+    }
+
+    // 两条子路径分别取前 s 与前 t 个顶点连接而成的路径的 MIS 权重。
+    // camera_vertex 为 t = 1 时重新在镜头上采样的相机顶点
+    #[allow(clippy::too_many_arguments)]
+    fn mis_weight(
+        &self,
+        light: &[Vertex],
+        camera: &[Vertex],
+        camera_vertex: Option<&Vertex>,
+        s: usize,
+        t: usize,
+        viewport: Option<&Viewport>,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        let pt = camera_vertex.unwrap_or(&camera[t - 1]);
+        let pt_minus = if t > 1 { Some(&camera[t - 2]) } else { None };
+        let qs = if s > 0 { Some(&light[s - 1]) } else { None };
+        let qs_minus = if s > 1 { Some(&light[s - 2]) } else { None };
+
+        let pdfs = |path: &[Vertex], n: usize| -> Vec<VertexPdf> {
+            path[..n]
+                .iter()
+                .map(|v| VertexPdf {
+                    fwd: v.pdf_fwd,
+                    rev: v.pdf_rev,
+                    delta: v.delta,
+                })
+                .collect()
+        };
+        let mut cam = pdfs(camera, t);
+        let mut lig = pdfs(light, s);
+
+        // 连接处的两个顶点及其前一个顶点，按连接后的路径重新计算反方向的概率密度
+        cam[t - 1].delta = false;
+        match qs {
+            Some(qs) => {
+                cam[t - 1].rev = qs.pdf(qs_minus, pt, viewport);
+                lig[s - 1].delta = false;
+                lig[s - 1].rev = pt.pdf(pt_minus, qs, viewport);
+                if let Some(qs_minus) = qs_minus {
+                    lig[s - 2].rev = qs.pdf(Some(pt), qs_minus, viewport);
+                }
+                if let Some(pt_minus) = pt_minus {
+                    cam[t - 2].rev = pt.pdf(Some(qs), pt_minus, viewport);
+                }
+            }
+            None => {
+                // 不能被采样的发光物体只能由相机一侧的路径击中
+                let pdf = self.lights.as_ref().map_or(0.0, |l| l.pdf_point(pt.p()));
+                if pdf <= 0.0 {
+                    return 1.0;
+                }
+                cam[t - 1].rev = pdf;
+                if let Some(pt_minus) = pt_minus {
+                    let pdf = emission_pdf(pt.rec.normal, pt_minus.p() - pt.p());
+                    cam[t - 2].rev = pt.convert_density(pdf, pt_minus);
+                }
+            }
+        }
+
+        // 依次把连接处向相机或光源一侧移动，累加其他连接方式与当前方式的概率密度之比。
+        // 经过镜面顶点的概率密度含有相同的 δ 函数，按 1 计
+        let ratio = |v: VertexPdf, rev_delta: bool, fwd_delta: bool| -> f64 {
+            let rev = if rev_delta { 1.0 } else { v.rev };
+            let fwd = if fwd_delta || v.fwd == 0.0 {
+                1.0
+            } else {
+                v.fwd
+            };
+            match self.heuristic {
+                Heuristic::Balance => rev / fwd,
+                Heuristic::Power => (rev / fwd) * (rev / fwd),
+            }
+        };
+        let mut sum = 0.0;
+        let mut ri = 1.0;
+        for i in (1..t).rev() {
+            ri *= ratio(cam[i], i + 1 < t && cam[i + 1].delta, cam[i - 1].delta);
+            if !cam[i].delta && !cam[i - 1].delta && (i > 1 || viewport.is_some()) {
+                sum += ri;
+            }
+        }
+        ri = 1.0;
+        for i in (0..s).rev() {
+            let prev_delta = i > 0 && lig[i - 1].delta;
+            ri *= ratio(lig[i], i + 1 < s && lig[i + 1].delta, prev_delta);
+            if !lig[i].delta && !prev_delta {
+                sum += ri;
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+
+    // 两点之间没有遮挡时的几何项
+    fn geometry(&self, world: &dyn Hittable, a: &Vertex, b: &Vertex) -> f64 {
+        let d = b.p() - a.p();
+        let distance = d.length();
+        let direction = d / distance;
+        let mut rec = HitRecord::new();
+        if world.hit(
+            &Ray::new(a.p(), direction),
+            0.001,
+            distance - 0.001,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+        let cos_a = if a.kind == VertexKind::Camera {
+            1.0
+        } else {
+            a.rec.normal.dot(direction).abs()
+        };
+        cos_a * b.rec.normal.dot(direction).abs() / (distance * distance)
+    }
+
+    // 相机子路径的前 t 个顶点与光源子路径的前 s 个顶点连接而成的路径的加权贡献，t >= 2
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        &self,
+        world: &dyn Hittable,
+        light: &[Vertex],
+        camera: &[Vertex],
+        s: usize,
+        t: usize,
+        viewport: Option<&Viewport>,
+    ) -> vec3::Color {
+        let pt = &camera[t - 1];
+        if pt.delta {
+            return vec3::Color::fill(0.0);
+        }
+        let color = if s == 0 {
+            pt.beta * pt.rec.mat_ptr.emitted(pt.rec.u, pt.rec.v, &pt.p())
+        } else {
+            let qs = &light[s - 1];
+            if qs.delta {
+                return vec3::Color::fill(0.0);
+            }
+            let d = pt.p() - qs.p();
+            let color = qs.beta * qs.f(d, -qs.incoming) * pt.f(-pt.incoming, -d) * pt.beta;
+            if color.length_squared() == 0.0 {
+                return color;
+            }
+            color * self.geometry(world, qs, pt)
+        };
+        if color.length_squared() == 0.0 {
+            return color;
+        }
+        color * self.mis_weight(light, camera, None, s, t, viewport)
+    }
+
+    // 光源子路径的前 s 个顶点直接与镜头上随机一点相连，返回视口坐标与加权贡献
+    #[allow(clippy::too_many_arguments)]
+    fn connect_camera(
+        &self,
+        world: &dyn Hittable,
+        light: &[Vertex],
+        camera: &[Vertex],
+        s: usize,
+        viewport: &Viewport,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(f64, f64, vec3::Color)> {
+        let qs = &light[s - 1];
+        if qs.delta {
+            return None;
+        }
+        let mut rec = HitRecord::new();
+        rec.p = viewport.sample_lens(sampler.get_2d());
+        let lens = Vertex::new(
+            VertexKind::Camera,
+            rec,
+            vec3::Vec3::fill(0.0),
+            vec3::Color::fill(1.0),
+        );
+        let (u, v) = viewport.project(lens.p(), qs.p())?;
+
+        // 重要性函数对 (s, t) ∈ [0, 1]² 归一化，镜头上的均匀采样与重要性中的镜头面积相抵消
+        let d = lens.p() - qs.p();
+        let cos_theta = viewport.cos_theta(-d);
+        let importance = viewport.pdf_direction(cos_theta) / cos_theta;
+        let color = qs.beta * qs.f(d, -qs.incoming) * importance;
+        if color.length_squared() == 0.0 {
+            return None;
+        }
+        let g = self.geometry(world, &lens, qs) * cos_theta;
+        if g == 0.0 {
+            return None;
+        }
+        let weight = self.mis_weight(light, camera, Some(&lens), s, 1, Some(viewport));
+        Some((u, v, color * g * weight))
+    }
+
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        viewport: Option<&Viewport>,
+        splat: &mut dyn FnMut(f64, f64, vec3::Color),
+    ) -> vec3::Color {
+        let depth = depth as usize;
+        let mut rec = HitRecord::new();
+        rec.p = r.origin;
+        let mut camera = vec![Vertex::new(
+            VertexKind::Camera,
+            rec,
+            vec3::Vec3::fill(0.0),
+            vec3::Color::fill(1.0),
+        )];
+        let pdf = viewport.map_or(1.0, |v| v.pdf_direction(v.cos_theta(r.direction)));
+        let escaped = self.random_walk(
+            world,
+            *r,
+            vec3::Color::fill(1.0),
+            pdf,
+            depth + 2,
+            false,
+            sampler,
+            &mut camera,
+        );
+        let light = self.light_subpath(world, depth + 1, sampler);
+
+        // 背景只能由相机一侧的路径得到
+        let mut color = match escaped {
+            Some((ray, beta)) => beta * self.background.color(&ray),
+            None => vec3::Color::fill(0.0),
+        };
+        for t in 1..=camera.len() {
+            for s in 0..=light.len() {
+                if s + t < 2 || (s == 1 && t == 1) || s + t - 2 > depth {
+                    continue;
+                }
+                if t > 1 {
+                    color += self.connect(world, &light, &camera, s, t, viewport);
+                } else if let Some(viewport) = viewport {
+                    if let Some((u, v, c)) =
+                        self.connect_camera(world, &light, &camera, s, viewport, sampler)
+                    {
+                        splat(u, v, c);
+                    }
+                }
+            }
+        }
+        color
+    }
+}
+
+impl Default for Bidirectional {
+    fn default() -> Self {
+        Bidirectional::new()
+    }
+}
+
+impl Integrator for Bidirectional {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        self.trace(r, world, depth, sampler, None, &mut |_, _, _| {})
+    }
+
+    fn sample(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        _index: usize,
+        camera: &dyn RayGenerator,
+        splat: &mut dyn FnMut(f64, f64, vec3::Color),
+    ) -> vec3::Color {
+        let viewport = camera.viewport();
+        self.trace(r, world, depth, sampler, viewport.as_ref(), splat)
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((
+            IntegratorDescription::Bidirectional,
+            self.background.describe(),
+        ))
+    }
+}
+
+// 光子：到达位置时的传播方向与功率
+#[derive(Debug, Clone, Copy)]
+struct Photon {
+    direction: vec3::Vec3,
+    power: vec3::Color,
+}
+
+// 一轮发射的光子，emitted 为发射的光子数，radius 为这一轮密度估计的半径
+struct PhotonMap {
+    photons: KdTree<Photon>,
+    emitted: usize,
+    radius: f64,
+}
+
+// 自动选择半径时初始半径内平均的光子数
+const LOOKUP_PHOTONS: usize = 20;
+
+// 渐进式光子映射。每个像素样本使用一轮单独发射的光子：光子从光源出发，
+// 在非镜面的表面上散射，第一次散射之后到达非镜面表面时存入 kd 树。
+// 相机光线经过镜面反射与折射后到达第一个非镜面的表面时，直接光照由朝光源采样得到，
+// 其余来自光源的光照由半径内光子的密度估计得到，焦散也因此可以看到。
+// 第 k 轮的半径满足 r_k² = r_(k-1)² (k + alpha) / (k + 1)，随轮次缩小，
+// 各轮结果的平均收敛到正确的值（概率渐进式光子映射）。
+// 光子只来自 lights，背景的光照仍沿材质采样的路径追踪。
+// 光子图由 start_pass 按轮次构建，end_pass 时释放；未经 start_pass 的轮次在第一次用到时构建。
+// 一个积分器只应用于同一个场景
+pub struct PhotonMapping {
+    pub background: Background,
+    pub lights: Option<Arc<dyn Hittable>>,
+    // 每轮发射的光子数
+    pub photons: usize,
+    // 第一轮的半径，None 时按光子的分布选择
+    pub radius: Option<f64>,
+    pub alpha: f64,
+    // 追踪光子的线程数
+    pub threads: usize,
+    maps: Mutex<PhotonMaps>,
+}
+
+// 各轮次的光子图，seed 为构建它们时渲染的种子
+#[derive(Default)]
+struct PhotonMaps {
+    seed: u64,
+    maps: BTreeMap<usize, Arc<OnceLock<PhotonMap>>>,
+}
+
+// 与渲染的种子混合，光子路径与同一种子下相机光线的随机数不同
+const PHOTON_SEED: u64 = 0x70686f746f6e;
+
+impl PhotonMapping {
+    pub fn new() -> Self {
+        PhotonMapping {
+            background: Background::Sky,
+            lights: None,
+            photons: 100000,
+            radius: None,
+            alpha: 2.0 / 3.0,
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            maps: Mutex::new(PhotonMaps::default()),
+        }
+    }
+
+    // 第 iteration 轮的光子图，尚未构建时构建
+    fn photon_map(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        depth: u64,
+        iteration: usize,
+    ) -> Arc<OnceLock<PhotonMap>> {
+        let (seed, map) = {
+            let mut maps = self.maps.lock().unwrap();
+            let map = maps.maps.entry(iteration).or_default().clone();
+            (maps.seed, map)
+        };
+        // 其他线程可以同时构建其他轮次的光子图
+        map.get_or_init(|| self.build(world, lights, depth, seed, iteration));
+        map
+    }
+
+    // 光子取决于渲染的种子与轮次
+    fn build(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        depth: u64,
+        seed: u64,
+        iteration: usize,
+    ) -> PhotonMap {
+        // 分段由多个线程追踪，按顺序拼接
+        let chunk = self.photons.div_ceil(self.threads.max(1)).max(1);
+        let stored = thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.photons)
+                .step_by(chunk)
+                .map(|start| {
+                    scope.spawn(move || {
+                        let mut sampler = IndependentSampler::new();
+                        sampler.set_seed(seed ^ PHOTON_SEED);
+                        let mut stored = Vec::new();
+                        for i in start..(start + chunk).min(self.photons) {
+                            sampler.start_pixel_sample(i, 0, iteration);
+                            self.trace_photon(world, lights, depth, &mut sampler, &mut stored);
+                        }
+                        stored
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("Photon tracing failed!"))
+                .collect()
+        });
+        let photons = KdTree::new(stored);
+
+        let radius = match self.radius {
+            Some(radius) => radius,
+            None => {
+                // 取部分光子到第 LOOKUP_PHOTONS 个近邻的距离的中位数，
+                // 光子稀疏处的距离很大，平均值会使焦散变得模糊
+                let step = (photons.len() / 64).max(1);
+                let mut distances: Vec<f64> = photons
+                    .points()
+                    .step_by(step)
+                    .filter_map(|(p, _)| {
+                        let nearest = photons.nearest(*p, LOOKUP_PHOTONS);
+                        nearest.last().map(|(distance, _)| *distance)
+                    })
+                    .collect();
+                distances.sort_by(f64::total_cmp);
+                distances.get(distances.len() / 2).copied().unwrap_or(0.0)
+            }
+        };
+        let mut radius_squared = radius * radius;
+        for k in 1..=iteration {
+            radius_squared *= (k as f64 + self.alpha) / (k + 1) as f64;
+        }
+        PhotonMap {
+            photons,
+            emitted: self.photons,
+            radius: radius_squared.sqrt(),
+        }
+    }
+
+    // 从光源发射一个光子，按材质散射，到达的非镜面表面（第一次除外）存入 stored
+    fn trace_photon(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        stored: &mut Vec<(vec3::Point3, Photon)>,
+    ) {
+        let (rec, pdf_area) = match lights.random_point(sampler) {
+            Some(sample) => sample,
+            None => return,
+        };
+        let normal = rec.normal;
+        let side = if sampler.get_1d() < 0.5 {
+            normal
+        } else {
+            -normal
+        };
+        let direction = Onb::new(side).local(sampler::cosine_direction(sampler.get_2d()));
+        let pdf = emission_pdf(normal, direction);
+        if pdf <= 0.0 {
+            return;
+        }
+        let mut power = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p)
+            * normal.dot(direction.unit_vector()).abs()
+            / (pdf_area * pdf);
+        let mut ray = Ray::new(rec.p, direction);
+
+        for bounce in 0..depth {
+            let mut rec = HitRecord::new();
+            if !world.hit(&ray, 0.001, f64::INFINITY, &mut rec) {
+                return;
+            }
+            let srec = match rec.mat_ptr.scatter(&ray, &rec, sampler) {
+                Some(srec) => srec,
+                None => return,
+            };
+            let attenuation = if srec.is_specular {
+                srec.attenuation
+            } else {
+                if bounce > 0 {
+                    let photon = Photon {
+                        direction: ray.direction,
+                        power,
+                    };
+                    stored.push((rec.p, photon));
+                }
+                let direction = srec.scattered.direction;
+                let cosine = rec.normal.dot(direction.unit_vector()).abs();
+                material_f(&rec, direction, -ray.direction) * cosine / srec.pdf
+            };
+
+            // 按衰减决定光子是否继续
+            let p = attenuation
+                .x()
+                .max(attenuation.y())
+                .max(attenuation.z())
+                .min(0.95);
+            if sampler.get_1d() >= p {
+                return;
+            }
+            power *= attenuation / p;
+            ray = srec.scattered;
+        }
+    }
+
+    // 交点处由光子密度估计的出射光
+    fn gather(map: &PhotonMap, r: &Ray, rec: &HitRecord) -> vec3::Color {
+        let mut sum = vec3::Color::fill(0.0);
+        if map.photons.is_empty() || map.radius <= 0.0 {
+            return sum;
+        }
+        map.photons.for_each_within(rec.p, map.radius, |_, photon| {
+            sum += material_f(rec, -r.direction, -photon.direction) * photon.power;
+        });
+        sum / (PI * map.radius * map.radius * map.emitted as f64)
+    }
+
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        iteration: usize,
+    ) -> vec3::Color {
+        let mut color = vec3::Color::fill(0.0);
+        let mut throughput = vec3::Color::fill(1.0);
+        let mut r = *r;
+        // 已经估计过来自光源的光照之后，只有背景的光照还需要追踪
+        let mut gathered = false;
+        let dark = matches!(self.background, Background::Color(c) if c.length_squared() == 0.0);
+        for _ in 0..depth {
+            let mut rec = HitRecord::new();
+            if !world.hit(&r, 0.001, f64::INFINITY, &mut rec) {
+                return color + throughput * self.background.color(&r);
+            }
+            if !gathered {
+                color += throughput * rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+            }
+            let srec = match rec.mat_ptr.scatter(&r, &rec, sampler) {
+                Some(srec) => srec,
+                None => return color,
+            };
+            if let Some(lights) = self
+                .lights
+                .as_ref()
+                .filter(|_| !gathered && !srec.is_specular)
+            {
+                gathered = true;
+                if let Some((_, pdf, contribution)) =
+                    sample_light(&r, &rec, &**lights, world, sampler)
+                {
+                    color += throughput * contribution / pdf;
+                }
+                let map = self.photon_map(world, &**lights, depth, iteration);
+                color += throughput * PhotonMapping::gather(map.get().unwrap(), &r, &rec);
+                if dark {
+                    return color;
+                }
+            }
+            throughput *= srec.attenuation;
+            r = srec.scattered;
+        }
+        color
+    }
+}
+
+impl Default for PhotonMapping {
+    fn default() -> Self {
+        PhotonMapping::new()
+    }
+}
+
+impl Integrator for PhotonMapping {
+    // 单独计算一条光线时使用第一轮的光子
+    fn ray_color(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+    ) -> vec3::Color {
+        self.trace(r, world, depth, sampler, 0)
+    }
+
+    fn sample(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: u64,
+        sampler: &mut dyn Sampler,
+        index: usize,
+        _camera: &dyn RayGenerator,
+        _splat: &mut dyn FnMut(f64, f64, vec3::Color),
+    ) -> vec3::Color {
+        self.trace(r, world, depth, sampler, index)
+    }
+
+    fn start_pass(&self, world: &dyn Hittable, depth: u64, seed: u64, samples: Range<usize>) {
+        let lights = match &self.lights {
+            Some(lights) => lights,
+            None => return,
+        };
+        {
+            let mut maps = self.maps.lock().unwrap();
+            if maps.seed != seed {
+                maps.seed = seed;
+                maps.maps.clear();
+            }
+        }
+        for iteration in samples {
+            self.photon_map(world, &**lights, depth, iteration);
+        }
+    }
+
+    fn end_pass(&self, samples: Range<usize>) {
+        let mut maps = self.maps.lock().unwrap();
+        for iteration in samples {
+            maps.maps.remove(&iteration);
+        }
+    }
+
+    fn describe(&self) -> Option<(IntegratorDescription, BackgroundDescription)> {
+        Some((
+            IntegratorDescription::PhotonMapping,
+            self.background.describe(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::camera::{Camera, PositionableCamera};
+    use crate::hittable_list::HittableList;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, ScatterRecord};
+    use crate::quad::Quad;
+    use crate::renderer::{ImageSettings, Renderer};
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+
+    // 多次采样同一条光线的平均颜色
+    fn estimate(integrator: &dyn Integrator, world: &dyn Hittable, r: &Ray, n: usize) -> f64 {
+        let mut sampler = IndependentSampler::new();
+        let mut sum = 0.0;
+        for i in 0..n {
+            sampler.start_pixel_sample(0, 0, i);
+            sum += integrator.ray_color(r, world, 3, &mut sampler).x();
+        }
+        sum / n as f64
+    }
+
+    // 同时发光的漫反射材质
+    #[derive(Debug, Clone)]
+    struct Glow {
+        lambertian: Lambertian,
+    }
+
+    impl Material for Glow {
+        fn scatter(
+            &self,
+            r_in: &Ray,
+            rec: &HitRecord,
+            sampler: &mut dyn Sampler,
+        ) -> Option<ScatterRecord> {
+            self.lambertian.scatter(r_in, rec, sampler)
+        }
+        fn emitted(&self, _u: f64, _v: f64, _p: &vec3::Point3) -> vec3::Color {
+            vec3::Color::fill(1.0)
+        }
+        fn rc_clone(&self) -> Arc<dyn Material> {
+            Arc::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_path_tracer() {
+        // 在反照率为 0.8、自发光为 1 的封闭球内，辐射亮度为 1 / (1 - 0.8) = 5
+        let mut sphere = Sphere::new(vec3::Point3::fill(0.0), 1.0);
+        sphere.mat_ptr = Arc::new(Glow {
+            lambertian: Lambertian::new(vec3::Color::fill(0.8)),
+        });
+        let r = Ray::new(vec3::Point3::fill(0.0), vec3::Vec3(0.0, 0.0, -1.0));
+
+        let path_tracer = PathTracer::new();
+        let value = estimate(&path_tracer, &sphere, &r, 20000);
+        assert!((value - 5.0).abs() < 0.1);
+        // 递归的积分器在深度限制处截断，结果偏暗
+        assert!(estimate(&Recursive::new(), &sphere, &r, 1000) < 3.0);
+    }
+
+    // 地面上方 y = 1 处的方形光源，返回场景与光源
+    fn light_over_ground(ground_material: Arc<dyn Material>) -> (HittableList, Arc<dyn Hittable>) {
+        let mut light = Quad::new(
+            vec3::Vec3(-1.0, 1.0, -1.0),
+            vec3::Vec3(2.0, 0.0, 0.0),
+            vec3::Vec3(0.0, 0.0, 2.0),
+        );
+        light.mat_ptr = Arc::new(DiffuseLight::new(vec3::Color::fill(1.0)));
+        let mut lights = HittableList::new();
+        lights.add(Arc::new(light.clone()));
+
+        let mut ground = Quad::new(
+            vec3::Vec3(-10.0, 0.0, 10.0),
+            vec3::Vec3(20.0, 0.0, 0.0),
+            vec3::Vec3(0.0, 0.0, -20.0),
+        );
+        ground.mat_ptr = ground_material;
+        let mut world = HittableList::new();
+        world.add(Arc::new(light));
+        world.add(Arc::new(ground));
+        (world, Arc::new(lights))
+    }
+
+    fn grounds() -> [Arc<dyn Material>; 2] {
+        [
+            Arc::new(Lambertian::new(vec3::Color::fill(0.5))),
+            Arc::new(Metal::new(vec3::Color::fill(0.5), 0.3)),
+        ]
+    }
+
+    #[test]
+    fn test_mis() {
+        let heuristic = Heuristic::Power;
+        assert!((heuristic.weight(1.0, 3.0) + heuristic.weight(3.0, 1.0) - 1.0).abs() < 1e-12);
+        assert_eq!(Heuristic::Balance.weight(2.0, 0.0), 1.0);
+
+        // 分别照亮漫反射与光泽的地面
+        let r = Ray::new(vec3::Vec3(0.0, 0.9, 1.5), vec3::Vec3(0.0, -0.9, -0.7));
+        for ground in grounds() {
+            let (world, lights) = light_over_ground(ground);
+            let mut recursive = Recursive::new();
+            recursive.background = Background::Color(vec3::Color::fill(0.0));
+            let expected = estimate(&recursive, &world, &r, 200000);
+            for heuristic in [Heuristic::Balance, Heuristic::Power] {
+                let mut mis = Mis::new();
+                mis.background = Background::Color(vec3::Color::fill(0.0));
+                mis.lights = Some(lights.clone());
+                mis.heuristic = heuristic;
+                let value = estimate(&mis, &world, &r, 20000);
+                assert!((value - expected).abs() < 0.02 * expected);
+            }
+        }
+    }
+
+    // 渲染小图像的平均亮度
+    fn render_mean(world: HittableList, integrator: Arc<dyn Integrator>, samples: usize) -> f64 {
+        let camera = PositionableCamera::new(
+            vec3::Vec3(0.0, 0.5, 2.0),
+            vec3::Point3::fill(0.0),
+            vec3::Vec3(0.0, 1.0, 0.0),
+            60.0,
+            1.0,
+        );
+        let image = ImageSettings::new(8, 1.0, samples, 10);
+        let mut renderer = Renderer::new(Arc::new(world), Arc::new(camera), image, integrator, "");
+        renderer.threads = 1;
+        let film = renderer.render();
+        let mut sum = 0.0;
+        for row in 0..film.height {
+            for col in 0..film.width {
+                sum += film.pixel(col, row).x();
+            }
+        }
+        sum / (film.width * film.height) as f64
+    }
+
+    #[test]
+    fn test_bidirectional() {
+        let r = Ray::new(vec3::Vec3(0.0, 0.9, 1.5), vec3::Vec3(0.0, -0.9, -0.7));
+        for ground in grounds() {
+            let (world, lights) = light_over_ground(ground.clone());
+            let mut recursive = Recursive::new();
+            recursive.background = Background::Color(vec3::Color::fill(0.0));
+            let mut bidirectional = Bidirectional::new();
+            bidirectional.background = Background::Color(vec3::Color::fill(0.0));
+            bidirectional.lights = Some(lights);
+
+            // 不连接镜头时其余的连接方式仍然完整
+            let expected = estimate(&recursive, &world, &r, 200000);
+            let value = estimate(&bidirectional, &world, &r, 20000);
+            assert!((value - expected).abs() < 0.02 * expected);
+
+            // 连接镜头的贡献通过 splat 累加到图像上
+            let (image_world, _) = light_over_ground(ground);
+            let expected = render_mean(image_world, Arc::new(recursive), 1024);
+            let value = render_mean(world, Arc::new(bidirectional), 256);
+            assert!((value - expected).abs() < 0.02 * expected);
+        }
+    }
+
+    #[test]
+    fn test_photon_mapping() {
+        // 光源下方的玻璃球在地面上形成焦散，光线从侧面贴近地面射向球的正下方，
+        // 那里的直接光照被玻璃球挡住
+        let (mut world, lights) =
+            light_over_ground(Arc::new(Lambertian::new(vec3::Color::fill(0.5))));
+        let mut glass = Sphere::new(vec3::Vec3(0.0, 0.3, 0.0), 0.3);
+        glass.mat_ptr = Arc::new(Dielectric::new(1.5));
+        world.add(Arc::new(glass));
+        let r = Ray::new(vec3::Vec3(1.0, 0.05, 0.0), vec3::Vec3(-0.95, -0.05, 0.0));
+
+        let mut sampler = IndependentSampler::new();
+        let mut recursive = Recursive::new();
+        recursive.background = Background::Color(vec3::Color::fill(0.0));
+        let n = 200000;
+        let mut expected = 0.0;
+        for i in 0..n {
+            sampler.start_pixel_sample(0, 0, i);
+            expected += recursive.ray_color(&r, &world, 10, &mut sampler).x() / n as f64;
+        }
+
+        let mut photon_mapping = PhotonMapping::new();
+        photon_mapping.background = Background::Color(vec3::Color::fill(0.0));
+        photon_mapping.lights = Some(lights);
+        photon_mapping.photons = 50000;
+        photon_mapping.radius = Some(0.03);
+        let camera = Camera::new();
+        let (n, iterations) = (20000, 64);
+        let mut value = 0.0;
+        for i in 0..n {
+            sampler.start_pixel_sample(0, 0, i);
+            let color = photon_mapping.sample(
+                &r,
+                &world,
+                10,
+                &mut sampler,
+                i * iterations / n,
+                &camera,
+                &mut |_, _, _| {},
+            );
+            value += color.x() / n as f64;
+        }
+        assert!((value - expected).abs() < 0.12 * expected);
+
+        // 渲染器准备的光子图随种子变化，一轮结束后释放
+        photon_mapping.end_pass(0..iterations);
+        let first_photon = |seed| {
+            photon_mapping.start_pass(&world, 10, seed, 0..2);
+            let maps = photon_mapping.maps.lock().unwrap();
+            assert_eq!(maps.maps.len(), 2);
+            let map = maps.maps[&0].get().unwrap();
+            let first = map.photons.points().next().unwrap().0;
+            first.to_array()
+        };
+        assert_ne!(first_photon(0), first_photon(1));
+        photon_mapping.end_pass(0..2);
+        assert!(photon_mapping.maps.lock().unwrap().maps.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::aabb::Aabb;
+use crate::vec3;
+
+// 三维点的 kd 树。每个节点在坐标范围最大的轴上按中位数划分，
+// 节点隐式地存放在数组中：区间 [start, end) 的中点为节点，左右两半为子树
+pub struct KdTree<T> {
+    points: Vec<(vec3::Point3, T)>,
+    axes: Vec<usize>,
+}
+
+impl<T> KdTree<T> {
+    pub fn new(mut points: Vec<(vec3::Point3, T)>) -> Self {
+        let mut axes = vec![0; points.len()];
+        build(&mut points, &mut axes);
+        KdTree { points, axes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &(vec3::Point3, T)> {
+        self.points.iter()
+    }
+
+    // 对与 p 的距离不超过 radius 的每个点调用 f
+    pub fn for_each_within<F: FnMut(vec3::Point3, &T)>(
+        &self,
+        p: vec3::Point3,
+        radius: f64,
+        mut f: F,
+    ) {
+        self.within(0, self.points.len(), p, radius * radius, &mut f);
+    }
+
+    fn within<F: FnMut(vec3::Point3, &T)>(
+        &self,
+        start: usize,
+        end: usize,
+        p: vec3::Point3,
+        radius_squared: f64,
+        f: &mut F,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let (q, value) = &self.points[mid];
+        if (*q - p).length_squared() <= radius_squared {
+            f(*q, value);
+        }
+        let d = p[self.axes[mid]] - q[self.axes[mid]];
+        // 先进入 p 所在的一侧，另一侧只在与球相交时进入
+        let (near, far) = if d <= 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.within(near.0, near.1, p, radius_squared, f);
+        if d * d <= radius_squared {
+            self.within(far.0, far.1, p, radius_squared, f);
+        }
+    }
+
+    // 离 p 最近的至多 k 个点，返回 (距离, 值)，按距离从小到大排列
+    pub fn nearest(&self, p: vec3::Point3, k: usize) -> Vec<(f64, &T)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search(0, self.points.len(), p, k, &mut heap);
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.distance_squared.sqrt(), &self.points[c.index].1))
+            .collect()
+    }
+
+    // heap 中保存当前最近的 k 个点，堆顶为其中最远的
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        p: vec3::Point3,
+        k: usize,
+        heap: &mut BinaryHeap<Candidate>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let q = self.points[mid].0;
+        let distance_squared = (q - p).length_squared();
+        if heap.len() < k {
+            heap.push(Candidate {
+                distance_squared,
+                index: mid,
+            });
+        } else if distance_squared < heap.peek().unwrap().distance_squared {
+            heap.pop();
+            heap.push(Candidate {
+                distance_squared,
+                index: mid,
+            });
+        }
+        let d = p[self.axes[mid]] - q[self.axes[mid]];
+        let (near, far) = if d <= 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.search(near.0, near.1, p, k, heap);
+        if heap.len() < k || d * d < heap.peek().unwrap().distance_squared {
+            self.search(far.0, far.1, p, k, heap);
+        }
+    }
+}
+
+fn build<T>(points: &mut [(vec3::Point3, T)], axes: &mut [usize]) {
+    if points.len() <= 1 {
+        return;
+    }
+    let axis = points
+        .iter()
+        .fold(Aabb::empty(), |bbox, (p, _)| bbox.include(*p))
+        .longest_axis();
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+    axes[mid] = axis;
+    let (left, right) = points.split_at_mut(mid);
+    let (left_axes, right_axes) = axes.split_at_mut(mid);
+    build(left, left_axes);
+    build(&mut right[1..], &mut right_axes[1..]);
+}
+
+// 最近邻搜索的候选点，按距离排序
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance_squared: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared.total_cmp(&other.distance_squared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn test_kdtree() {
+        let mut rng = utils::Rng::new(7);
+        let points: Vec<_> = (0..500)
+            .map(|i| (vec3::Vec3::random(&mut rng), i))
+            .collect();
+        let tree = KdTree::new(points.clone());
+        assert_eq!(tree.len(), 500);
+
+        // 与逐个比较的结果一致
+        for _ in 0..20 {
+            let p = vec3::Vec3::random(&mut rng);
+            let mut found = Vec::new();
+            tree.for_each_within(p, 0.2, |_, &i| found.push(i));
+            found.sort();
+            let expected: Vec<_> = points
+                .iter()
+                .filter(|(q, _)| (*q - p).length() <= 0.2)
+                .map(|&(_, i)| i)
+                .collect();
+            assert_eq!(found, expected);
+
+            let mut distances: Vec<_> = points.iter().map(|(q, _)| (*q - p).length()).collect();
+            distances.sort_by(f64::total_cmp);
+            let nearest = tree.nearest(p, 8);
+            assert_eq!(nearest.len(), 8);
+            for (i, (distance, _)) in nearest.iter().enumerate() {
+                assert!((distance - distances[i]).abs() < 1e-12);
+            }
+        }
+        assert!(KdTree::<usize>::new(Vec::new())
+            .nearest(vec3::Point3::fill(0.0), 4)
+            .is_empty());
+    }
+}
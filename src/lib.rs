@@ -0,0 +1,29 @@
+pub mod aabb;
+pub mod camera;
+pub mod checkpoint;
+pub mod film;
+pub mod hittable;
+pub mod hittable_list;
+pub mod import;
+pub mod integrator;
+pub mod kdtree;
+pub mod material;
+pub mod mesh;
+pub mod metrics;
+pub mod onb;
+pub mod pdf;
+pub mod quad;
+pub mod ray;
+pub mod renderer;
+pub mod sampler;
+pub mod scene;
+pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod utils;
+pub mod vec3;
+
+// README 中的示例作为文档测试编译
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
@@ -1,68 +1,133 @@
 use std::env;
 use std::io;
+use std::path::Path;
+use std::process;
 
-mod camera;
-mod hittable;
-mod hittable_list;
-mod material;
-mod ray;
-mod sphere;
-mod utils;
-mod vec3;
+use ray_tracing_rs::import;
+use ray_tracing_rs::renderer::{Renderer, DEFAULT_SEED};
+use ray_tracing_rs::scene::SceneDescription;
 
+mod cli;
 mod demo;
 
-type Demo = Vec<Box<dyn Fn() -> io::Result<()>>>;
+fn fail(message: &str, code: i32) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(code);
+}
+
+// JSON 场景描述，或者由其他渲染器的场景文件导入
+fn load_scene(path: &str, output: &str) -> io::Result<Renderer> {
+    if Path::new(path).extension().is_some_and(|e| e == "json") {
+        return SceneDescription::load(path)?.renderer(output);
+    }
+    let scene = import::load(path)?;
+    for warning in &scene.warnings {
+        eprintln!("warning: {}: {}", path, warning);
+    }
+    scene.renderer(output)
+}
+
+// 渲染，或者指定了 --export 时只导出场景
+fn finish(renderer: &mut Renderer, options: &cli::Options) -> io::Result<()> {
+    options.apply(renderer);
+    match &options.export {
+        Some(path) => SceneDescription::from_renderer(renderer)?.save(path),
+        None => renderer.run(),
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let options = match cli::Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            process::exit(2);
+        }
+    };
 
-    if args.len() != 2 {
-        panic!("{}", "USAGE: cargo run {number}");
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
     }
 
-    let demo: Demo = vec![
-        Box::new(demo::demo00::run),
-        Box::new(demo::demo01::run),
-        Box::new(demo::demo02::run),
-        Box::new(demo::demo03::run),
-        Box::new(demo::demo04::run),
-        Box::new(demo::demo05::run),
-        Box::new(demo::demo06::run),
-        Box::new(demo::demo07::run),
-        Box::new(demo::demo08::run),
-        Box::new(demo::demo09::run),
-        Box::new(demo::demo10::run),
-        Box::new(demo::demo11::run),
-        Box::new(demo::demo12::run),
-        Box::new(demo::demo13::run),
-        Box::new(demo::demo14::run),
-        Box::new(demo::demo15::run),
-        Box::new(demo::demo16::run),
-        Box::new(demo::demo17::run),
-        Box::new(demo::demo18::run),
-        Box::new(demo::demo19::run),
-        Box::new(demo::demo20::run),
-        Box::new(demo::demo21::run),
-    ];
+    if let Some(path) = &options.scene_file {
+        let output = Path::new(path).with_extension("ppm");
+        let result = load_scene(path, &output.to_string_lossy())
+            .and_then(|mut renderer| finish(&mut renderer, &options));
+        if let Err(e) = result {
+            fail(&format!("{}: {}", path, e), 1);
+        }
+        return;
+    }
 
-    let length = demo.len();
+    let demos = demo::all();
 
-    // Run all demo
-    if args[1] == "*" {
-        for run in demo.iter() {
-            run().unwrap();
+    if options.list {
+        for (n, demo) in demos.iter().enumerate() {
+            let kind = if demo.renderer.is_some() {
+                ""
+            } else {
+                " (fixed)"
+            };
+            println!("{:>2}  {}{}", n, demo.description, kind);
         }
+        return;
     }
 
-    // Run specified demo
-    if let Ok(n) = args[1].parse::<usize>() {
-        if n < length {
-            demo[n]().unwrap();
-        } else {
-            panic!("Demo{} is not existed", n);
+    let scene = match &options.scene {
+        Some(scene) => scene,
+        None => {
+            eprintln!("error: missing scene\n\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    // Run all demo or specified demo
+    let selected: Vec<usize> = if scene == "*" {
+        if options.output.is_some() {
+            fail("--output cannot be used when rendering every scene", 2);
+        }
+        if options.export.is_some() {
+            fail("--export cannot be used when rendering every scene", 2);
         }
+        (0..demos.len()).collect()
     } else {
-        panic!("Expected a number");
+        match scene.parse::<usize>() {
+            Ok(n) if n < demos.len() => vec![n],
+            _ => fail(
+                &format!(
+                    "unknown scene `{}`, expected 0..{} or `*` (see --list)",
+                    scene,
+                    demos.len() - 1
+                ),
+                2,
+            ),
+        }
+    };
+
+    for n in selected {
+        let demo = &demos[n];
+        let result = if options.has_render_options() {
+            match demo.renderer {
+                Some(renderer) => {
+                    let mut renderer = renderer(options.seed.unwrap_or(DEFAULT_SEED));
+                    finish(&mut renderer, &options)
+                }
+                None if scene == "*" => (demo.run)(),
+                None => fail(
+                    &format!(
+                        "scene {} is a fixed image and takes no rendering options",
+                        n
+                    ),
+                    2,
+                ),
+            }
+        } else {
+            (demo.run)()
+        };
+
+        if let Err(e) = result {
+            fail(&format!("scene {}: {}", n, e), 1);
+        }
     }
 }
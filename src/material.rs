@@ -1,22 +1,80 @@
+use std::f64::consts::PI;
 use std::fmt::Debug;
 use std::sync::Arc;
 
 use crate::hittable::HitRecord;
+use crate::pdf::{CosinePdf, Pdf};
 use crate::ray::Ray;
-use crate::utils;
+use crate::sampler::{self, Sampler};
+use crate::scene::MaterialDescription;
+use crate::texture::Texture;
 use crate::vec3;
 
+// 一次散射的结果。非镜面散射时 attenuation 为 eval / pdf，镜面散射（理想反射与折射）
+// 的方向是确定的，pdf 没有意义，不能用于光源采样。
+// 同一个材质的散射要么都是镜面的，要么都不是
+#[derive(Debug, Clone, Copy)]
+pub struct ScatterRecord {
+    pub attenuation: vec3::Color,
+    pub scattered: Ray,
+    pub pdf: f64,
+    pub is_specular: bool,
+}
+
+impl ScatterRecord {
+    pub fn specular(attenuation: vec3::Color, scattered: Ray) -> Self {
+        ScatterRecord {
+            attenuation,
+            scattered,
+            pdf: 0.0,
+            is_specular: true,
+        }
+    }
+}
+
 pub trait Material: Debug + Send + Sync {
+    // 按材质的分布采样散射方向，光线被吸收时返回 None
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut vec3::Color,
-        scattered: &mut Ray,
-    ) -> bool;
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+    // 散射方向为 scattered 时 BSDF 与余弦的乘积，镜面部分不计入
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> vec3::Color {
+        vec3::Color::fill(0.0)
+    }
+    // scatter 采样出 scattered 方向的概率密度（对立体角）
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+    // 自发光，默认不发光
+    fn emitted(&self, _u: f64, _v: f64, _p: &vec3::Point3) -> vec3::Color {
+        vec3::Color::fill(0.0)
+    }
+    // 导出场景时的描述，无法描述的材质返回 None
+    fn describe(&self) -> Option<MaterialDescription> {
+        None
+    }
     fn rc_clone(&self) -> Arc<dyn Material>;
 }
 
+// 反射方向加上半径为 fuzz 的球内均匀偏移后，方向 direction 的概率密度：
+// 沿该方向穿过偏移球的线段上 t² 的积分除以球的体积
+fn fuzz_pdf(reflected: vec3::Vec3, fuzz: f64, direction: vec3::Vec3) -> f64 {
+    let c = direction.unit_vector().dot(reflected.unit_vector());
+    let d = c * c - 1.0 + fuzz * fuzz;
+    if fuzz <= 0.0 || d <= 0.0 {
+        return 0.0;
+    }
+    let t_far = c + d.sqrt();
+    if t_far <= 0.0 {
+        return 0.0;
+    }
+    let t_near = (c - d.sqrt()).max(0.0);
+    (t_far.powi(3) - t_near.powi(3)) / (4.0 * PI * fuzz.powi(3))
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct DefaultMaterial {}
 
@@ -26,15 +84,23 @@ impl DefaultMaterial {
     }
 }
 
+impl Default for DefaultMaterial {
+    fn default() -> Self {
+        DefaultMaterial::new()
+    }
+}
+
 impl Material for DefaultMaterial {
     fn scatter(
         &self,
         _r_in: &Ray,
         _rec: &HitRecord,
-        _attenuation: &mut vec3::Color,
-        _scattered: &mut Ray,
-    ) -> bool {
-        false
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Default)
     }
     fn rc_clone(&self) -> Arc<dyn Material> {
         Arc::new(DefaultMaterial::new())
@@ -57,18 +123,27 @@ impl Material for Lambertian {
         &self,
         _r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut vec3::Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + vec3::Vec3::random_unit_vector();
-
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-
-        *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = self.albedo;
-        true
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let pdf = CosinePdf::new(rec.normal);
+        let scatter_direction = pdf.generate(sampler);
+        Some(ScatterRecord {
+            attenuation: self.albedo,
+            scattered: Ray::new(rec.p, scatter_direction),
+            pdf: pdf.value(scatter_direction),
+            is_specular: false,
+        })
+    }
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> vec3::Color {
+        self.albedo * self.pdf(r_in, rec, scattered)
+    }
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        CosinePdf::new(rec.normal).value(scattered.direction)
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Lambertian {
+            albedo: self.albedo.to_array(),
+        })
     }
     fn rc_clone(&self) -> Arc<dyn Material> {
         Arc::new(Lambertian::new(self.albedo))
@@ -93,16 +168,42 @@ impl Material for Metal {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut vec3::Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = vec3::Vec3::reflect(r_in.direction.unit_vector(), rec.normal);
-        *scattered = Ray::new(
+        let scattered = Ray::new(
             rec.p,
-            reflected + self.fuzz * vec3::Vec3::random_in_unit_sphere(),
+            reflected + self.fuzz * sampler::in_unit_sphere(sampler.get_2d(), sampler.get_1d()),
         );
-        *attenuation = self.albedo;
-        scattered.direction.dot(rec.normal) > 0.0
+        // 偏移到表面以下的光线被吸收
+        if scattered.direction.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+        if self.fuzz <= 0.0 {
+            return Some(ScatterRecord::specular(self.albedo, scattered));
+        }
+        Some(ScatterRecord {
+            attenuation: self.albedo,
+            scattered,
+            pdf: fuzz_pdf(reflected, self.fuzz, scattered.direction),
+            is_specular: false,
+        })
+    }
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> vec3::Color {
+        if scattered.direction.dot(rec.normal) <= 0.0 {
+            return vec3::Color::fill(0.0);
+        }
+        self.albedo * self.pdf(r_in, rec, scattered)
+    }
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let reflected = vec3::Vec3::reflect(r_in.direction.unit_vector(), rec.normal);
+        fuzz_pdf(reflected, self.fuzz, scattered.direction)
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Metal {
+            albedo: self.albedo.to_array(),
+            fuzz: self.fuzz,
+        })
     }
     fn rc_clone(&self) -> Arc<dyn Material> {
         Arc::new(Metal::new(self.albedo, 0.0))
@@ -131,10 +232,8 @@ impl Material for Dielectric {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut vec3::Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = vec3::Color::fill(1.0);
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -147,17 +246,250 @@ impl Material for Dielectric {
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
         let direction = if cannot_refract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > utils::random()
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
         {
             vec3::Vec3::reflect(unit_direction, rec.normal)
         } else {
             vec3::Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
         };
 
-        *scattered = Ray::new(rec.p, direction);
-        true
+        Some(ScatterRecord::specular(
+            vec3::Color::fill(1.0),
+            Ray::new(rec.p, direction),
+        ))
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Dielectric { ir: self.ir })
     }
     fn rc_clone(&self) -> Arc<dyn Material> {
         Arc::new(Dielectric::new(self.ir))
     }
 }
+
+// 发光材质，不散射光线
+#[derive(Debug, Clone, Copy)]
+pub struct DiffuseLight {
+    emit: vec3::Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: vec3::Color) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+    fn emitted(&self, _u: f64, _v: f64, _p: &vec3::Point3) -> vec3::Color {
+        self.emit
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::DiffuseLight {
+            emission: self.emit.to_array(),
+        })
+    }
+    fn rc_clone(&self) -> Arc<dyn Material> {
+        Arc::new(DiffuseLight::new(self.emit))
+    }
+}
+
+// glTF 的金属度-粗糙度材质，按金属度随机选择金属反射或带镜面高光的漫反射，
+// 贴图与对应的系数相乘，金属度-粗糙度贴图的蓝色通道为金属度、绿色通道为粗糙度
+#[derive(Debug, Clone)]
+pub struct MetallicRoughness {
+    pub base_color: vec3::Color,
+    pub base_color_texture: Option<Arc<dyn Texture>>,
+    pub metallic: f64,
+    pub roughness: f64,
+    pub metallic_roughness_texture: Option<Arc<dyn Texture>>,
+    pub emission: vec3::Color,
+    pub emissive_texture: Option<Arc<dyn Texture>>,
+}
+
+impl MetallicRoughness {
+    pub fn new(base_color: vec3::Color, metallic: f64, roughness: f64) -> Self {
+        MetallicRoughness {
+            base_color,
+            base_color_texture: None,
+            metallic,
+            roughness,
+            metallic_roughness_texture: None,
+            emission: vec3::Color::fill(0.0),
+            emissive_texture: None,
+        }
+    }
+}
+
+// 模糊半径的下限。镜面与漫反射分支都有概率密度，混合采样时才能正确加权
+const MIN_FUZZ: f64 = 1e-3;
+
+impl MetallicRoughness {
+    // 交点处乘上贴图后的 (基础色, 金属度, 模糊半径)
+    fn lookup(&self, rec: &HitRecord) -> (vec3::Color, f64, f64) {
+        let mut base_color = self.base_color;
+        if let Some(texture) = &self.base_color_texture {
+            base_color *= texture.value(rec.u, rec.v, &rec.p);
+        }
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(texture) = &self.metallic_roughness_texture {
+            let value = texture.value(rec.u, rec.v, &rec.p);
+            metallic *= value.z();
+            roughness *= value.y();
+        }
+        (
+            base_color,
+            metallic,
+            (roughness * roughness).clamp(MIN_FUZZ, 1.0),
+        )
+    }
+
+    // 非金属部分的镜面反射率，法线方向为 0.04
+    fn reflectance(r_in: &Ray, rec: &HitRecord) -> f64 {
+        let cos_theta = (-r_in.direction.unit_vector())
+            .dot(rec.normal)
+            .clamp(0.0, 1.0);
+        0.04 + 0.96 * (1.0 - cos_theta).powi(5)
+    }
+
+    // 三个分支 (金属反射, 非金属镜面反射, 漫反射) 的 eval 与 pdf 之和
+    fn eval_pdf(&self, r_in: &Ray, rec: &HitRecord, direction: vec3::Vec3) -> (vec3::Color, f64) {
+        let (base_color, metallic, fuzz) = self.lookup(rec);
+        let reflectance = MetallicRoughness::reflectance(r_in, rec);
+        let reflected = vec3::Vec3::reflect(r_in.direction.unit_vector(), rec.normal);
+        let glossy = fuzz_pdf(reflected, fuzz, direction);
+        let diffuse = CosinePdf::new(rec.normal).value(direction);
+        let pdf = (metallic + (1.0 - metallic) * reflectance) * glossy
+            + (1.0 - metallic) * (1.0 - reflectance) * diffuse;
+        if direction.dot(rec.normal) <= 0.0 {
+            return (vec3::Color::fill(0.0), pdf);
+        }
+        let eval = metallic * glossy * base_color
+            + vec3::Color::fill((1.0 - metallic) * reflectance * glossy)
+            + (1.0 - metallic) * (1.0 - reflectance) * diffuse * base_color;
+        (eval, pdf)
+    }
+}
+
+impl Material for MetallicRoughness {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        // 按金属度选择金属反射，否则按反射率选择镜面反射或漫反射
+        let (_, metallic, fuzz) = self.lookup(rec);
+        let reflected = vec3::Vec3::reflect(r_in.direction.unit_vector(), rec.normal)
+            + fuzz * sampler::in_unit_sphere(sampler.get_2d(), sampler.get_1d());
+        // 各分支的随机数都要取用，之后的维度才不随所选的分支错位
+        let (u_metallic, u_reflectance) = (sampler.get_1d(), sampler.get_1d());
+        let glossy =
+            u_metallic < metallic || u_reflectance < MetallicRoughness::reflectance(r_in, rec);
+        let diffuse = CosinePdf::new(rec.normal).generate(sampler);
+
+        let scattered = if !glossy {
+            Ray::new(rec.p, diffuse)
+        } else if reflected.dot(rec.normal) > 0.0 {
+            Ray::new(rec.p, reflected)
+        } else {
+            return None;
+        };
+        let (eval, pdf) = self.eval_pdf(r_in, rec, scattered.direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            attenuation: eval / pdf,
+            scattered,
+            pdf,
+            is_specular: false,
+        })
+    }
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> vec3::Color {
+        self.eval_pdf(r_in, rec, scattered.direction).0
+    }
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.eval_pdf(r_in, rec, scattered.direction).1
+    }
+    fn emitted(&self, u: f64, v: f64, p: &vec3::Point3) -> vec3::Color {
+        match &self.emissive_texture {
+            Some(texture) => self.emission * texture.value(u, v, p),
+            None => self.emission,
+        }
+    }
+    // 场景描述中没有贴图，有贴图时无法导出
+    fn describe(&self) -> Option<MaterialDescription> {
+        let textured = self.base_color_texture.is_some()
+            || self.metallic_roughness_texture.is_some()
+            || self.emissive_texture.is_some();
+        if textured {
+            return None;
+        }
+        Some(MaterialDescription::MetallicRoughness {
+            base_color: self.base_color.to_array(),
+            metallic: self.metallic,
+            roughness: self.roughness,
+            emission: self.emission.to_array(),
+        })
+    }
+    fn rc_clone(&self) -> Arc<dyn Material> {
+        Arc::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    #[test]
+    fn test_scatter_pdf() {
+        let mut rec = HitRecord::new();
+        rec.normal = vec3::Vec3(0.0, 0.0, 1.0);
+        let r_in = Ray::new(vec3::Vec3(-1.0, 0.0, 1.0), vec3::Vec3(1.0, 0.0, -1.0));
+
+        // 按网格在单位球面上积分，采样的概率密度积分为 1
+        let integrate = |material: &dyn Material| {
+            let n = 400;
+            let mut sum = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                    let scattered = Ray::new(rec.p, sampler::unit_vector(u));
+                    sum += material.pdf(&r_in, &rec, &scattered);
+                }
+            }
+            sum * 4.0 * PI / (n * n) as f64
+        };
+        let metal = Metal::new(vec3::Color::fill(0.8), 0.3);
+        let plastic = MetallicRoughness::new(vec3::Color::fill(0.5), 0.5, 0.6);
+        assert!((integrate(&Lambertian::new(vec3::Color::fill(0.5))) - 1.0).abs() < 1e-3);
+        assert!((integrate(&metal) - 1.0).abs() < 1e-2);
+        assert!((integrate(&plastic) - 1.0).abs() < 1e-2);
+
+        // 非镜面散射的 attenuation 等于 eval / pdf
+        let mut sampler = IndependentSampler::new();
+        sampler.start_pixel_sample(0, 0, 0);
+        for _ in 0..16 {
+            let srec = metal.scatter(&r_in, &rec, &mut sampler);
+            if let Some(srec) = srec {
+                assert!(!srec.is_specular);
+                let eval = metal.eval(&r_in, &rec, &srec.scattered) / srec.pdf;
+                assert!((eval - srec.attenuation).length() < 1e-9);
+            }
+        }
+        let glass = Dielectric::new(1.5).scatter(&r_in, &rec, &mut sampler);
+        assert!(glass.unwrap().is_specular);
+        assert!(DiffuseLight::new(vec3::Color::fill(1.0))
+            .scatter(&r_in, &rec, &mut sampler)
+            .is_none());
+    }
+}
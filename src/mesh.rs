@@ -0,0 +1,347 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{DefaultMaterial, Lambertian, Material};
+use crate::ray::Ray;
+use crate::vec3;
+use std::sync::Arc;
+
+// 叶子节点最多包含的三角形数
+const MAX_LEAF_TRIANGLES: usize = 4;
+// SAH 划分时每个轴上的桶数
+const SAH_BINS: usize = 12;
+// 遍历一个节点相对于与一个三角形求交的代价
+const TRAVERSAL_COST: f64 = 1.0;
+
+// 紧凑的 BVH 节点（32 字节）。内部节点的左子节点紧随其后，offset 为右子节点的下标，
+// count 为 0；叶子节点的三角形为 indices[offset..offset + count]
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    min: [f32; 3],
+    max: [f32; 3],
+    offset: u32,
+    count: u32,
+}
+
+impl BvhNode {
+    fn bbox(&self) -> Aabb {
+        Aabb::new(self.min.into(), self.max.into())
+    }
+}
+
+// 共享顶点的三角形网格，以单精度坐标与 32 位下标存放以节省内存，
+// normals 为空时使用几何法向量，uvs 为空时以重心坐标作为表面坐标。
+// colors 为线性的顶点颜色，不为空时交点的材质为以插值的颜色为反照率的漫反射，不再使用 mat_ptr
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<[u32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub colors: Vec<[f32; 3]>,
+    pub mat_ptr: Arc<dyn Material>,
+    nodes: Vec<BvhNode>,
+}
+
+impl TriangleMesh {
+    pub fn new(vertices: Vec<[f32; 3]>, normals: Vec<[f32; 3]>, indices: Vec<[u32; 3]>) -> Self {
+        assert!(vertices.len() <= u32::MAX as usize);
+        let mut mesh = TriangleMesh {
+            vertices,
+            normals,
+            indices,
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            mat_ptr: Arc::new(DefaultMaterial::new()),
+            nodes: Vec::new(),
+        };
+        mesh.build();
+        mesh
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), BvhNode::bbox)
+    }
+
+    fn vertex(&self, i: u32) -> vec3::Point3 {
+        self.vertices[i as usize].into()
+    }
+
+    fn triangle_bbox(&self, triangle: u32) -> Aabb {
+        self.indices[triangle as usize]
+            .iter()
+            .fold(Aabb::empty(), |bbox, &i| bbox.include(self.vertex(i)))
+    }
+
+    // 按表面积启发式（SAH）分桶划分，重排 indices 使每个叶子的三角形连续存放
+    fn build(&mut self) {
+        self.nodes.clear();
+        if self.indices.is_empty() {
+            return;
+        }
+        let centroids: Vec<[f32; 3]> = (0..self.indices.len() as u32)
+            .map(|i| self.triangle_bbox(i).centroid().to_f32_array())
+            .collect();
+        let mut order: Vec<u32> = (0..self.indices.len() as u32).collect();
+
+        // 深度优先地逐个生成节点，右子节点生成时将下标写回父节点
+        let mut tasks = vec![(0, order.len(), None)];
+        while let Some((start, end, parent)) = tasks.pop() {
+            let node = self.nodes.len();
+            if let Some(parent) = parent {
+                let parent: &mut BvhNode = &mut self.nodes[parent];
+                parent.offset = node as u32;
+            }
+            let triangles = &mut order[start..end];
+            let bbox = triangles
+                .iter()
+                .fold(Aabb::empty(), |bbox, &t| bbox.union(&self.triangle_bbox(t)));
+            self.nodes.push(BvhNode {
+                min: bbox.min.to_f32_array(),
+                max: bbox.max.to_f32_array(),
+                offset: start as u32,
+                count: triangles.len() as u32,
+            });
+            if let Some(mid) = self.split(&centroids, triangles, &bbox) {
+                self.nodes[node].count = 0;
+                tasks.push((start + mid, end, Some(node)));
+                tasks.push((start, start + mid, None));
+            }
+        }
+        self.indices = order.iter().map(|&t| self.indices[t as usize]).collect();
+    }
+
+    // 在三个轴上按质心分桶，选择代价最小的划分并重排 triangles，返回左侧的三角形数，
+    // 不值得划分时返回 None
+    fn split(&self, centroids: &[[f32; 3]], triangles: &mut [u32], bbox: &Aabb) -> Option<usize> {
+        let n = triangles.len();
+        if n <= 1 {
+            return None;
+        }
+        let bounds = triangles.iter().fold(Aabb::empty(), |b, &t| {
+            b.include(centroids[t as usize].into())
+        });
+        let extent = bounds.max - bounds.min;
+        let bin = |t: u32, axis: usize| {
+            let offset = centroids[t as usize][axis] as f64 - bounds.min[axis];
+            ((offset / extent[axis] * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+        };
+
+        // (代价, 轴, 左侧的桶数)
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut bins = [(Aabb::empty(), 0); SAH_BINS];
+            for &t in triangles.iter() {
+                let b = &mut bins[bin(t, axis)];
+                *b = (b.0.union(&self.triangle_bbox(t)), b.1 + 1);
+            }
+            let mut right = [(0.0, 0); SAH_BINS];
+            let mut accumulated = (Aabb::empty(), 0);
+            for i in (1..SAH_BINS).rev() {
+                accumulated = (accumulated.0.union(&bins[i].0), accumulated.1 + bins[i].1);
+                right[i] = (accumulated.0.surface_area(), accumulated.1);
+            }
+            let mut left = (Aabb::empty(), 0);
+            for i in 1..SAH_BINS {
+                left = (left.0.union(&bins[i - 1].0), left.1 + bins[i - 1].1);
+                if left.1 == 0 || right[i].1 == 0 {
+                    continue;
+                }
+                let cost = left.0.surface_area() * left.1 as f64 + right[i].0 * right[i].1 as f64;
+                if best.map_or(true, |(c, _, _)| cost < c) {
+                    best = Some((cost, axis, i));
+                }
+            }
+        }
+
+        match best {
+            Some((cost, axis, boundary)) => {
+                let area = bbox.surface_area();
+                let cost = TRAVERSAL_COST + if area > 0.0 { cost / area } else { 0.0 };
+                if n <= MAX_LEAF_TRIANGLES && cost >= n as f64 {
+                    return None;
+                }
+                Some(partition(triangles, |&t| bin(t, axis) < boundary))
+            }
+            // 质心重合时无法按位置划分，改为对半分
+            None if n > MAX_LEAF_TRIANGLES => Some(n / 2),
+            None => None,
+        }
+    }
+
+    // Möller–Trumbore 算法，返回 (t, u, v)
+    fn hit_triangle(
+        &self,
+        triangle: usize,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let [a, b, c] = self.indices[triangle];
+        let (p0, p1, p2) = (self.vertex(a), self.vertex(b), self.vertex(c));
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = r.direction.cross(e2);
+        let det = e1.dot(pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = r.origin - p0;
+        let u = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let qvec = tvec.cross(e1);
+        let v = r.direction.dot(qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(qvec) * inv_det;
+        if !(t_min..=t_max).contains(&t) {
+            return None;
+        }
+        Some((t, u, v))
+    }
+}
+
+// 将满足 pred 的元素移到前面，返回其个数
+fn partition<T, F: Fn(&T) -> bool>(items: &mut [T], pred: F) -> usize {
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inv_direction = vec3::Vec3(
+            1.0 / r.direction.x(),
+            1.0 / r.direction.y(),
+            1.0 / r.direction.z(),
+        );
+        let mut closest = None;
+        let mut t_max = t_max;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            if !node.bbox().hit(r, inv_direction, t_min, t_max) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for triangle in start..start + node.count as usize {
+                    if let Some((t, u, v)) = self.hit_triangle(triangle, r, t_min, t_max) {
+                        t_max = t;
+                        closest = Some((triangle, u, v));
+                    }
+                }
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(i + 1);
+            }
+        }
+
+        let (triangle, u, v) = match closest {
+            Some(hit) => hit,
+            None => return false,
+        };
+        let [a, b, c] = self.indices[triangle];
+        let outward_normal = if self.normals.is_empty() {
+            (self.vertex(b) - self.vertex(a))
+                .cross(self.vertex(c) - self.vertex(a))
+                .unit_vector()
+        } else {
+            let normal = |i: u32| vec3::Vec3::from(self.normals[i as usize]);
+            ((1.0 - u - v) * normal(a) + u * normal(b) + v * normal(c)).unit_vector()
+        };
+        rec.t = t_max;
+        rec.p = r.at(t_max);
+        rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = if self.uvs.is_empty() {
+            (u, v)
+        } else {
+            let uv = |i: u32, k: usize| self.uvs[i as usize][k] as f64;
+            (
+                (1.0 - u - v) * uv(a, 0) + u * uv(b, 0) + v * uv(c, 0),
+                (1.0 - u - v) * uv(a, 1) + u * uv(b, 1) + v * uv(c, 1),
+            )
+        };
+        rec.mat_ptr = if self.colors.is_empty() {
+            self.mat_ptr.clone()
+        } else {
+            let color = |i: u32| vec3::Color::from(self.colors[i as usize]);
+            Arc::new(Lambertian::new(
+                (1.0 - u - v) * color(a) + u * color(b) + v * color(c),
+            ))
+        };
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    #[test]
+    fn test_mesh_hit() {
+        // 由 z = -1 平面上的 8×8 个正方形组成的网格
+        let n = 8u32;
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                vertices.push([i as f32 / n as f32 - 0.5, j as f32 / n as f32 - 0.5, -1.0]);
+            }
+        }
+        for j in 0..n {
+            for i in 0..n {
+                let k = j * (n + 1) + i;
+                indices.push([k, k + 1, k + n + 2]);
+                indices.push([k, k + n + 2, k + n + 1]);
+            }
+        }
+        let mesh = TriangleMesh::new(vertices, Vec::new(), indices);
+        assert_eq!(mesh.indices.len(), 2 * n as usize * n as usize);
+        let bbox = mesh.bounding_box();
+        assert_eq!(bbox.min.to_array(), [-0.5, -0.5, -1.0]);
+        assert_eq!(bbox.max.to_array(), [0.5, 0.5, -1.0]);
+
+        let mut rec = HitRecord::new();
+        let r = Ray::new(vec3::Vec3(0.1, 0.2, 0.0), vec3::Vec3(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&r, 0.0, f64::INFINITY, &mut rec));
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.normal.z() - 1.0).abs() < 1e-12);
+
+        assert!(!mesh.hit(&r, 0.0, 0.5, &mut rec));
+        let r = Ray::new(vec3::Vec3(0.6, 0.0, 0.0), vec3::Vec3(0.0, 0.0, -1.0));
+        assert!(!mesh.hit(&r, 0.0, f64::INFINITY, &mut rec));
+
+        // 顶点颜色随位置线性变化，插值得到的反照率与交点的位置一致
+        let mut mesh = mesh;
+        mesh.colors = mesh
+            .vertices
+            .iter()
+            .map(|p| [p[0] + 0.5, p[1] + 0.5, 0.0])
+            .collect();
+        let r = Ray::new(vec3::Vec3(0.1, 0.2, 0.0), vec3::Vec3(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&r, 0.0, f64::INFINITY, &mut rec));
+        let mut sampler = IndependentSampler::new();
+        let srec = rec.mat_ptr.scatter(&r, &rec, &mut sampler).unwrap();
+        assert!((srec.attenuation - vec3::Vec3(0.6, 0.7, 0.0)).length() < 1e-6);
+    }
+}
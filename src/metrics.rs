@@ -0,0 +1,355 @@
+use std::f64::consts::PI;
+
+use crate::film::{self, Film};
+use crate::utils;
+use crate::vec3;
+
+// 图像差异的度量，都在显示值(gamma 2 校正并截断到 [0, 1]，与写出的图像一致)上计算
+
+fn display(film: &Film) -> Vec<vec3::Color> {
+    let mut pixels = Vec::with_capacity(film.width * film.height);
+    for row in 0..film.height {
+        for col in 0..film.width {
+            let c = film.pixel(col, row);
+            pixels.push(vec3::Vec3(
+                utils::clamp(c.r().max(0.0).sqrt(), 0.0, 1.0),
+                utils::clamp(c.g().max(0.0).sqrt(), 0.0, 1.0),
+                utils::clamp(c.b().max(0.0).sqrt(), 0.0, 1.0),
+            ));
+        }
+    }
+    pixels
+}
+
+fn check_size(reference: &Film, test: &Film) {
+    assert!(
+        reference.width == test.width && reference.height == test.height,
+        "cannot compare a {}x{} image with a {}x{} image",
+        reference.width,
+        reference.height,
+        test.width,
+        test.height
+    );
+}
+
+pub fn rmse(reference: &Film, test: &Film) -> f64 {
+    check_size(reference, test);
+    let (a, b) = (display(reference), display(test));
+    let total: f64 = a
+        .iter()
+        .zip(&b)
+        .map(|(a, b)| (*a - *b).length_squared())
+        .sum();
+    (total / (3 * a.len()) as f64).sqrt()
+}
+
+// 峰值为 1 的峰值信噪比，单位 dB，两幅图像相同时为无穷大
+pub fn psnr(reference: &Film, test: &Film) -> f64 {
+    -20.0 * rmse(reference, test).log10()
+}
+
+// FLIP (Andersson et al. 2020)
+
+// 观察条件：0.7 米外宽 0.7 米、水平 3840 像素的显示器，每度视角约 67 像素
+const PIXELS_PER_DEGREE: f64 = 0.7 * (3840.0 / 0.7) * PI / 180.0;
+
+const WHITE: [f64; 3] = [0.950_470, 1.0, 1.088_830];
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_rgb_to_xyz(c: [f64; 3]) -> [f64; 3] {
+    [
+        0.412_456_4 * c[0] + 0.357_576_1 * c[1] + 0.180_437_5 * c[2],
+        0.212_672_9 * c[0] + 0.715_152_2 * c[1] + 0.072_175_0 * c[2],
+        0.019_333_9 * c[0] + 0.119_192_0 * c[1] + 0.950_304_1 * c[2],
+    ]
+}
+
+fn xyz_to_linear_rgb(c: [f64; 3]) -> [f64; 3] {
+    [
+        3.240_454_2 * c[0] - 1.537_138_5 * c[1] - 0.498_531_4 * c[2],
+        -0.969_266_0 * c[0] + 1.876_010_8 * c[1] + 0.041_556_0 * c[2],
+        0.055_643_4 * c[0] - 0.204_025_9 * c[1] + 1.057_225_2 * c[2],
+    ]
+}
+
+// 线性化的 CIELab，滤波在这个对立色空间中进行
+fn xyz_to_ycxcz(c: [f64; 3]) -> [f64; 3] {
+    let (x, y, z) = (c[0] / WHITE[0], c[1] / WHITE[1], c[2] / WHITE[2]);
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+fn ycxcz_to_xyz(c: [f64; 3]) -> [f64; 3] {
+    let y = (c[0] + 16.0) / 116.0;
+    [
+        (c[1] / 500.0 + y) * WHITE[0],
+        y * WHITE[1],
+        (y - c[2] / 200.0) * WHITE[2],
+    ]
+}
+
+fn xyz_to_lab(c: [f64; 3]) -> [f64; 3] {
+    let delta: f64 = 6.0 / 29.0;
+    let f = |t: f64| {
+        if t > delta.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3.0 * delta * delta) + 4.0 / 29.0
+        }
+    };
+    let (x, y, z) = (f(c[0] / WHITE[0]), f(c[1] / WHITE[1]), f(c[2] / WHITE[2]));
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+// Hunt 效应：暗处的颜色差异不明显
+fn hunt(lab: [f64; 3]) -> [f64; 3] {
+    [lab[0], 0.01 * lab[0] * lab[1], 0.01 * lab[0] * lab[2]]
+}
+
+fn hyab(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (a[0] - b[0]).abs() + ((a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+// 单通道图像，row-major
+struct Channel<'a> {
+    width: usize,
+    height: usize,
+    data: &'a [f64],
+}
+
+impl Channel<'_> {
+    // 边界外的像素取最近的边界像素
+    fn convolve(&self, kernel: &[f64], radius: usize) -> Vec<f64> {
+        let size = 2 * radius + 1;
+        let mut out = vec![0.0; self.data.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = 0.0;
+                for ky in 0..size {
+                    let sy = (y + ky).saturating_sub(radius).min(self.height - 1);
+                    for kx in 0..size {
+                        let sx = (x + kx).saturating_sub(radius).min(self.width - 1);
+                        sum += kernel[ky * size + kx] * self.data[sy * self.width + sx];
+                    }
+                }
+                out[y * self.width + x] = sum;
+            }
+        }
+        out
+    }
+}
+
+// 对比敏感度函数在空间域的近似：两个高斯函数之和，归一化为总和 1
+fn csf_kernel(a1: f64, b1: f64, a2: f64, b2: f64) -> (Vec<f64>, usize) {
+    let radius = (3.0 * (b1.max(b2) / (2.0 * PI * PI)).sqrt() * PIXELS_PER_DEGREE).ceil() as usize;
+    let size = 2 * radius + 1;
+    let mut kernel = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let dx = (x as f64 - radius as f64) / PIXELS_PER_DEGREE;
+            let dy = (y as f64 - radius as f64) / PIXELS_PER_DEGREE;
+            let d2 = dx * dx + dy * dy;
+            kernel.push(
+                a1 * (PI / b1).sqrt() * (-PI * PI * d2 / b1).exp()
+                    + a2 * (PI / b2).sqrt() * (-PI * PI * d2 / b2).exp(),
+            );
+        }
+    }
+    let total: f64 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= total);
+    (kernel, radius)
+}
+
+// 边缘(一阶导数)和点(二阶导数)检测用的高斯导数核，正负部分分别归一化
+fn feature_kernels() -> (Vec<f64>, Vec<f64>, usize) {
+    let sigma = 0.5 * 0.082 * PIXELS_PER_DEGREE;
+    let radius = (3.0 * sigma).ceil() as usize;
+    let size = 2 * radius + 1;
+    let mut edge = Vec::with_capacity(size * size);
+    let mut point = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f64 - radius as f64;
+            let dy = y as f64 - radius as f64;
+            let g = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+            edge.push(-dx * g);
+            point.push((dx * dx / (sigma * sigma) - 1.0) * g);
+        }
+    }
+    for kernel in [&mut edge, &mut point] {
+        let positive: f64 = kernel.iter().filter(|k| **k > 0.0).sum();
+        let negative: f64 = -kernel.iter().filter(|k| **k < 0.0).sum::<f64>();
+        for k in kernel.iter_mut() {
+            *k /= if *k > 0.0 { positive } else { negative };
+        }
+    }
+    (edge, point, radius)
+}
+
+fn transpose(kernel: &[f64], radius: usize) -> Vec<f64> {
+    let size = 2 * radius + 1;
+    (0..size * size)
+        .map(|i| kernel[(i % size) * size + i / size])
+        .collect()
+}
+
+// 每个像素的 FLIP 误差，取值 [0, 1]，0 表示看不出差别
+pub fn flip_map(reference: &Film, test: &Film) -> Vec<f64> {
+    check_size(reference, test);
+    let (width, height) = (reference.width, reference.height);
+
+    // 转换到 YCxCz 并按各通道的对比敏感度滤波
+    let to_ycxcz = |film: &Film| -> [Vec<f64>; 3] {
+        let mut channels = [Vec::new(), Vec::new(), Vec::new()];
+        for c in display(film) {
+            let linear = [
+                srgb_to_linear(c.r()),
+                srgb_to_linear(c.g()),
+                srgb_to_linear(c.b()),
+            ];
+            let ycxcz = xyz_to_ycxcz(linear_rgb_to_xyz(linear));
+            for (channel, value) in channels.iter_mut().zip(ycxcz.iter()) {
+                channel.push(*value);
+            }
+        }
+        channels
+    };
+    let csf = [
+        csf_kernel(1.0, 0.0047, 0.0, 1e-5),
+        csf_kernel(1.0, 0.0053, 0.0, 1e-5),
+        csf_kernel(34.1, 0.04, 13.5, 0.025),
+    ];
+    let filtered_lab = |channels: &[Vec<f64>; 3]| -> Vec<[f64; 3]> {
+        let filtered: Vec<Vec<f64>> = channels
+            .iter()
+            .zip(csf.iter())
+            .map(|(data, (kernel, radius))| {
+                Channel {
+                    width,
+                    height,
+                    data,
+                }
+                .convolve(kernel, *radius)
+            })
+            .collect();
+        (0..width * height)
+            .map(|i| {
+                let rgb = xyz_to_linear_rgb(ycxcz_to_xyz([
+                    filtered[0][i],
+                    filtered[1][i],
+                    filtered[2][i],
+                ]));
+                let rgb = rgb.map(|c| utils::clamp(c, 0.0, 1.0));
+                hunt(xyz_to_lab(linear_rgb_to_xyz(rgb)))
+            })
+            .collect()
+    };
+
+    // 边缘和点特征，在归一化的亮度上检测
+    let (edge, point, radius) = feature_kernels();
+    let (edge_y, point_y) = (transpose(&edge, radius), transpose(&point, radius));
+    let features = |channels: &[Vec<f64>; 3]| -> (Vec<f64>, Vec<f64>) {
+        let luminance: Vec<f64> = channels[0].iter().map(|y| (y + 16.0) / 116.0).collect();
+        let channel = Channel {
+            width,
+            height,
+            data: &luminance,
+        };
+        let magnitude = |a: Vec<f64>, b: Vec<f64>| -> Vec<f64> {
+            a.iter().zip(&b).map(|(a, b)| a.hypot(*b)).collect()
+        };
+        (
+            magnitude(
+                channel.convolve(&edge, radius),
+                channel.convolve(&edge_y, radius),
+            ),
+            magnitude(
+                channel.convolve(&point, radius),
+                channel.convolve(&point_y, radius),
+            ),
+        )
+    };
+
+    let (reference, test) = (to_ycxcz(reference), to_ycxcz(test));
+    let (lab_reference, lab_test) = (filtered_lab(&reference), filtered_lab(&test));
+    let (edges_reference, points_reference) = features(&reference);
+    let (edges_test, points_test) = features(&test);
+
+    // 颜色差异按最大可能的差异(绿与蓝之间)压缩到 [0, 1]
+    let (qc, pc, pt) = (0.7, 0.4, 0.95);
+    let green = hunt(xyz_to_lab(linear_rgb_to_xyz([0.0, 1.0, 0.0])));
+    let blue = hunt(xyz_to_lab(linear_rgb_to_xyz([0.0, 0.0, 1.0])));
+    let cmax = hyab(green, blue).powf(qc);
+
+    (0..width * height)
+        .map(|i| {
+            let color = hyab(lab_reference[i], lab_test[i]).powf(qc);
+            let color = if color < pc * cmax {
+                pt / (pc * cmax) * color
+            } else {
+                pt + (color - pc * cmax) / (cmax - pc * cmax) * (1.0 - pt)
+            };
+            let feature = ((edges_reference[i] - edges_test[i]).abs())
+                .max((points_reference[i] - points_test[i]).abs())
+                / 2f64.sqrt();
+            utils::clamp(color.powf(1.0 - feature.sqrt()), 0.0, 1.0)
+        })
+        .collect()
+}
+
+// 平均 FLIP 误差
+pub fn flip(reference: &Film, test: &Film) -> f64 {
+    let errors = flip_map(reference, test);
+    errors.iter().sum::<f64>() / errors.len() as f64
+}
+
+// 将逐像素误差画成热力图，用于查看差异出现的位置
+pub fn error_image(width: usize, height: usize, errors: &[f64]) -> Film {
+    let pixels: Vec<vec3::Color> = errors.iter().map(|e| film::heat_color(*e)).collect();
+    Film::from_pixels(width, height, &pixels)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn gradient(width: usize, height: usize, offset: f64) -> Film {
+        let mut pixels = Vec::new();
+        for row in 0..height {
+            for col in 0..width {
+                let t = (col + row) as f64 / (width + height) as f64;
+                pixels.push(vec3::Vec3(t, 0.5 * t + offset, 0.25));
+            }
+        }
+        Film::from_pixels(width, height, &pixels)
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let a = gradient(12, 8, 0.0);
+        assert_eq!(rmse(&a, &a), 0.0);
+        assert_eq!(psnr(&a, &a), f64::INFINITY);
+        assert_eq!(flip(&a, &a), 0.0);
+    }
+
+    #[test]
+    fn test_errors_grow_with_difference() {
+        let a = gradient(12, 8, 0.0);
+        let (b, c) = (gradient(12, 8, 0.05), gradient(12, 8, 0.2));
+        assert!(rmse(&a, &b) < rmse(&a, &c));
+        assert!(psnr(&a, &b) > psnr(&a, &c));
+        assert!(0.0 < flip(&a, &b) && flip(&a, &b) < flip(&a, &c));
+
+        let black = Film::from_pixels(1, 1, &[vec3::Color::fill(0.0)]);
+        let white = Film::from_pixels(1, 1, &[vec3::Color::fill(1.0)]);
+        assert_eq!(rmse(&black, &white), 1.0);
+        assert!(flip(&black, &white) > 0.9);
+    }
+}
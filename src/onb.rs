@@ -0,0 +1,28 @@
+use crate::vec3;
+
+// 以 w 为 z 轴的正交基，用于将局部坐标系中采样的方向转换到世界坐标系
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: vec3::Vec3,
+    pub v: vec3::Vec3,
+    pub w: vec3::Vec3,
+}
+
+impl Onb {
+    pub fn new(w: vec3::Vec3) -> Self {
+        let w = w.unit_vector();
+        // 选择与 w 不平行的辅助向量
+        let a = if w.x().abs() > 0.9 {
+            vec3::Vec3(0.0, 1.0, 0.0)
+        } else {
+            vec3::Vec3(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).unit_vector();
+        let u = v.cross(w);
+        Onb { u, v, w }
+    }
+
+    pub fn local(&self, a: vec3::Vec3) -> vec3::Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
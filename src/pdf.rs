@@ -0,0 +1,183 @@
+use std::f64::consts::PI;
+
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::{self, Sampler};
+use crate::vec3;
+
+// 方向的概率分布：value 为方向 direction 的概率密度（对立体角），generate 按该分布采样方向
+pub trait Pdf {
+    fn value(&self, direction: vec3::Vec3) -> f64;
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3;
+}
+
+// 单位球面上的均匀分布
+#[derive(Debug, Clone, Copy)]
+pub struct SpherePdf {}
+
+impl SpherePdf {
+    pub fn new() -> Self {
+        SpherePdf {}
+    }
+}
+
+impl Default for SpherePdf {
+    fn default() -> Self {
+        SpherePdf::new()
+    }
+}
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: vec3::Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        sampler::unit_vector(sampler.get_2d())
+    }
+}
+
+// 以 w 为中心的余弦分布
+#[derive(Debug, Clone, Copy)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: vec3::Vec3) -> Self {
+        CosinePdf { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: vec3::Vec3) -> f64 {
+        direction.unit_vector().dot(self.uvw.w).max(0.0) / PI
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        self.uvw.local(sampler::cosine_direction(sampler.get_2d()))
+    }
+}
+
+// 从 origin 出发朝向物体（通常是光源）的方向
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: vec3::Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: vec3::Point3) -> Self {
+        HittablePdf { objects, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: vec3::Vec3) -> f64 {
+        self.objects.pdf_value(self.origin, direction)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        self.objects.random(self.origin, sampler)
+    }
+}
+
+// 材质在交点处的散射分布，被吸收的样本返回表面以下的方向
+pub struct MaterialPdf<'a> {
+    material: &'a dyn Material,
+    r_in: &'a Ray,
+    rec: &'a HitRecord,
+}
+
+impl<'a> MaterialPdf<'a> {
+    pub fn new(material: &'a dyn Material, r_in: &'a Ray, rec: &'a HitRecord) -> Self {
+        MaterialPdf {
+            material,
+            r_in,
+            rec,
+        }
+    }
+}
+
+impl Pdf for MaterialPdf<'_> {
+    fn value(&self, direction: vec3::Vec3) -> f64 {
+        let scattered = Ray::new(self.rec.p, direction);
+        self.material.pdf(self.r_in, self.rec, &scattered)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        match self.material.scatter(self.r_in, self.rec, sampler) {
+            Some(srec) => srec.scattered.direction,
+            None => -self.rec.normal,
+        }
+    }
+}
+
+// 两个分布各占一半的混合
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        MixturePdf { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: vec3::Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        if sampler.get_1d() < 0.5 {
+            self.p[0].generate(sampler)
+        } else {
+            self.p[1].generate(sampler)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+
+    // 按网格在单位球面上积分概率密度
+    fn integrate(pdf: &dyn Pdf) -> f64 {
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                sum += pdf.value(sampler::unit_vector(u));
+            }
+        }
+        sum * 4.0 * PI / (n * n) as f64
+    }
+
+    #[test]
+    fn test_pdf() {
+        let uvw = Onb::new(vec3::Vec3(1.0, 2.0, -2.0));
+        assert!((uvw.w.length() - 1.0).abs() < 1e-12);
+        assert!(uvw.u.dot(uvw.v).abs() < 1e-12 && uvw.u.dot(uvw.w).abs() < 1e-12);
+        assert!((uvw.u.cross(uvw.v) - uvw.w).length() < 1e-12);
+
+        let sphere = Sphere::new(vec3::Vec3(4.0, 0.0, 0.0), 1.0);
+        let origin = vec3::Point3::fill(0.0);
+        let cosine = CosinePdf::new(vec3::Vec3(0.0, 1.0, 0.0));
+        let light = HittablePdf::new(&sphere, origin);
+        let mixture = MixturePdf::new(&cosine, &light);
+        for pdf in [&SpherePdf::new() as &dyn Pdf, &cosine, &light, &mixture] {
+            assert!((integrate(pdf) - 1.0).abs() < 1e-2);
+        }
+
+        // 朝向光源采样的方向都击中光源
+        let mut sampler = IndependentSampler::new();
+        sampler.start_pixel_sample(0, 0, 0);
+        let mut rec = HitRecord::new();
+        for _ in 0..64 {
+            let direction = light.generate(&mut sampler);
+            assert!(sphere.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec));
+            assert!(cosine.generate(&mut sampler).y() >= 0.0);
+        }
+    }
+}
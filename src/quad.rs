@@ -0,0 +1,169 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{DefaultMaterial, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::scene::{SceneWriter, ShapeDescription};
+use crate::vec3;
+use std::io;
+use std::sync::Arc;
+
+// 以 q 为一角、两边为 u 和 v 的平行四边形，外法向量为 u × v 的方向，
+// 表面坐标 (u, v) 为交点沿两边的比例
+#[derive(Debug, Clone)]
+pub struct Quad {
+    q: vec3::Point3,
+    u: vec3::Vec3,
+    v: vec3::Vec3,
+    pub mat_ptr: Arc<dyn Material>,
+    normal: vec3::Vec3,
+    d: f64,
+    w: vec3::Vec3,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: vec3::Point3, u: vec3::Vec3, v: vec3::Vec3) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+        Quad {
+            q,
+            u,
+            v,
+            mat_ptr: Arc::new(DefaultMaterial::new()),
+            normal,
+            d: normal.dot(q),
+            w: n / n.length_squared(),
+            area: n.length(),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::empty()
+            .include(self.q)
+            .include(self.q + self.u)
+            .include(self.q + self.v)
+            .include(self.q + self.u + self.v)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let denom = self.normal.dot(r.direction);
+        // 与平面平行
+        if denom.abs() < 1e-12 {
+            return false;
+        }
+        let t = (self.d - self.normal.dot(r.origin)) / denom;
+        if !(t_min..=t_max).contains(&t) {
+            return false;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.set_face_normal(r, self.normal);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat_ptr = self.mat_ptr.clone();
+
+        true
+    }
+
+    // 在面积上均匀采样，换算为立体角上的概率密度
+    fn pdf_value(&self, origin: vec3::Point3, direction: vec3::Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec) {
+            return 0.0;
+        }
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(self.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: vec3::Point3, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        let (s, t) = sampler.get_2d();
+        self.q + s * self.u + t * self.v - origin
+    }
+
+    fn random_point(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord, f64)> {
+        let (s, t) = sampler.get_2d();
+        let mut rec = HitRecord::new();
+        rec.p = self.q + s * self.u + t * self.v;
+        rec.normal = self.normal;
+        rec.front_face = true;
+        rec.u = s;
+        rec.v = t;
+        rec.mat_ptr = self.mat_ptr.clone();
+        Some((rec, 1.0 / self.area))
+    }
+
+    fn pdf_point(&self, p: vec3::Point3) -> f64 {
+        let planar = p - self.q;
+        if (self.normal.dot(planar)).abs() > 1e-6 * self.area.sqrt() {
+            return 0.0;
+        }
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return 0.0;
+        }
+        1.0 / self.area
+    }
+
+    fn describe(&self, scene: &mut SceneWriter) -> io::Result<()> {
+        let shape = ShapeDescription::Quad {
+            q: self.q.to_array(),
+            u: self.u.to_array(),
+            v: self.v.to_array(),
+        };
+        scene.add(shape, &*self.mat_ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    #[test]
+    fn test_quad() {
+        // y = 2 平面上朝下的 2×1 矩形
+        let quad = Quad::new(
+            vec3::Vec3(-1.0, 2.0, 0.0),
+            vec3::Vec3(2.0, 0.0, 0.0),
+            vec3::Vec3(0.0, 0.0, 1.0),
+        );
+        let mut rec = HitRecord::new();
+        let r = Ray::new(vec3::Point3::fill(0.0), vec3::Vec3(0.5, 1.0, 0.25));
+        assert!(quad.hit(&r, 0.001, f64::INFINITY, &mut rec));
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert!((rec.u - 1.0).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        assert!(rec.front_face);
+        let r = Ray::new(vec3::Point3::fill(0.0), vec3::Vec3(0.0, 1.0, 1.5));
+        assert!(!quad.hit(&r, 0.001, f64::INFINITY, &mut rec));
+
+        // 正下方的 pdf 为 距离² / 面积
+        let origin = vec3::Vec3(0.0, 0.0, 0.5);
+        let pdf = quad.pdf_value(origin, vec3::Vec3(0.0, 1.0, 0.0));
+        assert!((pdf - 2.0).abs() < 1e-12);
+        let mut sampler = IndependentSampler::new();
+        sampler.start_pixel_sample(0, 0, 0);
+        for _ in 0..16 {
+            let direction = quad.random(origin, &mut sampler);
+            assert!(quad.pdf_value(origin, direction) > 0.0);
+            let (rec, pdf) = quad.random_point(&mut sampler).unwrap();
+            assert_eq!(pdf, 0.5);
+            assert_eq!(quad.pdf_point(rec.p), 0.5);
+        }
+        assert_eq!(quad.pdf_point(vec3::Vec3(0.0, 2.0, 1.5)), 0.0);
+    }
+}
@@ -0,0 +1,549 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::camera::RayGenerator;
+use crate::checkpoint::Checkpoint;
+use crate::film::{BoxFilter, Film, Filter, Tile};
+use crate::hittable::Hittable;
+use crate::integrator::Integrator;
+use crate::sampler::{IndependentSampler, Sampler};
+use crate::vec3;
+
+pub const DEFAULT_SEED: u64 = 0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: u64,
+}
+
+impl ImageSettings {
+    pub fn new(width: usize, aspect_ratio: f64, samples_per_pixel: usize, max_depth: u64) -> Self {
+        let height = (width as f64 / aspect_ratio) as usize;
+        ImageSettings {
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+        }
+    }
+
+    // 相机把像素映射到 [0, 1] 时除以 width - 1 与 height - 1，图像至少为 2x2
+    pub fn validate(&self) -> io::Result<()> {
+        if self.width < 2 || self.height < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "image must be at least 2x2 pixels, got {}x{}",
+                    self.width, self.height
+                ),
+            ));
+        }
+        if self.samples_per_pixel == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "image needs at least one sample per pixel",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// 渐进式渲染时写出中间结果的频率，两者都为 None 时只在结束时写出
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnapshotSettings {
+    pub every_passes: Option<usize>,
+    pub interval: Option<Duration>,
+}
+
+// 提前结束渲染的条件，samples_per_pixel 仍是样本数的上限
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StopCriteria {
+    pub time_limit: Option<Duration>,
+    pub target_error: Option<f64>,
+}
+
+// 自适应采样：每个像素先采样 min_samples 次，之后只对亮度均值的 95% 置信区间
+// 相对半宽仍大于 threshold 的像素继续采样
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSettings {
+    pub min_samples: usize,
+    pub threshold: f64,
+}
+
+impl AdaptiveSettings {
+    pub fn new(min_samples: usize, threshold: f64) -> Self {
+        AdaptiveSettings {
+            min_samples,
+            threshold,
+        }
+    }
+}
+
+// samples 为已完成的轮次给每个像素的样本数，也是之后样本序号的起点；
+// 自适应采样时部分像素提前停止，mean_samples 为胶片上每个像素实际的平均样本数
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub passes: usize,
+    pub samples: usize,
+    pub mean_samples: f64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn samples_per_pixel(&self) -> String {
+        if self.mean_samples + 0.05 < self.samples as f64 {
+            format!("{:.1} (at most {})", self.mean_samples, self.samples)
+        } else {
+            self.samples.to_string()
+        }
+    }
+}
+
+pub struct Renderer {
+    pub world: Arc<dyn Hittable>,
+    pub camera: Arc<dyn RayGenerator>,
+    pub image: ImageSettings,
+    pub integrator: Arc<dyn Integrator>,
+    pub filter: Arc<dyn Filter>,
+    pub sampler: Box<dyn Sampler>,
+    pub seed: u64,
+    pub threads: usize,
+    pub tile_size: usize,
+    pub samples_per_pass: usize,
+    pub snapshots: SnapshotSettings,
+    pub stop: StopCriteria,
+    pub adaptive: Option<AdaptiveSettings>,
+    pub output: String,
+    pub heatmap: Option<String>,
+    pub checkpoint: Option<String>,
+    pub resume: bool,
+}
+
+impl Renderer {
+    pub fn new(
+        world: Arc<dyn Hittable>,
+        camera: Arc<dyn RayGenerator>,
+        image: ImageSettings,
+        integrator: Arc<dyn Integrator>,
+        output: &str,
+    ) -> Self {
+        let threads = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Renderer {
+            world,
+            camera,
+            image,
+            integrator,
+            filter: Arc::new(BoxFilter::new(0.5)),
+            sampler: Box::new(IndependentSampler::new()),
+            seed: DEFAULT_SEED,
+            threads,
+            tile_size: 32,
+            samples_per_pass: 1,
+            snapshots: SnapshotSettings::default(),
+            stop: StopCriteria::default(),
+            adaptive: None,
+            output: String::from(output),
+            heatmap: None,
+            checkpoint: None,
+            resume: false,
+        }
+    }
+
+    // 对 tile 中的像素采样第 first_sample 起的 samples 个样本。
+    // 随机数只取决于种子、像素和样本序号，与由哪个线程渲染无关。
+    // active 为 None 时采样所有像素，否则只采样标记为 true 的像素
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        seed: u64,
+        first_sample: usize,
+        samples: usize,
+        active: Option<&[bool]>,
+    ) -> Film {
+        let image = self.image;
+        let mut film = Film::new_tile(image.width, image.height, self.filter.clone(), tile);
+        let mut sampler = self.sampler.box_clone();
+        sampler.set_seed(seed);
+        for row in (tile.row0..tile.row1).rev() {
+            for col in tile.col0..tile.col1 {
+                if active.is_some_and(|active| !active[row * image.width + col]) {
+                    continue;
+                }
+                for index in first_sample..first_sample + samples {
+                    sampler.start_pixel_sample(col, row, index);
+                    let (dx, dy) = sampler.get_2d();
+                    let x = col as f64 + dx;
+                    let y = row as f64 + dy;
+                    let u = x / (image.width - 1) as f64;
+                    let v = y / (image.height - 1) as f64;
+                    let r = self.camera.get_ray(u, v, &mut *sampler);
+                    // 视口坐标 (s, t) 对应像素坐标 (s * (width - 1), t * (height - 1))，
+                    // splat 的贡献按像素面积换算
+                    let (sx, sy) = ((image.width - 1) as f64, (image.height - 1) as f64);
+                    let mut splat = |s: f64, t: f64, color: vec3::Color| {
+                        film.add_splat(s * sx, t * sy, sx * sy * color)
+                    };
+                    let color = self.integrator.sample(
+                        &r,
+                        &*self.world,
+                        image.max_depth,
+                        &mut *sampler,
+                        index,
+                        &*self.camera,
+                        &mut splat,
+                    );
+                    film.add_sample(x, y, color);
+                }
+            }
+        }
+        film
+    }
+
+    pub fn render(&self) -> Film {
+        self.render_with(None, |_, _| {}).0
+    }
+
+    // 每一轮(pass)对所有像素各采样 samples_per_pass 次，
+    // 每完成一轮就以当前的累积结果和进度调用 on_pass。
+    // 从检查点恢复时沿用其中的种子，只补足 samples_per_pixel 中剩余的样本，
+    // 满足 stop 中的条件时在轮次结束处提前停止
+    pub fn render_with<F: FnMut(&Film, Progress)>(
+        &self,
+        start: Option<Checkpoint>,
+        mut on_pass: F,
+    ) -> (Film, Progress) {
+        let start_time = Instant::now();
+        let image = self.image;
+        let seed = start
+            .as_ref()
+            .map_or(self.seed, |checkpoint| checkpoint.seed);
+        let (mut film, start_samples) = match start {
+            Some(checkpoint) => (checkpoint.film, checkpoint.samples),
+            None => (Film::new(image.width, image.height, self.filter.clone()), 0),
+        };
+        let remaining = image.samples_per_pixel.saturating_sub(start_samples);
+        let per_pass = self.samples_per_pass.max(1);
+        let passes = remaining.div_ceil(per_pass);
+        let pass_samples = |pass: usize| per_pass.min(remaining - pass * per_pass);
+        let tiles = Tile::split(image.width, image.height, self.tile_size);
+        let jobs = passes.saturating_mul(tiles.len());
+        let next = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let (sender, receiver) = sync_channel::<(usize, Film)>(self.threads);
+        // 已合并的任务数。线程只开始与其相距不到一轮的任务，
+        // 已完成但未合并的 tile 因此少于一轮的 tile 数
+        let window = Mutex::new(0);
+        let window_ready = Condvar::new();
+        // 自适应采样时，一轮要采样的像素取决于之前所有轮次的结果，
+        // 线程需等待上一轮合并完成、(可采样的轮次, 像素掩码) 更新之后再开始
+        let active = Mutex::new((0, self.active_pixels(&film, start_samples)));
+        let active_ready = Condvar::new();
+        // 每一轮的第一个任务开始之前由积分器准备这一轮，同一轮的其他任务等待准备完成
+        let started: Vec<OnceLock<()>> = (0..passes).map(|_| OnceLock::new()).collect();
+        let pass_range = |pass: usize| {
+            let first = start_samples + pass * per_pass;
+            first..first + pass_samples(pass)
+        };
+
+        let mut progress = Progress {
+            passes: 0,
+            samples: start_samples,
+            mean_samples: film.mean_samples(),
+            elapsed: Duration::from_secs(0),
+        };
+        thread::scope(|scope| {
+            // 空闲的线程从共享队列中领取下一个 tile，按轮次先后排列
+            for _ in 0..self.threads {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next = &next;
+                let stopped = &stopped;
+                let (active, active_ready) = (&active, &active_ready);
+                let (window, window_ready) = (&window, &window_ready);
+                let (started, pass_range) = (&started, &pass_range);
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= jobs || stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    {
+                        let mut merged = window.lock().unwrap();
+                        while i >= *merged + tiles.len() && !stopped.load(Ordering::Relaxed) {
+                            merged = window_ready.wait(merged).unwrap();
+                        }
+                    }
+                    let pass = i / tiles.len();
+                    let mask = if self.adaptive.is_some() {
+                        let mut state = active.lock().unwrap();
+                        while state.0 < pass && !stopped.load(Ordering::Relaxed) {
+                            state = active_ready.wait(state).unwrap();
+                        }
+                        state.1.clone()
+                    } else {
+                        None
+                    };
+                    if stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    started[pass].get_or_init(|| {
+                        self.integrator.start_pass(
+                            &*self.world,
+                            image.max_depth,
+                            seed,
+                            pass_range(pass),
+                        )
+                    });
+                    let tile = self.render_tile(
+                        &tiles[i % tiles.len()],
+                        seed,
+                        start_samples + pass * per_pass,
+                        pass_samples(pass),
+                        mask.as_deref().map(|mask| mask.as_slice()),
+                    );
+                    sender.send((i, tile)).expect("Ray tracing failed!");
+                });
+            }
+            drop(sender);
+
+            // 提前完成的 tile 先暂存，按任务顺序合并：浮点累加的顺序与线程调度无关，
+            // 回调时胶片也恰好包含已完成的轮次
+            let mut pending = BTreeMap::new();
+            let mut merged = 0;
+            for (i, tile) in receiver.iter() {
+                if stopped.load(Ordering::Relaxed) {
+                    continue;
+                }
+                pending.insert(i, tile);
+                while let Some(tile) = pending.remove(&merged) {
+                    film.merge(&tile);
+                    merged += 1;
+                    *window.lock().unwrap() = merged;
+                    window_ready.notify_all();
+                    if merged % tiles.len() == 0 {
+                        self.integrator.end_pass(pass_range(progress.passes));
+                        progress.samples += pass_samples(progress.passes);
+                        progress.passes += 1;
+                        progress.mean_samples = film.mean_samples();
+                        progress.elapsed = start_time.elapsed();
+                        eprint!("\rSamples per pixel: {} ", progress.samples_per_pixel());
+                        on_pass(&film, progress);
+
+                        let mask = self.active_pixels(&film, progress.samples);
+                        let converged = mask.as_ref().is_some_and(|mask| !mask.contains(&true));
+                        if converged || self.should_stop(&film, progress) {
+                            stopped.store(true, Ordering::Relaxed);
+                        }
+                        *active.lock().unwrap() = (progress.passes, mask);
+                        active_ready.notify_all();
+                        // 先获取一次锁再通知，已检查过 stopped 但尚未等待的线程不会错过通知
+                        drop(window.lock().unwrap());
+                        window_ready.notify_all();
+                        if stopped.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        (film, progress)
+    }
+
+    // 仍需采样的像素，未开启自适应采样或样本数不足 min_samples 时为 None
+    fn active_pixels(&self, film: &Film, samples: usize) -> Option<Arc<Vec<bool>>> {
+        let adaptive = self.adaptive?;
+        if samples < adaptive.min_samples {
+            return None;
+        }
+        let mut active = Vec::with_capacity(film.width * film.height);
+        for row in 0..film.height {
+            for col in 0..film.width {
+                let half_width = 1.96 * film.variance(col, row).relative_error();
+                active.push(half_width > adaptive.threshold);
+            }
+        }
+        Some(Arc::new(active))
+    }
+
+    fn should_stop(&self, film: &Film, progress: Progress) -> bool {
+        let out_of_time = self
+            .stop
+            .time_limit
+            .is_some_and(|limit| progress.elapsed >= limit);
+        let converged = self
+            .stop
+            .target_error
+            .is_some_and(|target| film.relative_error() <= target);
+        out_of_time || converged
+    }
+
+    pub fn run(&self) -> io::Result<()> {
+        let start = match &self.checkpoint {
+            Some(path) if self.resume => {
+                let checkpoint = Checkpoint::load(
+                    path,
+                    self.filter.clone(),
+                    self.image.width,
+                    self.image.height,
+                )
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+                // 样本序号接着检查点继续，采样器须相同；分层采样的分层方式还取决于每像素样本数
+                if let Some((name, samples_per_pixel)) = &checkpoint.sampler {
+                    let sampler = self.sampler.name();
+                    let restratified =
+                        name == "stratified" && *samples_per_pixel != self.image.samples_per_pixel;
+                    if name != sampler || restratified {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "{}: checkpoint uses the {} sampler with {} samples per pixel, \
+                                 cannot resume with the {} sampler with {}",
+                                path,
+                                name,
+                                samples_per_pixel,
+                                sampler,
+                                self.image.samples_per_pixel
+                            ),
+                        ));
+                    }
+                }
+                eprintln!(
+                    "Resuming from {} samples per pixel with seed {}",
+                    checkpoint.samples, checkpoint.seed
+                );
+                Some(checkpoint)
+            }
+            _ => None,
+        };
+        let seed = start
+            .as_ref()
+            .map_or(self.seed, |checkpoint| checkpoint.seed);
+        let mut last_snapshot = Instant::now();
+        let (film, progress) = self.render_with(start, |film, progress| {
+            let by_passes = self
+                .snapshots
+                .every_passes
+                .is_some_and(|n| progress.passes % n == 0);
+            let by_time = self
+                .snapshots
+                .interval
+                .is_some_and(|interval| last_snapshot.elapsed() >= interval);
+            if by_passes || by_time {
+                last_snapshot = Instant::now();
+                if let Err(e) = self.save(film, progress.samples, seed) {
+                    eprintln!("\nwarning: failed to write snapshot: {}", e);
+                }
+            }
+        });
+        self.save(&film, progress.samples, seed)?;
+        if let Some(path) = &self.heatmap {
+            film.sample_heatmap().save(path)?;
+        }
+
+        eprintln!(
+            "\nDone: {} samples per pixel in {:.1?}, estimated relative error {:.2}%",
+            progress.samples_per_pixel(),
+            progress.elapsed,
+            100.0 * film.relative_error()
+        );
+        Ok(())
+    }
+
+    // 写出图像，若设置了检查点路径则同时写出检查点
+    fn save(&self, film: &Film, samples: usize, seed: u64) -> io::Result<()> {
+        film.save(&self.output)?;
+        if let Some(path) = &self.checkpoint {
+            let mut checkpoint = Checkpoint::new(samples, seed, film.clone());
+            checkpoint.sampler = Some((
+                self.sampler.name().to_string(),
+                self.image.samples_per_pixel,
+            ));
+            checkpoint.save(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::MitchellFilter;
+    use crate::hittable_list::HittableList;
+    use crate::integrator::Recursive;
+    use crate::material::{Lambertian, Metal};
+    use crate::sampler::SobolSampler;
+    use crate::sphere::Sphere;
+
+    fn test_renderer(threads: usize, seed: u64) -> Renderer {
+        let mut world = HittableList::new();
+        let mut ground = Sphere::new(vec3::Vec3(0.0, -100.5, -1.0), 100.0);
+        ground.mat_ptr = Arc::new(Lambertian::new(vec3::Vec3(0.8, 0.8, 0.0)));
+        world.add(Arc::new(ground));
+        let mut sphere = Sphere::new(vec3::Vec3(0.0, 0.0, -1.0), 0.5);
+        sphere.mat_ptr = Arc::new(Metal::new(vec3::Vec3(0.8, 0.6, 0.2), 0.3));
+        world.add(Arc::new(sphere));
+
+        let image = ImageSettings::new(16, 16.0 / 9.0, 4, 10);
+        let mut renderer = Renderer::new(
+            Arc::new(world),
+            Arc::new(Camera::new()),
+            image,
+            Arc::new(Recursive::new()),
+            "",
+        );
+        renderer.filter = Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0));
+        renderer.threads = threads;
+        renderer.tile_size = 5;
+        renderer.seed = seed;
+        renderer
+    }
+
+    // 逐位比较像素值
+    fn pixels(film: &Film) -> Vec<[u64; 3]> {
+        let mut pixels = Vec::new();
+        for row in 0..film.height {
+            for col in 0..film.width {
+                let c = film.pixel(col, row);
+                pixels.push([c.x().to_bits(), c.y().to_bits(), c.z().to_bits()]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let single = pixels(&test_renderer(1, 7).render());
+        assert_eq!(single, pixels(&test_renderer(3, 7).render()));
+        assert_ne!(single, pixels(&test_renderer(1, 8).render()));
+
+        let mut sobol = test_renderer(1, 7);
+        sobol.sampler = Box::new(SobolSampler::new());
+        let mut sobol_threads = test_renderer(4, 7);
+        sobol_threads.sampler = Box::new(SobolSampler::new());
+        assert_eq!(pixels(&sobol.render()), pixels(&sobol_threads.render()));
+    }
+
+    #[test]
+    fn test_adaptive_progress() {
+        // 自适应采样时进度报告实际的平均样本数
+        let mut renderer = test_renderer(2, 7);
+        renderer.image.samples_per_pixel = 32;
+        renderer.samples_per_pass = 4;
+        renderer.adaptive = Some(AdaptiveSettings::new(4, 0.5));
+        let (film, progress) = renderer.render_with(None, |_, _| {});
+        assert_eq!(progress.mean_samples, film.mean_samples());
+        assert!(progress.mean_samples < progress.samples as f64);
+        assert!(progress.samples_per_pixel().contains("at most"));
+    }
+}
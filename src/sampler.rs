@@ -0,0 +1,638 @@
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+use crate::utils;
+use crate::vec3;
+
+// 为每个像素样本提供 [0, 1) 上的随机数。
+// 每个样本开始前调用 start_pixel_sample，之后按固定顺序依次取用各个维度，
+// 相机、材质等按取用顺序占用各自的维度。
+// 取到的值只由种子、像素、样本序号和维度决定，因此渲染结果可以复现
+pub trait Sampler: Send + Sync {
+    fn set_seed(&mut self, seed: u64);
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+    // 与 SAMPLERS 中的名称一致
+    fn name(&self) -> &'static str;
+    fn box_clone(&self) -> Box<dyn Sampler>;
+}
+
+pub const SAMPLERS: [&str; 5] = ["independent", "stratified", "halton", "sobol", "blue-noise"];
+
+// 按名称创建采样器，分层采样需要知道每个像素的样本数
+pub fn from_name(name: &str, samples_per_pixel: usize) -> Option<Box<dyn Sampler>> {
+    match name {
+        "independent" => Some(Box::new(IndependentSampler::new())),
+        "stratified" => Some(Box::new(StratifiedSampler::new(samples_per_pixel))),
+        "halton" => Some(Box::new(HaltonSampler::new())),
+        "sobol" => Some(Box::new(SobolSampler::new())),
+        "blue-noise" => Some(Box::new(BlueNoiseSampler::new())),
+        _ => None,
+    }
+}
+
+// 由 [0, 1)² 上的均匀样本生成方向与点
+
+// 单位球面上均匀分布的方向
+pub fn unit_vector(u: (f64, f64)) -> vec3::Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    vec3::Vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+// 单位球内均匀分布的点，半径由第三个样本 w 决定
+pub fn in_unit_sphere(u: (f64, f64), w: f64) -> vec3::Vec3 {
+    w.cbrt() * unit_vector(u)
+}
+
+// 与法向量同侧的单位球内的点
+pub fn in_hemisphere(normal: &vec3::Vec3, u: (f64, f64), w: f64) -> vec3::Vec3 {
+    let in_unit_sphere = in_unit_sphere(u, w);
+    if in_unit_sphere.dot(*normal) > 0.0 {
+        in_unit_sphere
+    } else {
+        -in_unit_sphere
+    }
+}
+
+// 以 +z 为中心按余弦分布的单位方向，概率密度为 cosθ / π
+pub fn cosine_direction(u: (f64, f64)) -> vec3::Vec3 {
+    let r = u.0.sqrt();
+    let phi = 2.0 * PI * u.1;
+    vec3::Vec3(r * phi.cos(), r * phi.sin(), (1.0 - u.0).max(0.0).sqrt())
+}
+
+// 以 +z 为轴、半角余弦为 cos_theta_max 的圆锥内均匀分布的单位方向
+pub fn cone_direction(cos_theta_max: f64, u: (f64, f64)) -> vec3::Vec3 {
+    let z = 1.0 + u.0 * (cos_theta_max - 1.0);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    vec3::Vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+// z = 0 平面上单位圆盘内均匀分布的点
+pub fn in_unit_disk(u: (f64, f64)) -> vec3::Vec3 {
+    let r = u.0.sqrt();
+    let theta = 2.0 * PI * u.1;
+    vec3::Vec3(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// 哈希：由像素、样本序号和维度确定性地生成随机数
+
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, v| {
+        mix(h ^ v.wrapping_add(0x9e3779b97f4a7c15))
+    })
+}
+
+fn hash_float(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// 将 u32 表示的 [0, 1) 上的定点数转换为浮点数
+fn to_unit(x: u32) -> f64 {
+    x as f64 / 4294967296.0
+}
+
+// 每个样本独立地均匀采样，每个像素样本使用各自的随机数序列
+#[derive(Debug, Clone, Copy)]
+pub struct IndependentSampler {
+    seed: u64,
+    rng: utils::Rng,
+}
+
+impl IndependentSampler {
+    pub fn new() -> Self {
+        IndependentSampler {
+            seed: 0,
+            rng: utils::Rng::new(0),
+        }
+    }
+}
+
+impl Default for IndependentSampler {
+    fn default() -> Self {
+        IndependentSampler::new()
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize) {
+        self.rng = utils::Rng::new(hash(&[self.seed, col as u64, row as u64, index as u64]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.random()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.rng.random(), self.rng.random())
+    }
+
+    fn name(&self) -> &'static str {
+        "independent"
+    }
+    fn box_clone(&self) -> Box<dyn Sampler> {
+        Box::new(*self)
+    }
+}
+
+// 种子、当前像素样本的位置，以及下一个要取用的维度
+#[derive(Debug, Clone, Copy, Default)]
+struct SampleState {
+    seed: u64,
+    col: u64,
+    row: u64,
+    index: u64,
+    dimension: u64,
+}
+
+impl SampleState {
+    fn start(&mut self, col: usize, row: usize, index: usize) {
+        *self = SampleState {
+            seed: self.seed,
+            col: col as u64,
+            row: row as u64,
+            index: index as u64,
+            dimension: 0,
+        };
+    }
+
+    fn next_dimension(&mut self) -> u64 {
+        self.dimension += 1;
+        self.dimension - 1
+    }
+}
+
+// Kensler 的哈希置换：返回 [0, l) 的第 p 个伪随机排列中位置 i 上的元素
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+// 分层抖动采样：每个维度划分为 strata² 个层，每 strata² 个样本各落入一层，
+// 层的顺序按像素和维度随机打乱，层内随机抖动。样本数超过 strata² 后开始新的一轮
+#[derive(Debug, Clone, Copy)]
+pub struct StratifiedSampler {
+    pub strata: usize,
+    state: SampleState,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        let strata = ((samples_per_pixel.min(1 << 16) as f64).sqrt().ceil() as usize).max(1);
+        StratifiedSampler {
+            strata,
+            state: SampleState::default(),
+        }
+    }
+
+    // 当前样本在本维度上所在的层，以及层内的抖动
+    fn stratum(&mut self) -> (usize, u64) {
+        let s = self.state;
+        let dimension = self.state.next_dimension();
+        let count = (self.strata * self.strata) as u64;
+        let round = s.index / count;
+        let seed = hash(&[s.seed, s.col, s.row, dimension, round]);
+        let stratum = permutation_element((s.index % count) as u32, count as u32, seed as u32);
+        (stratum as usize, hash(&[seed, s.index]))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn set_seed(&mut self, seed: u64) {
+        self.state.seed = seed;
+    }
+
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize) {
+        self.state.start(col, row, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter) = self.stratum();
+        let count = self.strata * self.strata;
+        (stratum as f64 + hash_float(jitter)) / count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, jitter) = self.stratum();
+        let n = self.strata as f64;
+        (
+            ((stratum % self.strata) as f64 + hash_float(jitter)) / n,
+            ((stratum / self.strata) as f64 + hash_float(mix(jitter))) / n,
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "stratified"
+    }
+    fn box_clone(&self) -> Box<dyn Sampler> {
+        Box::new(*self)
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// 以 base 为底将 index 的各位数字倒序排列到小数点之后
+fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        inv *= inv_base;
+        result += (index % base) as f64 * inv;
+        index /= base;
+    }
+    result
+}
+
+// Halton 序列：第 d 维以第 d 个质数为底，每个像素按哈希做 Cranley-Patterson 旋转。
+// 超出质数表的维度退化为独立随机数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new() -> Self {
+        HaltonSampler::default()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn set_seed(&mut self, seed: u64) {
+        self.state.seed = seed;
+    }
+
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize) {
+        self.state.start(col, row, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let s = self.state;
+        let dimension = self.state.next_dimension();
+        let h = hash(&[s.seed, s.col, s.row, dimension]);
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => (radical_inverse(base, s.index) + hash_float(h)).fract(),
+            None => hash_float(hash(&[h, s.index])),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    fn name(&self) -> &'static str {
+        "halton"
+    }
+    fn box_clone(&self) -> Box<dyn Sampler> {
+        Box::new(*self)
+    }
+}
+
+// Sobol 序列的前两维
+fn sobol(mut index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut v = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            y ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+// Laine-Karras 哈希，低位只影响高位，配合位反转实现 Owen 置乱
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// 经 Owen 置乱的二维 Sobol 点，seed 不同的维度对之间通过打乱样本序号去相关
+fn owen_sobol_2d(index: u64, seed: u64) -> (u32, u32) {
+    let index = nested_uniform_scramble(index as u32, seed as u32);
+    let (x, y) = sobol(index);
+    (
+        nested_uniform_scramble(x, mix(seed ^ 1) as u32),
+        nested_uniform_scramble(y, mix(seed ^ 2) as u32),
+    )
+}
+
+// Owen 置乱的 Sobol 序列 (Burley 2020)：每次取用的一维或二维都由 Sobol 前两维
+// 生成，用各自的种子置乱，种子由像素和维度决定
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new() -> Self {
+        SobolSampler::default()
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn set_seed(&mut self, seed: u64) {
+        self.state.seed = seed;
+    }
+
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize) {
+        self.state.start(col, row, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let s = self.state;
+        let dimension = self.state.next_dimension();
+        to_unit(owen_sobol_2d(s.index, hash(&[s.seed, s.col, s.row, dimension])).0)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let s = self.state;
+        let dimension = self.state.next_dimension();
+        self.state.next_dimension();
+        let (x, y) = owen_sobol_2d(s.index, hash(&[s.seed, s.col, s.row, dimension]));
+        (to_unit(x), to_unit(y))
+    }
+
+    fn name(&self) -> &'static str {
+        "sobol"
+    }
+    fn box_clone(&self) -> Box<dyn Sampler> {
+        Box::new(*self)
+    }
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+// 用 void-and-cluster 方法生成可平铺的蓝噪声阈值图，值为 0..SIZE² 的排列
+fn generate_blue_noise() -> Vec<u32> {
+    const N: usize = BLUE_NOISE_SIZE;
+    let sigma = 1.5;
+    let mut kernel = vec![0.0; N * N];
+    for dy in 0..N {
+        for dx in 0..N {
+            let x = dx.min(N - dx) as f64;
+            let y = dy.min(N - dy) as f64;
+            kernel[dy * N + dx] = (-(x * x + y * y) / (2.0 * sigma * sigma)).exp();
+        }
+    }
+
+    let mut energy = vec![0.0; N * N];
+    let mut pattern = vec![false; N * N];
+    let update = |energy: &mut Vec<f64>, i: usize, sign: f64| {
+        let (x0, y0) = (i % N, i / N);
+        for y in 0..N {
+            for x in 0..N {
+                let k = kernel[((y + N - y0) % N) * N + (x + N - x0) % N];
+                energy[y * N + x] += sign * k;
+            }
+        }
+    };
+    // 能量最高的点(最密集的簇)与能量最低的空位(最大的空洞)
+    let tightest = |energy: &[f64], pattern: &[bool]| {
+        (0..N * N)
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |energy: &[f64], pattern: &[bool]| {
+        (0..N * N)
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // 初始点集：随机选取约十分之一的点，再反复把最密集的点移到最大的空洞中直到稳定
+    let initial = N * N / 10;
+    let mut placed = 0;
+    let mut seed = 0;
+    while placed < initial {
+        let i = (hash(&[seed]) % (N * N) as u64) as usize;
+        seed += 1;
+        if !pattern[i] {
+            pattern[i] = true;
+            update(&mut energy, i, 1.0);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = tightest(&energy, &pattern);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = largest_void(&energy, &pattern);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; N * N];
+    // 从初始点集中依次移除最密集的点，排名递减
+    let (mut removed, mut removed_energy) = (pattern.clone(), energy.clone());
+    for rank in (0..initial).rev() {
+        let cluster = tightest(&removed_energy, &removed);
+        removed[cluster] = false;
+        update(&mut removed_energy, cluster, -1.0);
+        ranks[cluster] = rank as u32;
+    }
+    // 再依次填入最大的空洞，排名递增
+    for rank in initial..N * N {
+        let void = largest_void(&energy, &pattern);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        ranks[void] = rank as u32;
+    }
+    ranks
+}
+
+fn blue_noise() -> &'static [u32] {
+    static BLUE_NOISE: OnceLock<Vec<u32>> = OnceLock::new();
+    BLUE_NOISE.get_or_init(generate_blue_noise)
+}
+
+// 在所有像素共用的 Owen 置乱 Sobol 序列上，按蓝噪声阈值图对每个像素做
+// Cranley-Patterson 旋转，使相邻像素的误差互补，低样本数时噪声呈蓝噪声分布。
+// 每个维度使用阈值图的不同平移
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlueNoiseSampler {
+    state: SampleState,
+}
+
+impl BlueNoiseSampler {
+    pub fn new() -> Self {
+        blue_noise();
+        BlueNoiseSampler::default()
+    }
+
+    fn offset(&self, dimension: u64) -> f64 {
+        let shift = hash(&[self.state.seed, dimension]);
+        let x = (self.state.col + (shift & 0xffff)) as usize % BLUE_NOISE_SIZE;
+        let y = (self.state.row + (shift >> 16 & 0xffff)) as usize % BLUE_NOISE_SIZE;
+        let rank = blue_noise()[y * BLUE_NOISE_SIZE + x];
+        (rank as f64 + 0.5) / (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as f64
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn set_seed(&mut self, seed: u64) {
+        self.state.seed = seed;
+    }
+
+    fn start_pixel_sample(&mut self, col: usize, row: usize, index: usize) {
+        self.state.start(col, row, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.next_dimension();
+        let x = owen_sobol_2d(self.state.index, hash(&[self.state.seed, dimension])).0;
+        (to_unit(x) + self.offset(dimension)).fract()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.next_dimension();
+        self.state.next_dimension();
+        let (x, y) = owen_sobol_2d(self.state.index, hash(&[self.state.seed, dimension]));
+        (
+            (to_unit(x) + self.offset(dimension)).fract(),
+            (to_unit(y) + self.offset(dimension + 1)).fract(),
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "blue-noise"
+    }
+    fn box_clone(&self) -> Box<dyn Sampler> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn samples_2d(sampler: &mut dyn Sampler, count: usize) -> Vec<(f64, f64)> {
+        (0..count)
+            .map(|i| {
+                sampler.start_pixel_sample(3, 5, i);
+                sampler.get_1d();
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    // 每个 1/n × 1/n 的格子中恰有一个样本
+    fn is_stratified(samples: &[(f64, f64)], n: usize) -> bool {
+        let mut cells = vec![false; n * n];
+        for (x, y) in samples {
+            assert!((0.0..1.0).contains(x) && (0.0..1.0).contains(y));
+            let cell = (*y * n as f64) as usize * n + (*x * n as f64) as usize;
+            if cells[cell] {
+                return false;
+            }
+            cells[cell] = true;
+        }
+        true
+    }
+
+    #[test]
+    fn test_samplers_are_stratified() {
+        assert!(is_stratified(
+            &samples_2d(&mut StratifiedSampler::new(64), 64),
+            8
+        ));
+        assert!(is_stratified(&samples_2d(&mut SobolSampler::new(), 64), 8));
+
+        for name in SAMPLERS.iter() {
+            let mut sampler = from_name(name, 16).unwrap();
+            for (x, y) in samples_2d(&mut *sampler, 256) {
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+            }
+        }
+        assert!(from_name("random", 16).is_none());
+    }
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_permutation_element() {
+        let mut seen = [false; 37];
+        for i in 0..37 {
+            seen[permutation_element(i, 37, 12345) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn test_blue_noise_is_permutation() {
+        let mut seen = vec![false; BLUE_NOISE_SIZE * BLUE_NOISE_SIZE];
+        for &rank in blue_noise() {
+            seen[rank as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+}
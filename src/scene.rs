@@ -0,0 +1,780 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter, Serializer};
+
+use crate::camera::LensCamera;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::integrator::{
+    Background, Bidirectional, Heuristic, Integrator, Mis, NextEvent, Normals, PathTracer,
+    PhotonMapping, Recursive,
+};
+use crate::material::{
+    DefaultMaterial, Dielectric, DiffuseLight, Lambertian, Material, Metal, MetallicRoughness,
+};
+use crate::quad::Quad;
+use crate::renderer::{ImageSettings, Renderer};
+use crate::sampler;
+use crate::sphere::Sphere;
+use crate::vec3;
+
+// 场景描述文件(JSON)：
+//   {
+//     "image": { "width": 400, "aspect_ratio": 1.5, "samples": 100, "max_depth": 50 },
+//     "camera": { "look_from": [13, 2, 3], "look_at": [0, 0, 0], "vfov": 20 },
+//     "background": { "type": "sky" },
+//     "materials": { "ground": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] } },
+//     "objects": [ { "type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground" } ],
+//     "lights": [ { "type": "sphere", "center": [0, 5, 0], "radius": 1, "emission": [4, 4, 4] } ]
+//   }
+// 除 camera 外都可以省略，向量写作 [x, y, z]。
+// 物体与光源还可以是平行四边形 { "type": "quad", "q": 一角, "u": 一边, "v": 另一边, ... }
+// 材质还可以是 glTF 的 { "type": "metallic_roughness", "base_color": ..., "metallic": ...,
+// "roughness": ..., "emission": 可省略 }
+
+fn vec(v: [f64; 3]) -> vec3::Vec3 {
+    vec3::Vec3(v[0], v[1], v[2])
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn unsupported(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageDescription {
+    pub width: usize,
+    pub aspect_ratio: f64,
+    pub samples: usize,
+    pub max_depth: u64,
+}
+
+impl Default for ImageDescription {
+    fn default() -> Self {
+        ImageDescription {
+            width: 400,
+            aspect_ratio: 16.0 / 9.0,
+            samples: 100,
+            max_depth: 50,
+        }
+    }
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f64 {
+    90.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: [f64; 3],
+    pub look_at: [f64; 3],
+    #[serde(default = "default_up")]
+    pub up: [f64; 3],
+    #[serde(default = "default_vfov")]
+    pub vfov: f64,
+    #[serde(default)]
+    pub aperture: f64,
+    // 省略时对焦到 look_at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f64>,
+    // 视口的宽高比，省略时与图像相同
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BackgroundDescription {
+    #[default]
+    Sky,
+    Color {
+        color: [f64; 3],
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegratorDescription {
+    #[default]
+    Recursive,
+    NextEvent,
+    Mis,
+    MisBalance,
+    Path,
+    Bidirectional,
+    PhotonMapping,
+    Normals,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: [f64; 3],
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        ir: f64,
+    },
+    DiffuseLight {
+        emission: [f64; 3],
+    },
+    MetallicRoughness {
+        base_color: [f64; 3],
+        metallic: f64,
+        roughness: f64,
+        #[serde(default)]
+        emission: [f64; 3],
+    },
+    // 既不散射也不发光
+    Default,
+}
+
+impl MaterialDescription {
+    pub fn build(&self) -> Arc<dyn Material> {
+        match *self {
+            MaterialDescription::Lambertian { albedo } => Arc::new(Lambertian::new(vec(albedo))),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal::new(vec(albedo), fuzz)),
+            MaterialDescription::Dielectric { ir } => Arc::new(Dielectric::new(ir)),
+            MaterialDescription::DiffuseLight { emission } => {
+                Arc::new(DiffuseLight::new(vec(emission)))
+            }
+            MaterialDescription::MetallicRoughness {
+                base_color,
+                metallic,
+                roughness,
+                emission,
+            } => {
+                let mut material = MetallicRoughness::new(vec(base_color), metallic, roughness);
+                material.emission = vec(emission);
+                Arc::new(material)
+            }
+            MaterialDescription::Default => Arc::new(DefaultMaterial::new()),
+        }
+    }
+}
+
+// 物体通过名称引用 materials 中的材质
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+    },
+}
+
+// 光源是带有发光材质的物体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        emission: [f64; 3],
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        emission: [f64; 3],
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub image: ImageDescription,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub background: BackgroundDescription,
+    #[serde(default)]
+    pub integrator: IntegratorDescription,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampler: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl SceneDescription {
+    pub fn parse(text: &str) -> io::Result<SceneDescription> {
+        serde_json::from_str(text).map_err(|e| invalid_data(e.to_string()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<SceneDescription> {
+        SceneDescription::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn world(&self) -> io::Result<HittableList> {
+        let materials: BTreeMap<&str, Arc<dyn Material>> = self
+            .materials
+            .iter()
+            .map(|(name, material)| (name.as_str(), material.build()))
+            .collect();
+
+        let material = |name: &str| {
+            materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| invalid_data(format!("unknown material `{}`", name)))
+        };
+
+        let mut world = HittableList::new();
+        for object in &self.objects {
+            match object {
+                ObjectDescription::Sphere {
+                    center,
+                    radius,
+                    material: name,
+                } => {
+                    let mut sphere = Sphere::new(vec(*center), *radius);
+                    sphere.mat_ptr = material(name)?;
+                    world.add(Arc::new(sphere));
+                }
+                ObjectDescription::Quad {
+                    q,
+                    u,
+                    v,
+                    material: name,
+                } => {
+                    let mut quad = Quad::new(vec(*q), vec(*u), vec(*v));
+                    quad.mat_ptr = material(name)?;
+                    world.add(Arc::new(quad));
+                }
+            }
+        }
+        for light in self.light_objects().objects {
+            world.add(light);
+        }
+        Ok(world)
+    }
+
+    // 光源同时加入场景与积分器，积分器朝向它们采样
+    fn light_objects(&self) -> HittableList {
+        let mut lights = HittableList::new();
+        for light in &self.lights {
+            match *light {
+                LightDescription::Sphere {
+                    center,
+                    radius,
+                    emission,
+                } => {
+                    let mut sphere = Sphere::new(vec(center), radius);
+                    sphere.mat_ptr = Arc::new(DiffuseLight::new(vec(emission)));
+                    lights.add(Arc::new(sphere));
+                }
+                LightDescription::Quad { q, u, v, emission } => {
+                    let mut quad = Quad::new(vec(q), vec(u), vec(v));
+                    quad.mat_ptr = Arc::new(DiffuseLight::new(vec(emission)));
+                    lights.add(Arc::new(quad));
+                }
+            }
+        }
+        lights
+    }
+
+    pub fn camera(&self) -> LensCamera {
+        let camera = &self.camera;
+        let (look_from, look_at) = (vec(camera.look_from), vec(camera.look_at));
+        LensCamera::new(
+            look_from,
+            look_at,
+            vec(camera.up),
+            camera.vfov,
+            camera.aspect_ratio.unwrap_or(self.image.aspect_ratio),
+            camera.aperture,
+            camera
+                .focus_distance
+                .unwrap_or_else(|| (look_from - look_at).length()),
+        )
+    }
+
+    pub fn integrator(&self) -> Arc<dyn Integrator> {
+        let background = match self.background {
+            BackgroundDescription::Sky => Background::Sky,
+            BackgroundDescription::Color { color } => Background::Color(vec(color)),
+        };
+        let lights: Option<Arc<dyn Hittable>> = if self.lights.is_empty() {
+            None
+        } else {
+            Some(Arc::new(self.light_objects()))
+        };
+        match self.integrator {
+            IntegratorDescription::Recursive => {
+                let mut integrator = Recursive::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                Arc::new(integrator)
+            }
+            IntegratorDescription::NextEvent => {
+                let mut integrator = NextEvent::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                Arc::new(integrator)
+            }
+            IntegratorDescription::Mis | IntegratorDescription::MisBalance => {
+                let mut integrator = Mis::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                if self.integrator == IntegratorDescription::MisBalance {
+                    integrator.heuristic = Heuristic::Balance;
+                }
+                Arc::new(integrator)
+            }
+            IntegratorDescription::Path => {
+                let mut integrator = PathTracer::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                Arc::new(integrator)
+            }
+            IntegratorDescription::Bidirectional => {
+                let mut integrator = Bidirectional::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                Arc::new(integrator)
+            }
+            IntegratorDescription::PhotonMapping => {
+                let mut integrator = PhotonMapping::new();
+                integrator.background = background;
+                integrator.lights = lights;
+                Arc::new(integrator)
+            }
+            IntegratorDescription::Normals => Arc::new(Normals::new()),
+        }
+    }
+
+    // 文件中没有指定 output 时写出到 output
+    pub fn renderer(&self, output: &str) -> io::Result<Renderer> {
+        let aspect_ratio = self.image.aspect_ratio;
+        if !aspect_ratio.is_finite() || aspect_ratio <= 0.0 {
+            return Err(invalid_data(format!(
+                "invalid image aspect ratio {}",
+                aspect_ratio
+            )));
+        }
+        let image = ImageSettings::new(
+            self.image.width,
+            self.image.aspect_ratio,
+            self.image.samples,
+            self.image.max_depth,
+        );
+        image.validate()?;
+        let mut renderer = Renderer::new(
+            Arc::new(self.world()?),
+            Arc::new(self.camera()),
+            image,
+            self.integrator(),
+            self.output.as_deref().unwrap_or(output),
+        );
+        if let Some(name) = &self.sampler {
+            renderer.sampler = sampler::from_name(name, image.samples_per_pixel)
+                .ok_or_else(|| invalid_data(format!("unknown sampler `{}`", name)))?;
+        }
+        if let Some(seed) = self.seed {
+            renderer.seed = seed;
+        }
+        Ok(renderer)
+    }
+
+    // 导出程序生成的场景，图像尺寸、采样器与种子取自 renderer
+    pub fn from_renderer(renderer: &Renderer) -> io::Result<SceneDescription> {
+        let mut writer = SceneWriter::default();
+        renderer.world.describe(&mut writer)?;
+        let image = &renderer.image;
+        let image_aspect_ratio = aspect_ratio(image.width, image.height);
+        let mut camera = renderer
+            .camera
+            .describe()
+            .ok_or_else(|| unsupported("camera cannot be exported"))?;
+        if camera
+            .aspect_ratio
+            .is_some_and(|a| (a / image_aspect_ratio - 1.0).abs() < 1e-9)
+        {
+            camera.aspect_ratio = None;
+        }
+        let (integrator, background) = renderer
+            .integrator
+            .describe()
+            .ok_or_else(|| unsupported("integrator cannot be exported"))?;
+
+        Ok(SceneDescription {
+            image: ImageDescription {
+                width: image.width,
+                aspect_ratio: image_aspect_ratio,
+                samples: image.samples_per_pixel,
+                max_depth: image.max_depth,
+            },
+            camera,
+            background,
+            integrator,
+            materials: writer.materials,
+            objects: writer.objects,
+            lights: writer.lights,
+            sampler: Some(renderer.sampler.name().to_string()),
+            seed: Some(renderer.seed),
+            output: None,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = Vec::new();
+        let mut serializer = Serializer::with_formatter(&mut json, SceneFormatter::new());
+        self.serialize(&mut serializer).unwrap();
+        String::from_utf8(json).unwrap()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json() + "\n")
+    }
+}
+
+// ImageSettings::new 由宽度和宽高比截断得到高度，取能还原出原高度的宽高比
+fn aspect_ratio(width: usize, height: usize) -> f64 {
+    let mut aspect_ratio = width as f64 / height as f64;
+    while aspect_ratio > 0.0 && ((width as f64 / aspect_ratio) as usize) < height {
+        // 正数的位模式减一即为下一个更小的浮点数
+        aspect_ratio = f64::from_bits(aspect_ratio.to_bits() - 1);
+    }
+    aspect_ratio
+}
+
+// 与 PrettyFormatter 相同，但只含数字的数组写在一行，例如向量 [0.5, 0.5, 0.5]。
+// 数组元素开始时先不换行，等到元素的内容写出时再按是否为数字决定
+struct SceneFormatter {
+    pretty: PrettyFormatter<'static>,
+    // 各层数组是否写在一行
+    arrays: Vec<bool>,
+    // 尚未写出开头的数组元素，值为是否为第一个元素
+    pending: Option<bool>,
+}
+
+impl SceneFormatter {
+    fn new() -> Self {
+        SceneFormatter {
+            pretty: PrettyFormatter::new(),
+            arrays: Vec::new(),
+            pending: None,
+        }
+    }
+
+    // 写出一个值之前写出所在数组元素的开头
+    fn value<W: ?Sized + io::Write>(&mut self, writer: &mut W, number: bool) -> io::Result<()> {
+        if let Some(first) = self.pending.take() {
+            let compact = self.arrays.last_mut().unwrap();
+            if number && (first || *compact) {
+                *compact = true;
+                if !first {
+                    writer.write_all(b", ")?;
+                }
+            } else {
+                *compact = false;
+                self.pretty.begin_array_value(writer, first)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+macro_rules! write_numbers {
+    ($($name:ident: $ty:ty),*) => {
+        $(
+            fn $name<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: $ty) -> io::Result<()> {
+                self.value(writer, true)?;
+                CompactFormatter.$name(writer, value)
+            }
+        )*
+    };
+}
+
+impl Formatter for SceneFormatter {
+    write_numbers!(
+        write_i8: i8, write_i16: i16, write_i32: i32, write_i64: i64, write_i128: i128,
+        write_u8: u8, write_u16: u16, write_u32: u32, write_u64: u64, write_u128: u128,
+        write_f32: f32, write_f64: f64
+    );
+
+    fn write_null<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.value(writer, false)?;
+        CompactFormatter.write_null(writer)
+    }
+
+    fn write_bool<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: bool) -> io::Result<()> {
+        self.value(writer, false)?;
+        CompactFormatter.write_bool(writer, value)
+    }
+
+    fn begin_string<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.value(writer, false)?;
+        CompactFormatter.begin_string(writer)
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.value(writer, false)?;
+        self.arrays.push(false);
+        self.pretty.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.arrays.pop();
+        self.pretty.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        _writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.pending = Some(first);
+        Ok(())
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if *self.arrays.last().unwrap() {
+            Ok(())
+        } else {
+            self.pretty.end_array_value(writer)
+        }
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.value(writer, false)?;
+        self.pretty.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object_value(writer)
+    }
+}
+
+// 物体的几何形状，材质由 SceneWriter 另行命名
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+    },
+}
+
+// 导出场景时收集物体与材质，相同的材质只写出一次，发光的物体写为光源
+#[derive(Debug, Default)]
+pub struct SceneWriter {
+    materials: BTreeMap<String, MaterialDescription>,
+    objects: Vec<ObjectDescription>,
+    lights: Vec<LightDescription>,
+}
+
+impl SceneWriter {
+    pub fn add(&mut self, shape: ShapeDescription, material: &dyn Material) -> io::Result<()> {
+        let material = material.describe().ok_or_else(|| {
+            // 以 Debug 输出开头的类型名指明材质
+            let debug = format!("{:?}", material);
+            let name = debug.split(|c: char| !c.is_alphanumeric()).next();
+            unsupported(&format!(
+                "material {} cannot be exported",
+                name.unwrap_or_default()
+            ))
+        })?;
+        if let MaterialDescription::DiffuseLight { emission } = material {
+            self.lights.push(match shape {
+                ShapeDescription::Sphere { center, radius } => LightDescription::Sphere {
+                    center,
+                    radius,
+                    emission,
+                },
+                ShapeDescription::Quad { q, u, v } => LightDescription::Quad { q, u, v, emission },
+            });
+        } else {
+            let material = self.material_name(material);
+            self.objects.push(match shape {
+                ShapeDescription::Sphere { center, radius } => ObjectDescription::Sphere {
+                    center,
+                    radius,
+                    material,
+                },
+                ShapeDescription::Quad { q, u, v } => ObjectDescription::Quad { q, u, v, material },
+            });
+        }
+        Ok(())
+    }
+
+    fn material_name(&mut self, material: MaterialDescription) -> String {
+        if let Some((name, _)) = self.materials.iter().find(|(_, m)| **m == material) {
+            return name.clone();
+        }
+        let name = format!("material{}", self.materials.len());
+        self.materials.insert(name.clone(), material);
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::texture::SolidColor;
+
+    const SCENE: &str = r#"{
+        "image": { "width": 60, "samples": 4 },
+        "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1], "aperture": 0.1 },
+        "background": { "type": "color", "color": [0, 0, 0] },
+        "materials": {
+            "ground": { "type": "lambertian", "albedo": [0.8, 0.8, 0.0] },
+            "glass": { "type": "dielectric", "ir": 1.5 }
+        },
+        "objects": [
+            { "type": "sphere", "center": [0, -100.5, -1], "radius": 100, "material": "ground" },
+            { "type": "sphere", "center": [0, 0, -1], "radius": 0.5, "material": "glass" }
+        ],
+        "lights": [
+            { "type": "sphere", "center": [0, 2, -1], "radius": 0.5, "emission": [4, 4, 4] },
+            { "type": "quad", "q": [-1, 3, -2], "u": [2, 0, 0], "v": [0, 0, 1], "emission": [2, 2, 2] }
+        ],
+        "integrator": "next_event",
+        "seed": 3
+    }"#;
+
+    #[test]
+    fn test_load_scene() {
+        let scene = SceneDescription::parse(SCENE).unwrap();
+        assert_eq!(scene.image.width, 60);
+        assert_eq!(scene.image.max_depth, 50);
+        assert_eq!(scene.camera.up, [0.0, 1.0, 0.0]);
+        assert_eq!(scene.world().unwrap().objects.len(), 4);
+
+        let renderer = scene.renderer("out.ppm").unwrap();
+        assert_eq!(renderer.image.height, 33);
+        assert_eq!(renderer.seed, 3);
+        assert_eq!(renderer.output, "out.ppm");
+    }
+
+    #[test]
+    fn test_scene_errors() {
+        assert!(SceneDescription::parse("{}").is_err());
+        assert!(SceneDescription::parse(&SCENE.replace("albedo", "colour")).is_err());
+
+        let scene = SceneDescription::parse(&SCENE.replace("\"glass\" }", "\"steel\" }")).unwrap();
+        let error = scene.world().err().unwrap();
+        assert!(error.to_string().contains("steel"));
+
+        // 图像至少为 2x2 且每个像素至少一个样本
+        for image in [
+            r#""width": 4, "aspect_ratio": 4, "samples": 2"#,
+            r#""width": 1, "samples": 4"#,
+            r#""width": 60, "aspect_ratio": 100, "samples": 4"#,
+            r#""width": 60, "aspect_ratio": -1, "samples": 4"#,
+            r#""width": 60, "samples": 0"#,
+        ] {
+            let text = SCENE.replace(r#""width": 60, "samples": 4"#, image);
+            let error = SceneDescription::parse(&text)
+                .unwrap()
+                .renderer("out.ppm")
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_export_scene() {
+        let scene = SceneDescription::parse(SCENE).unwrap();
+        let exported =
+            SceneDescription::from_renderer(&scene.renderer("out.ppm").unwrap()).unwrap();
+        assert_eq!(exported.image.width, scene.image.width);
+        assert_eq!(exported.background, scene.background);
+        assert_eq!(exported.materials.len(), 2);
+        assert_eq!(exported.objects.len(), 2);
+        assert_eq!(exported.lights, scene.lights);
+        assert_eq!(exported.integrator, IntegratorDescription::NextEvent);
+        assert_eq!(exported.seed, Some(3));
+
+        let camera = &exported.camera;
+        assert_eq!(camera.look_from, scene.camera.look_from);
+        assert!((vec(camera.look_at) - vec([0.0, 0.0, -1.0])).length() < 1e-12);
+        assert!((vec(camera.up) - vec([0.0, 1.0, 0.0])).length() < 1e-12);
+        assert!((camera.vfov - 90.0).abs() < 1e-12);
+        assert!((camera.aperture - 0.1).abs() < 1e-12);
+        assert!((camera.aspect_ratio.unwrap() - 16.0 / 9.0).abs() < 1e-12);
+
+        let json = exported.to_json();
+        assert!(json.contains("\"albedo\": [0.8, 0.8, 0.0]"));
+        let parsed = SceneDescription::parse(&json).unwrap();
+        assert_eq!(parsed, exported);
+        assert_eq!(parsed.renderer("out.ppm").unwrap().image.height, 33);
+
+        // 字符串中的方括号原样写出
+        let mut exported = exported;
+        exported.output = Some(String::from("out[ 1 ].png"));
+        let json = exported.to_json();
+        assert!(json.contains("\"output\": \"out[ 1 ].png\""));
+        assert_eq!(SceneDescription::parse(&json).unwrap(), exported);
+
+        // 没有贴图的金属度-粗糙度材质可以导出，有贴图时的错误指明材质
+        let sphere = || ShapeDescription::Sphere {
+            center: [0.0; 3],
+            radius: 1.0,
+        };
+        let mut material = MetallicRoughness::new(vec3::Color::fill(0.5), 1.0, 0.2);
+        let mut writer = SceneWriter::default();
+        writer.add(sphere(), &material).unwrap();
+        let description = &writer.materials["material0"];
+        assert_eq!(description.build().describe().as_ref(), Some(description));
+        material.base_color_texture = Some(Arc::new(SolidColor::new(vec3::Color::fill(1.0))));
+        let error = writer.add(sphere(), &material).err().unwrap();
+        assert!(error.to_string().contains("MetallicRoughness"));
+    }
+}
@@ -1,7 +1,12 @@
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::{DefaultMaterial, Material};
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::{self, Sampler};
+use crate::scene::{SceneWriter, ShapeDescription};
 use crate::vec3;
+use std::f64::consts::PI;
+use std::io;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -11,6 +16,13 @@ pub struct Sphere {
     pub mat_ptr: Arc<dyn Material>,
 }
 
+// 单位球面上的点 p 对应的 (u, v)，u 绕 y 轴从 -x 开始，v 从 -y 到 +y
+fn get_sphere_uv(p: vec3::Point3) -> (f64, f64) {
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
 impl Sphere {
     pub fn new(center: vec3::Point3, radius: f64) -> Self {
         let mat_ptr = Arc::new(DefaultMaterial::new());
@@ -46,8 +58,64 @@ impl Hittable for Sphere {
         rec.p = r.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        let (u, v) = get_sphere_uv(outward_normal);
+        rec.u = u;
+        rec.v = v;
         rec.mat_ptr = self.mat_ptr.clone();
 
         true
     }
+
+    // 在 origin 看到的球所张的圆锥内均匀采样，origin 在球内时对整个球面均匀采样
+    fn pdf_value(&self, origin: vec3::Point3, direction: vec3::Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec) {
+            return 0.0;
+        }
+        let distance_squared = (self.center - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: vec3::Point3, sampler: &mut dyn Sampler) -> vec3::Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return sampler::unit_vector(sampler.get_2d());
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        Onb::new(direction).local(sampler::cone_direction(cos_theta_max, sampler.get_2d()))
+    }
+
+    fn random_point(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord, f64)> {
+        let outward_normal = sampler::unit_vector(sampler.get_2d());
+        let mut rec = HitRecord::new();
+        rec.p = self.center + self.radius * outward_normal;
+        rec.normal = outward_normal;
+        rec.front_face = true;
+        let (u, v) = get_sphere_uv(outward_normal);
+        rec.u = u;
+        rec.v = v;
+        rec.mat_ptr = self.mat_ptr.clone();
+        Some((rec, 1.0 / (4.0 * PI * self.radius * self.radius)))
+    }
+
+    // 与球面的距离在舍入误差以内的点都视为在球面上
+    fn pdf_point(&self, p: vec3::Point3) -> f64 {
+        if ((p - self.center).length() - self.radius).abs() > 1e-6 * self.radius {
+            return 0.0;
+        }
+        1.0 / (4.0 * PI * self.radius * self.radius)
+    }
+
+    fn describe(&self, scene: &mut SceneWriter) -> io::Result<()> {
+        let shape = ShapeDescription::Sphere {
+            center: self.center.to_array(),
+            radius: self.radius,
+        };
+        scene.add(shape, &*self.mat_ptr)
+    }
 }
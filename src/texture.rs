@@ -0,0 +1,295 @@
+use std::fmt;
+use std::fmt::Debug;
+use std::io;
+
+use crate::vec3;
+
+// 纹理在表面坐标 (u, v) 处的值，v 向上
+pub trait Texture: Debug + Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &vec3::Point3) -> vec3::Color;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolidColor {
+    color: vec3::Color,
+}
+
+impl SolidColor {
+    pub fn new(color: vec3::Color) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &vec3::Point3) -> vec3::Color {
+        self.color
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// sRGB 编码的分量转换为线性值
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// 图片纹理，按行从上到下存放线性颜色，超出 [0, 1] 的坐标重复平铺，双线性插值
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    data: Vec<vec3::Color>,
+}
+
+// 不输出像素数据
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImageTexture")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, data: Vec<vec3::Color>) -> Self {
+        assert_eq!(data.len(), width * height);
+        ImageTexture {
+            width,
+            height,
+            data,
+        }
+    }
+
+    // 解码 PNG、JPEG 或 Radiance HDR 图片，srgb 为 true 时把 PNG 与 JPEG 转换为线性颜色，
+    // HDR 图片本身就是线性的
+    pub fn decode(bytes: &[u8], srgb: bool) -> io::Result<ImageTexture> {
+        let (width, height, channels, values) = if bytes.starts_with(b"\x89PNG") {
+            decode_png(bytes)?
+        } else if bytes.starts_with(&[0xff, 0xd8]) {
+            decode_jpeg(bytes)?
+        } else if bytes.starts_with(b"#?") {
+            decode_hdr(bytes)?
+        } else {
+            return Err(invalid_data(String::from(
+                "unknown image format, expected PNG, JPEG or Radiance HDR",
+            )));
+        };
+        let srgb = srgb && !bytes.starts_with(b"#?");
+
+        let convert = |c: f64| if srgb { srgb_to_linear(c) } else { c };
+        let data = values
+            .chunks_exact(channels)
+            .map(|p| match channels {
+                1 | 2 => vec3::Color::fill(convert(p[0])),
+                _ => vec3::Vec3(convert(p[0]), convert(p[1]), convert(p[2])),
+            })
+            .collect();
+        Ok(ImageTexture::new(width, height, data))
+    }
+
+    pub fn average(&self) -> vec3::Color {
+        self.data
+            .iter()
+            .fold(vec3::Color::fill(0.0), |sum, &c| sum + c)
+            / self.data.len().max(1) as f64
+    }
+
+    fn texel(&self, x: i64, y: i64) -> vec3::Color {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.data[y * self.width + x]
+    }
+}
+
+// 返回 (宽, 高, 通道数, [0, 1] 内的分量)
+fn decode_png(bytes: &[u8]) -> io::Result<(usize, usize, usize, Vec<f64>)> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| invalid_data(e.to_string()))?;
+    buf.truncate(info.buffer_size());
+
+    let channels = info.color_type.samples();
+    let values = match info.bit_depth {
+        png::BitDepth::Sixteen => buf
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]) as f64 / 65535.0)
+            .collect(),
+        _ => buf.iter().map(|&c| c as f64 / 255.0).collect(),
+    };
+    Ok((info.width as usize, info.height as usize, channels, values))
+}
+
+fn decode_jpeg(bytes: &[u8]) -> io::Result<(usize, usize, usize, Vec<f64>)> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode().map_err(|e| invalid_data(e.to_string()))?;
+    let info = decoder.info().unwrap();
+    let channels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => 1,
+        jpeg_decoder::PixelFormat::RGB24 => 3,
+        format => {
+            return Err(invalid_data(format!(
+                "unsupported JPEG pixel format {:?}",
+                format
+            )))
+        }
+    };
+    let values = pixels.iter().map(|&c| c as f64 / 255.0).collect();
+    Ok((info.width as usize, info.height as usize, channels, values))
+}
+
+// Radiance HDR (RGBE)：文本的文件头以空行结束，之后是形如 "-Y 高 +X 宽" 的尺寸，
+// 然后从上到下是每行的像素，每个像素为共用指数的 (r, g, b, e)，一行可以整体游程编码
+fn decode_hdr(bytes: &[u8]) -> io::Result<(usize, usize, usize, Vec<f64>)> {
+    let truncated = || invalid_data(String::from("truncated HDR image"));
+    let mut lines = bytes.split(|&b| b == b'\n');
+    let mut offset = 0;
+    let mut next_line = || {
+        let line = lines.next()?;
+        offset += line.len() + 1;
+        Some(String::from_utf8_lossy(line).into_owned())
+    };
+    loop {
+        let line = next_line().ok_or_else(truncated)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported HDR format {}", format)));
+            }
+        }
+    }
+    let size = next_line().ok_or_else(truncated)?;
+    let (height, width) = match size.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse().ok(), width.parse().ok()),
+        _ => (None, None),
+    };
+    let (height, width): (usize, usize) = match (height, width) {
+        (Some(height), Some(width)) if width > 0 && height > 0 => (height, width),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported HDR image size `{}`",
+                size
+            )))
+        }
+    };
+
+    let mut data = bytes.get(offset..).unwrap_or_default().iter().copied();
+    let mut next = || data.next().ok_or_else(truncated);
+    let mut values = Vec::new();
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let first = [next()?, next()?, next()?, next()?];
+        if first[0] == 2 && first[1] == 2 && (8..0x8000).contains(&width) {
+            // 四个分量依次游程编码：大于 128 的计数后跟一个重复的值，否则后跟计数个值
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = next()? as usize;
+                    let (count, run) = if count > 128 {
+                        (count - 128, true)
+                    } else {
+                        (count, false)
+                    };
+                    if count == 0 || x + count > width {
+                        return Err(invalid_data(String::from("corrupt HDR scanline")));
+                    }
+                    let value = if run { next()? } else { 0 };
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = if run { value } else { next()? };
+                    }
+                    x += count;
+                }
+            }
+        } else {
+            scanline[0] = first;
+            for pixel in &mut scanline[1..] {
+                *pixel = [next()?, next()?, next()?, next()?];
+            }
+        }
+        for &[r, g, b, e] in &scanline {
+            let scale = if e == 0 {
+                0.0
+            } else {
+                2f64.powi(e as i32 - 136)
+            };
+            values.extend([r, g, b].iter().map(|&c| c as f64 * scale));
+        }
+    }
+    Ok((width, height, 3, values))
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &vec3::Point3) -> vec3::Color {
+        // 图片的第一行对应 v = 1
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - tx) * self.texel(x0, y0) + tx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * self.texel(x0, y0 + 1) + tx * self.texel(x0 + 1, y0 + 1);
+        (1.0 - ty) * top + ty * bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_image_texture() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+        }
+        let texture = ImageTexture::decode(&bytes, true).unwrap();
+        let p = vec3::Point3::fill(0.0);
+        assert_eq!(texture.value(0.25, 0.5, &p).to_array(), [1.0, 0.0, 0.0]);
+        assert_eq!(texture.value(0.75, 0.5, &p).to_array(), [0.0, 0.0, 1.0]);
+        // 平铺时两侧相接
+        assert_eq!(texture.value(1.0, 0.5, &p).to_array(), [0.5, 0.0, 0.5]);
+
+        assert!(ImageTexture::decode(b"GIF89a", true).is_err());
+
+        // 不压缩的 HDR 图片，127 / 256 * 2 = 0.9921875，值不按 sRGB 转换
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        hdr.extend_from_slice(&[127, 0, 0, 129, 0, 0, 0, 0]);
+        let texture = ImageTexture::decode(&hdr, true).unwrap();
+        assert_eq!(
+            texture.value(0.25, 0.5, &p).to_array(),
+            [0.9921875, 0.0, 0.0]
+        );
+        assert_eq!(texture.value(0.75, 0.5, &p).to_array(), [0.0; 3]);
+
+        // 游程编码的一行：8 个相同的像素
+        let mut hdr = b"#?RGBE\n\n-Y 1 +X 8\n".to_vec();
+        hdr.extend_from_slice(&[2, 2, 0, 8]);
+        for value in [0, 127, 0, 129] {
+            hdr.extend_from_slice(&[128 + 8, value]);
+        }
+        let texture = ImageTexture::decode(&hdr, false).unwrap();
+        assert_eq!(texture.average().to_array(), [0.0, 0.9921875, 0.0]);
+        hdr.pop();
+        assert!(ImageTexture::decode(&hdr, false).is_err());
+    }
+}
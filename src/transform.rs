@@ -0,0 +1,225 @@
+use std::ops;
+
+use crate::vec3;
+
+// 4×4 仿射变换矩阵，按行存储，作用于列向量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Transform {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Transform { m }
+    }
+
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Transform { m }
+    }
+
+    pub fn translate(delta: vec3::Vec3) -> Self {
+        Transform::new([
+            [1.0, 0.0, 0.0, delta.x()],
+            [0.0, 1.0, 0.0, delta.y()],
+            [0.0, 0.0, 1.0, delta.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(s: vec3::Vec3) -> Self {
+        Transform::new([
+            [s.x(), 0.0, 0.0, 0.0],
+            [0.0, s.y(), 0.0, 0.0],
+            [0.0, 0.0, s.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // 绕 axis 旋转 degrees 度
+    pub fn rotate(degrees: f64, axis: vec3::Vec3) -> Self {
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Transform::new([
+            [
+                a.x() * a.x() + (1.0 - a.x() * a.x()) * cos,
+                a.x() * a.y() * (1.0 - cos) - a.z() * sin,
+                a.x() * a.z() * (1.0 - cos) + a.y() * sin,
+                0.0,
+            ],
+            [
+                a.x() * a.y() * (1.0 - cos) + a.z() * sin,
+                a.y() * a.y() + (1.0 - a.y() * a.y()) * cos,
+                a.y() * a.z() * (1.0 - cos) - a.x() * sin,
+                0.0,
+            ],
+            [
+                a.x() * a.z() * (1.0 - cos) - a.y() * sin,
+                a.y() * a.z() * (1.0 - cos) + a.x() * sin,
+                a.z() * a.z() + (1.0 - a.z() * a.z()) * cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // 单位四元数 [x, y, z, w] 表示的旋转
+    pub fn quaternion(q: [f64; 4]) -> Self {
+        let [x, y, z, w] = q;
+        Transform::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Transform { m }
+    }
+
+    // 高斯-约当消元求逆，矩阵奇异时返回 None
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Transform::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-300 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                let factor = a[row][col];
+                if row != col && factor != 0.0 {
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+        Some(Transform { m: inv })
+    }
+
+    pub fn point(&self, p: vec3::Point3) -> vec3::Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        if w == 1.0 {
+            vec3::Vec3(x, y, z)
+        } else {
+            vec3::Vec3(x, y, z) / w
+        }
+    }
+
+    pub fn vector(&self, v: vec3::Vec3) -> vec3::Vec3 {
+        let m = &self.m;
+        vec3::Vec3(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    // 法向量按逆转置矩阵变换，结果未单位化
+    pub fn normal(&self, n: vec3::Vec3) -> vec3::Vec3 {
+        match self.inverse() {
+            Some(inverse) => inverse.transpose().vector(n),
+            None => n,
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+// Transform * Transform，先应用右侧的变换
+impl ops::Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Transform { m }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn assert_near(a: vec3::Vec3, b: vec3::Vec3) {
+        assert!((a - b).length() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_transform() {
+        let t = Transform::translate(vec3::Vec3(1.0, 2.0, 3.0))
+            * Transform::rotate(90.0, vec3::Vec3(0.0, 0.0, 1.0))
+            * Transform::scale(vec3::Vec3(2.0, 2.0, 2.0));
+        assert_near(
+            t.point(vec3::Vec3(1.0, 0.0, 0.0)),
+            vec3::Vec3(1.0, 4.0, 3.0),
+        );
+        assert_near(
+            t.vector(vec3::Vec3(1.0, 0.0, 0.0)),
+            vec3::Vec3(0.0, 2.0, 0.0),
+        );
+
+        let inverse = t.inverse().unwrap();
+        assert_near(
+            inverse.point(t.point(vec3::Vec3(0.3, -2.0, 5.0))),
+            vec3::Vec3(0.3, -2.0, 5.0),
+        );
+        assert!(Transform::scale(vec3::Vec3(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+
+        let squash = Transform::scale(vec3::Vec3(1.0, 0.5, 1.0));
+        let n = squash.normal(vec3::Vec3(1.0, 1.0, 0.0));
+        assert_near(n, vec3::Vec3(1.0, 2.0, 0.0));
+    }
+}
@@ -1,9 +1,30 @@
-pub fn random() -> f64 {
-    rand::random::<f64>()
+// 可设定种子的伪随机数生成器 (SplitMix64)，相同的种子总是给出相同的序列
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
 }
 
-pub fn random_in(min: f64, max: f64) -> f64 {
-    min + (max - min) * random()
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // [0, 1) 上的均匀分布
+    pub fn random(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn random_in(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.random()
+    }
 }
 
 pub fn clamp(num: f64, min: f64, max: f64) -> f64 {
@@ -32,6 +32,22 @@ impl Vec3 {
         self.2
     }
 
+    pub fn min(&self, v: Vec3) -> Vec3 {
+        Vec3(self.0.min(v.0), self.1.min(v.1), self.2.min(v.2))
+    }
+
+    pub fn max(&self, v: Vec3) -> Vec3 {
+        Vec3(self.0.max(v.0), self.1.max(v.1), self.2.max(v.2))
+    }
+
+    pub fn to_array(&self) -> [f64; 3] {
+        [self.0, self.1, self.2]
+    }
+
+    pub fn to_f32_array(&self) -> [f32; 3] {
+        [self.0 as f32, self.1 as f32, self.2 as f32]
+    }
+
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
     }
@@ -58,57 +74,18 @@ impl Vec3 {
         *self / self.length()
     }
 
-    pub fn random() -> Vec3 {
-        Vec3(utils::random(), utils::random(), utils::random())
+    pub fn random(rng: &mut utils::Rng) -> Vec3 {
+        Vec3(rng.random(), rng.random(), rng.random())
     }
 
-    pub fn random_in(min: f64, max: f64) -> Vec3 {
+    pub fn random_in(rng: &mut utils::Rng, min: f64, max: f64) -> Vec3 {
         Vec3(
-            utils::random_in(min, max),
-            utils::random_in(min, max),
-            utils::random_in(min, max),
+            rng.random_in(min, max),
+            rng.random_in(min, max),
+            rng.random_in(min, max),
         )
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
-        loop {
-            let p = Vec3::random_in(-1.0, 1.0);
-            if p.length_squared() >= 1.0 {
-                continue;
-            } else {
-                return p;
-            }
-        }
-    }
-
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::unit_vector(&Vec3::random_in_unit_sphere())
-    }
-
-    pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
-        let in_unit_sphere = Vec3::random_in_unit_sphere();
-        if in_unit_sphere.dot(*normal) > 0.0 {
-            in_unit_sphere
-        } else {
-            -in_unit_sphere
-        }
-    }
-
-    pub fn random_in_unit_disk() -> Vec3 {
-        loop {
-            let p = Vec3(
-                utils::random_in(-1.0, 1.0),
-                utils::random_in(-1.0, 1.0),
-                0.0,
-            );
-            if p.length_squared() >= 1.0 {
-                continue;
-            } else {
-                return p;
-            }
-        }
-    }
-
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
         self.0.abs() < s && self.1.abs() < s && self.2.abs() < s
@@ -131,7 +108,11 @@ impl Vec3 {
 }
 
 impl Color {
-    pub fn write_color<T: io::Write>(&self, out: &mut T, samples_per_pixel: i32) -> io::Result<()> {
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
+    pub fn to_rgb8(&self, samples_per_pixel: i32) -> [u8; 3] {
         let mut r = self.r();
         let mut g = self.g();
         let mut b = self.b();
@@ -142,12 +123,16 @@ impl Color {
         g = (scale * g).sqrt();
         b = (scale * b).sqrt();
 
-        let s = format!(
-            "{} {} {}\n",
-            (256.0 * utils::clamp(r, 0.0, 0.999)) as i32,
-            (256.0 * utils::clamp(g, 0.0, 0.999)) as i32,
-            (256.0 * utils::clamp(b, 0.0, 0.999)) as i32
-        );
+        [
+            (256.0 * utils::clamp(r, 0.0, 0.999)) as u8,
+            (256.0 * utils::clamp(g, 0.0, 0.999)) as u8,
+            (256.0 * utils::clamp(b, 0.0, 0.999)) as u8,
+        ]
+    }
+
+    pub fn write_color<T: io::Write>(&self, out: &mut T, samples_per_pixel: i32) -> io::Result<()> {
+        let [r, g, b] = self.to_rgb8(samples_per_pixel);
+        let s = format!("{} {} {}\n", r, g, b);
         out.write_all(s.as_bytes())?;
         Ok(())
     }
@@ -251,6 +236,26 @@ impl ops::DivAssign<f64> for Vec3 {
     }
 }
 
+impl From<[f32; 3]> for Vec3 {
+    fn from(v: [f32; 3]) -> Vec3 {
+        Vec3(v[0] as f64, v[1] as f64, v[2] as f64)
+    }
+}
+
+// Vec3[i]
+impl ops::Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, i: usize) -> &f64 {
+        match i {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => panic!("Vec3 index out of range: {}", i),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 